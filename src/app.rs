@@ -1,2 +1,37 @@
 mod render;
-pub use render::render;
+mod dump;
+mod layout;
+mod get;
+mod validate;
+mod cameras;
+mod benchmark;
+pub use render::{render, RenderOptions};
+pub use dump::dump;
+pub use layout::layout;
+pub use get::get;
+pub use validate::validate;
+pub use cameras::cameras;
+pub use benchmark::benchmark;
+
+use std::path::Path;
+use crate::camera::CameraDb;
+use crate::stream::ByteStream;
+
+// `-` reads the file from standard input.
+fn open(input_path: impl AsRef<Path>) -> std::io::Result<ByteStream> {
+  let input_path = input_path.as_ref();
+  if input_path == Path::new("-") {
+    ByteStream::from_stdin()
+  } else {
+    ByteStream::open(input_path)
+  }
+}
+
+// Built-in and user camera databases, then `extra` over them.
+fn load_cameras(extra: Option<impl AsRef<Path>>) -> anyhow::Result<CameraDb> {
+  let mut db = CameraDb::load()?;
+  if let Some(extra) = extra {
+    db.merge_file(extra)?;
+  }
+  Ok(db)
+}
@@ -0,0 +1,49 @@
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use log::{info, warn};
+use crate::demosaic::{self, Algorithm};
+use crate::img::Scale;
+use crate::raw::{self, DecodeOptions};
+use crate::tiff;
+
+// Decodes a file once, then times each demosaicing algorithm on its default crop.
+pub fn benchmark(
+  input_path: impl AsRef<Path>,
+  camera_db: Option<PathBuf>,
+  algorithms: &[Algorithm],
+  runs: usize,
+  threads: usize,
+) -> anyhow::Result<()> {
+  let mut stream = super::open(input_path)?;
+  let mut parser = tiff::Parser::new(&mut stream);
+  let tiff = parser.parse()?;
+  let decode_options = DecodeOptions {
+    threads,
+    cameras: Arc::new(super::load_cameras(camera_db.as_ref())?),
+    scale: Scale::Full,
+  };
+  let mut detection = raw::detect(&mut stream, &tiff, &decode_options)?;
+  info!("Decoder: {}", detection.describe());
+  let img = detection.decoder.decode(None)?.cropped();
+  let pool = rayon::ThreadPoolBuilder::new()
+    .num_threads(threads)
+    .build()?;
+  println!("{}x{}, {} run(s), {} thread(s)", img.width(), img.height(), runs.max(1), pool.current_num_threads());
+  println!("{:<10} {:>10} {:>10} {:>8}", "algorithm", "best [ms]", "mean [ms]", "MP/s");
+  let cfa = img.cfa()?;
+  for algorithm in algorithms {
+    let demosaicer = algorithm.demosaicer();
+    if !demosaicer.supports(&cfa) {
+      warn!("Skipping {}: it doesn't support the {} CFA", demosaicer.name(), cfa);
+      continue;
+    }
+    let timing = pool.install(|| demosaic::benchmark(&img, *algorithm, runs))?;
+    println!(
+      "{:<10} {:>10.1} {:>10.1} {:>8.1}",
+      timing.algorithm,
+      timing.best.as_secs_f64() * 1e3,
+      timing.mean.as_secs_f64() * 1e3,
+      timing.megapixels_per_second);
+  }
+  Ok(())
+}
@@ -0,0 +1,57 @@
+use std::path::Path;
+use crate::camera::Camera;
+
+// Lists camera database entries, or the merged entry for one camera.
+pub fn cameras(
+  camera_db: Option<impl AsRef<Path>>,
+  make: Option<&str>,
+  model: Option<&str>,
+  mode: Option<&str>,
+) -> anyhow::Result<()> {
+  let db = super::load_cameras(camera_db)?;
+  match (make, model) {
+    (Some(make), Some(model)) => {
+      let Some(camera) = db.lookup(make, model, mode) else {
+        return Err(anyhow::Error::msg(format!("{} {} is not in the camera database", make, model)));
+      };
+      print_camera(&camera);
+    }
+    _ => {
+      let cameras = db.cameras().iter()
+        .filter(|it| make.is_none_or(|make| it.make.eq_ignore_ascii_case(make)));
+      for camera in cameras {
+        print_camera(camera);
+        println!();
+      }
+    }
+  }
+  Ok(())
+}
+
+fn print_camera(camera: &Camera) {
+  println!("make: {}", camera.make);
+  println!("model: {}", camera.model.as_deref().unwrap_or("*"));
+  println!("mode: {}", camera.mode.as_deref().unwrap_or("*"));
+  println!("supported: {}", camera.is_supported());
+  if let Some(crop) = &camera.crop {
+    println!("crop: {}x{}+{}+{}", crop.width, crop.height, crop.x, crop.y);
+  }
+  for area in camera.masked_areas.iter().flatten() {
+    println!("masked area: {}x{}+{}+{}", area.width, area.height, area.x, area.y);
+  }
+  if let Some(black) = &camera.black_levels {
+    println!("black levels: {:?}", black);
+  }
+  if let Some(white) = camera.white_level {
+    println!("white level: {}", white);
+  }
+  if let Some(cfa) = &camera.cfa {
+    println!("cfa: {} ({}x{})", cfa.colors, cfa.width, cfa.height);
+  }
+  if let Some(m) = &camera.color_matrix {
+    println!("color matrix: {:?}", m);
+  }
+  for (key, value) in &camera.hints {
+    println!("hint {}: {}", key, value);
+  }
+}
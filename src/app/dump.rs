@@ -0,0 +1,15 @@
+use std::path::Path;
+use log::info;
+use crate::tiff;
+
+pub fn dump(input_path: impl AsRef<Path>, output_dir: impl AsRef<Path>) -> anyhow::Result<()> {
+  let mut stream = super::open(input_path)?;
+  let mut parser = tiff::Parser::new(&mut stream);
+  let tiff = parser.parse()?;
+  let manifest = tiff::dumper::Dumper::new(&mut stream, &tiff, &output_dir).dump()?;
+  info!(
+    "Dumped {} blocks into {}",
+    manifest.blocks.len(),
+    output_dir.as_ref().display());
+  Ok(())
+}
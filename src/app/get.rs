@@ -0,0 +1,19 @@
+use std::path::Path;
+use crate::tiff;
+use crate::tiff::query::Query;
+
+pub fn get(input_path: impl AsRef<Path>, query: &str) -> anyhow::Result<()> {
+  let query = Query::parse(query)?;
+  let mut stream = super::open(input_path)?;
+  let mut parser = tiff::Parser::new(&mut stream);
+  let tiff = parser.parse()?;
+  let matches = query.select(&tiff);
+  if matches.is_empty() {
+    return Err(anyhow::Error::msg("No tags matched"));
+  }
+  for m in matches {
+    let value = m.value(&mut stream)?;
+    println!("{}/{} ({}) = {}", m.ifd_path, m.tag_name(), m.header.tag, value);
+  }
+  Ok(())
+}
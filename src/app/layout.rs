@@ -0,0 +1,44 @@
+use std::path::Path;
+use crate::tiff;
+use crate::tiff::layout::{Issue, Layout};
+
+pub fn layout(input_path: impl AsRef<Path>) -> anyhow::Result<()> {
+  let mut stream = super::open(input_path)?;
+  let mut parser = tiff::Parser::new(&mut stream);
+  let tiff = parser.parse()?;
+  let layout = Layout::analyze(&mut stream, &tiff)?;
+  println!("       start          end         size  kind       label");
+  for region in &layout.regions {
+    println!(
+      "{:>12} {:>12} {:>12}  {:<10} {}",
+      region.start,
+      region.end,
+      region.size(),
+      format!("{:?}", region.kind),
+      region.label);
+  }
+  println!("file size: {}", layout.file_size);
+  if layout.issues.is_empty() {
+    println!("No issues found.");
+    return Ok(());
+  }
+  println!("{} issue(s):", layout.issues.len());
+  for issue in &layout.issues {
+    match issue {
+      Issue::Overlap(a, b) => {
+        let (a, b) = (&layout.regions[*a], &layout.regions[*b]);
+        println!(
+          "  overlap: {} [{}, {}) and {} [{}, {})",
+          a.label, a.start, a.end, b.label, b.start, b.end);
+      }
+      Issue::PastEof(i) => {
+        let r = &layout.regions[*i];
+        println!("  past EOF: {} [{}, {})", r.label, r.start, r.end);
+      }
+      Issue::Gap { start, end } => {
+        println!("  unreferenced: [{}, {}) {} bytes", start, end, end - start);
+      }
+    }
+  }
+  Ok(())
+}
@@ -1,19 +1,216 @@
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 use crate::tiff;
-use crate::raw::{ArwDecoder, RawDecoder};
+use log::{debug, info, warn};
+use crate::color::{self, ColorSpace, ToneCurve, WhiteBalance};
+use crate::demosaic::{self, Algorithm};
+use crate::img::{BadPixelMap, CfaKind, RawImage, Rect, RgbImage, Scale};
+use crate::raw::{self, DecodeOptions};
 
-pub fn render(input_path: impl AsRef<Path>, output_path: impl AsRef<Path>) -> anyhow::Result<()>{
-  let mut stream = tiff::ByteStream::open(input_path)?;
+// Pixels decoded around `RenderOptions::crop` so demosaicing sees the same context as in a full render.
+// More than any algorithm's strip margin.
+const CROP_MARGIN: u32 = 16;
+
+#[derive(Clone, Debug, Default)]
+pub struct RenderOptions {
+  // Decoding threads. 0 uses every core.
+  pub threads: usize,
+  // Camera database merged over the built-in and user ones.
+  pub camera_db: Option<PathBuf>,
+  // Measure black levels in the optical black areas.
+  pub optical_black: bool,
+  // Also remove row and column banding measured there.
+  pub pattern_noise: bool,
+  // Find outliers among same-color neighbors. Differences above `bad_pixel_threshold` of the range count.
+  pub detect_bad_pixels: bool,
+  pub bad_pixel_threshold: f32,
+  // User map of known bad pixels, merged with the one from the file.
+  pub bad_pixel_map: Option<PathBuf>,
+  // Where to write the detected map for reuse with `bad_pixel_map`.
+  pub save_bad_pixel_map: Option<PathBuf>,
+  // Picked from the CFA if None: bilinear for Bayer, Markesteijn for X-Trans, generic otherwise.
+  pub demosaic: Option<Algorithm>,
+  // Keep the full sensor area instead of cropping to the default crop.
+  pub uncropped: bool,
+  // Reduced sizes skip demosaicing for Bayer sensors.
+  pub scale: Scale,
+  // Region to render in sensor pixels, instead of the default crop. Only the rows around it are decoded.
+  pub crop: Option<Rect>,
+  // Falls back to gray world when the file has no as-shot white balance. Rectangles are in sensor pixels.
+  pub white_balance: WhiteBalance,
+  // Primaries, white and transfer function of the output, whose ICC profile is embedded.
+  pub color_space: ColorSpace,
+  // Exposure compensation in EV, added to the file's BaselineExposure unless `ignore_baseline_exposure`.
+  pub exposure: f32,
+  pub ignore_baseline_exposure: bool,
+  pub tone_curve: ToneCurve,
+}
+
+pub fn render(input_path: impl AsRef<Path>, output_path: impl AsRef<Path>, options: &RenderOptions) -> anyhow::Result<()>{
+  let mut stream = super::open(input_path)?;
   let mut parser = tiff::Parser::new(&mut stream);
   let tiff = parser.parse()?;
   tiff.inspect();
-  //tiff::dumper::Dumper::new(&mut stream, &tiff).dump()?;
-  let mut decoder = ArwDecoder::new(&mut stream, &tiff);
-  if !decoder.is_acceptable() {
-    return Err(anyhow::Error::msg("This file is not ARW!"));
+  let decode_options = DecodeOptions {
+    threads: options.threads,
+    cameras: Arc::new(super::load_cameras(options.camera_db.as_ref())?),
+    scale: options.scale,
+  };
+  let mut detection = raw::detect(&mut stream, &tiff, &decode_options)?;
+  info!("Decoder: {}", detection.describe());
+  // Margins are in sensor pixels, so they grow with the scale to stay the same in the output.
+  let roi = options.crop.map(|it| it.expand(CROP_MARGIN.saturating_mul(options.scale.factor() as u32)));
+  let mut img = detection.decoder.decode(roi)?;
+  if let Some(crop) = options.crop {
+    if img.width() == 0 || img.height() == 0 {
+      return Err(anyhow::Error::msg(format!(
+        "Crop {},{},{},{} lies outside of the image", crop.x, crop.y, crop.width, crop.height)));
+    }
+    info!("Region of interest: {}x{} at ({}, {})", crop.width, crop.height, crop.x, crop.y);
+  }
+  if options.optical_black || options.pattern_noise {
+    if img.apply_optical_black(options.pattern_noise) {
+      info!("Black levels from optical black: {:?}", img.black_levels());
+    } else {
+      warn!("No optical black areas are known for this camera; keeping black levels {:?}", img.black_levels());
+    }
+  }
+  fix_bad_pixels(&mut img, options)?;
+  let multipliers = white_balance(&mut img, options, roi)?;
+  let img = if options.uncropped || options.crop.is_some() {
+    img
+  } else {
+    let area = img.visible_area();
+    info!("Crop: {}x{} at ({}, {})", area.width, area.height, area.x, area.y);
+    img.cropped()
+  };
+  let mut rgb = to_rgb(&img, options)?;
+  color_correct(&mut rgb, &img, &multipliers, &options.color_space)?;
+  let mut rgb = match (options.crop, roi) {
+    // The decoded region starts at `roi`; in the output, every pixel stands for `factor` sensor pixels.
+    (Some(crop), Some(roi)) => {
+      let factor = options.scale.factor() as u32;
+      rgb.cropped(&Rect {
+        x: (crop.x - roi.x) / factor,
+        y: (crop.y - roi.y) / factor,
+        width: crop.width / factor,
+        height: crop.height / factor,
+      })
+    }
+    _ => rgb,
+  };
+  tone_map(&mut rgb, &img, options);
+  info!("Output: {}x{}", rgb.width(), rgb.height());
+  rgb.save_to_file(output_path, false, &options.color_space)?;
+
+  Ok(())
+}
+
+// Applies `options.white_balance` before cropping, so the automatic modes see the whole visible area.
+// Returns the multipliers.
+fn white_balance(img: &mut RawImage, options: &RenderOptions, roi: Option<Rect>) -> anyhow::Result<Vec<f32>> {
+  let mode = match options.white_balance {
+    // From sensor pixels to the decoded region, decimated for quarter and eighth size.
+    WhiteBalance::Rect(rect) => {
+      let (ox, oy) = roi.map(|it| (it.x, it.y)).unwrap_or((0, 0));
+      let k = options.scale.decimation() as u32;
+      WhiteBalance::Rect(Rect {
+        x: rect.x.saturating_sub(ox) / k,
+        y: rect.y.saturating_sub(oy) / k,
+        width: (rect.width / k).max(2),
+        height: (rect.height / k).max(2),
+      })
+    }
+    mode => mode,
+  };
+  let multipliers = match color::multipliers(img, &mode) {
+    Ok(multipliers) => multipliers,
+    Err(err) if mode == WhiteBalance::AsShot => {
+      warn!("{}; using gray world", err);
+      color::multipliers(img, &WhiteBalance::GrayWorld)?
+    }
+    Err(err) => return Err(err),
+  };
+  info!("White balance: {:?}, multipliers: {:?}", mode, multipliers);
+  color::apply(img, &multipliers)?;
+  Ok(multipliers)
+}
+
+// Camera RGB to linear `space` through XYZ, for the white balance of `multipliers` the mosaic already has.
+fn color_correct(rgb: &mut RgbImage, img: &RawImage, multipliers: &[f32], space: &ColorSpace) -> anyhow::Result<()> {
+  let cfa = img.cfa()?;
+  if !cfa.is_rgb() {
+    warn!("No color matrices for the {} CFA; keeping camera colors", cfa);
+    return Ok(());
   }
-  let img = decoder.decode()?;
-  img.save_to_file(output_path, false)?;
+  let neutral = [0, 1, 2].map(|i| 1.0 / multipliers[i]);
+  let Some((camera_to_xyz, temperature)) = img.color().camera_to_xyz_d50(neutral) else {
+    warn!("No color matrix is known for this camera; keeping camera colors");
+    return Ok(());
+  };
+  let xyz_to_rgb = space.xyz_to_rgb();
+  let matrix = color::mul(&xyz_to_rgb, &color::mul(&camera_to_xyz, &color::diagonal(neutral)));
+  // Rows sum to the space's white as in dcraw, 1 for RGB spaces, so balanced white, clipped highlights
+  // included, stays white.
+  let white = color::mul_vec(&xyz_to_rgb, color::D50);
+  let matrix = [0, 1, 2].map(|r| {
+    let sum: f32 = matrix[r].iter().sum();
+    matrix[r].map(|it| it * white[r] / sum)
+  });
+  info!("Color: camera to {}, white at {:.0}K", space.name(), temperature);
+  debug!("Color matrix: {:?}", matrix);
+  color::transform(rgb, &matrix);
+  Ok(())
+}
+
+// Applies the exposure and tone curve of `options`, on linear values in the output space.
+fn tone_map(rgb: &mut RgbImage, img: &RawImage, options: &RenderOptions) {
+  let baseline = match options.ignore_baseline_exposure {
+    true => 0.0,
+    false => img.color().baseline_exposure.unwrap_or(0.0),
+  };
+  let exposure = baseline + options.exposure;
+  info!("Exposure: {:+.2} EV (baseline {:+.2} EV), tone curve: {}", exposure, baseline, options.tone_curve.describe());
+  if exposure != 0.0 || options.tone_curve != ToneCurve::None {
+    color::tone_map(rgb, &options.tone_curve, exposure, &options.color_space.transfer());
+  }
+}
 
+// Demosaics at full size. Reduced sizes take Bayer cells as pixels, and demosaic other patterns
+// before downscaling. The decoder has already decimated quarter and eighth size down to half.
+fn to_rgb(img: &RawImage, options: &RenderOptions) -> anyhow::Result<RgbImage> {
+  let cfa = img.cfa()?;
+  if options.scale != Scale::Full && cfa.kind() == CfaKind::Bayer {
+    info!("CFA: {}, scale: {} (superpixel)", cfa, options.scale.name());
+    return img.superpixel();
+  }
+  let algorithm = options.demosaic.unwrap_or_else(|| Algorithm::default_for(&cfa));
+  info!("CFA: {}, demosaic: {}", cfa, algorithm.demosaicer().name());
+  let rgb = demosaic::demosaic(img, algorithm)?;
+  Ok(match options.scale {
+    Scale::Full => rgb,
+    _ => rgb.downscaled(2),
+  })
+}
+
+// Interpolates known and detected bad pixels, before demosaicing.
+fn fix_bad_pixels(img: &mut RawImage, options: &RenderOptions) -> anyhow::Result<()> {
+  let mut map = img.bad_pixels().clone();
+  if let Some(path) = options.bad_pixel_map.as_ref() {
+    map.extend(&BadPixelMap::load(path)?);
+  }
+  if options.detect_bad_pixels || options.save_bad_pixel_map.is_some() {
+    let detected = img.detect_bad_pixels(&img.visible_area(), options.bad_pixel_threshold);
+    info!("Detected bad pixels: {}", detected.len());
+    if let Some(path) = options.save_bad_pixel_map.as_ref() {
+      detected.save(path)?;
+      info!("Saved bad pixel map: {}", path.display());
+    }
+    map.extend(&detected);
+  }
+  if !map.is_empty() {
+    let fixed = img.fix_bad_pixels(&map);
+    info!("Fixed bad pixels: {} of {}", fixed, map.len());
+  }
   Ok(())
 }
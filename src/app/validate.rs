@@ -0,0 +1,29 @@
+use std::path::Path;
+use crate::tiff;
+use crate::tiff::validator::{Profile, Severity, Validator};
+
+pub fn validate(input_path: impl AsRef<Path>, profile: Option<&str>, strict: bool) -> anyhow::Result<()> {
+  let mut stream = super::open(input_path)?;
+  let mut parser = tiff::Parser::new(&mut stream);
+  let tiff = parser.parse()?;
+  let mut validator = Validator::new(&mut stream, &tiff);
+  match profile {
+    None => {}
+    Some("tiff") => validator = validator.with_profile(Profile::Tiff),
+    Some("tiff-ep") => validator = validator.with_profile(Profile::TiffEp),
+    Some("dng") => validator = validator.with_profile(Profile::Dng),
+    Some(it) => return Err(anyhow::Error::msg(format!("Unknown profile: {}", it))),
+  }
+  println!("Profile: {:?}", validator.profile());
+  let findings = validator.validate()?;
+  for finding in &findings {
+    println!("{}", finding);
+  }
+  let threshold = Severity::threshold(strict);
+  let rejected = findings.iter().filter(|it| it.severity >= threshold).count();
+  if rejected > 0 {
+    return Err(anyhow::Error::msg(format!("{} finding(s) at or above {}", rejected, threshold)));
+  }
+  println!("OK");
+  Ok(())
+}
@@ -0,0 +1,268 @@
+/*
+Camera database with per-model quirks, in the spirit of rawspeed's cameras.xml.
+
+The built-in database is data/cameras.toml, see there for the format.
+*/
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use log::debug;
+use serde::Deserialize;
+use crate::img::Rect;
+use crate::tiff::{CFAPattern, CFAPatternDim};
+
+const BUILTIN: &str = include_str!("../data/cameras.toml");
+const ENV_CAMERAS: &str = "AG_CAMERAS";
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CfaOverride {
+  pub width: usize,
+  pub height: usize,
+  pub colors: String,
+}
+
+impl CfaOverride {
+  pub fn dim(&self) -> CFAPatternDim {
+    CFAPatternDim {
+      width: self.width,
+      height: self.height,
+    }
+  }
+
+  // [TIFF/EP] p.26: 0 = Red, 1 = Green, 2 = Blue, 3 = Cyan, 4 = Magenta, 5 = Yellow, 6 = White
+  pub fn pattern(&self) -> anyhow::Result<Vec<CFAPattern>> {
+    let pattern = self.colors.chars().map(|c| match c.to_ascii_uppercase() {
+      'R' => Ok(CFAPattern::R),
+      'G' => Ok(CFAPattern::G),
+      'B' => Ok(CFAPattern::B),
+      'C' => Ok(CFAPattern::Unknown(3)),
+      'M' => Ok(CFAPattern::Unknown(4)),
+      'Y' => Ok(CFAPattern::Unknown(5)),
+      'W' => Ok(CFAPattern::Unknown(6)),
+      c => Err(anyhow::Error::msg(format!("Unknown CFA color: {}", c))),
+    }).collect::<anyhow::Result<Vec<_>>>()?;
+    if pattern.len() != self.width * self.height {
+      return Err(anyhow::Error::msg(format!(
+        "CFA override has {} colors for {}x{}", pattern.len(), self.width, self.height)));
+    }
+    Ok(pattern)
+  }
+}
+
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Camera {
+  pub make: String,
+  pub model: Option<String>,
+  pub mode: Option<String>,
+  pub supported: Option<bool>,
+  pub crop: Option<Rect>,
+  pub masked_areas: Option<Vec<Rect>>,
+  pub black_levels: Option<Vec<u32>>,
+  pub white_level: Option<u32>,
+  pub cfa: Option<CfaOverride>,
+  pub color_matrix: Option<Vec<f64>>,
+  #[serde(default)]
+  pub hints: BTreeMap<String, toml::Value>,
+}
+
+impl Camera {
+  pub fn is_supported(&self) -> bool {
+    self.supported.unwrap_or(true)
+  }
+
+  pub fn hint(&self, key: &str) -> Option<&toml::Value> {
+    self.hints.get(key)
+  }
+
+  pub fn hint_u32(&self, key: &str) -> Option<u32> {
+    self.hint(key)?.as_integer().and_then(|it| u32::try_from(it).ok())
+  }
+
+  fn matches(&self, make: &str, model: &str, mode: Option<&str>) -> bool {
+    self.make.trim().eq_ignore_ascii_case(make.trim()) &&
+      self.model.as_deref().is_none_or(|it| it.trim() == model.trim()) &&
+      self.mode.as_deref().is_none_or(|it| Some(it) == mode)
+  }
+
+  // Model matches weigh more than mode matches.
+  fn specificity(&self) -> u8 {
+    (self.model.is_some() as u8) * 2 + (self.mode.is_some() as u8)
+  }
+
+  // Fields set in `other` replace ours.
+  fn overlay(&mut self, other: &Camera) {
+    if other.model.is_some() {
+      self.model = other.model.clone();
+    }
+    if other.mode.is_some() {
+      self.mode = other.mode.clone();
+    }
+    if other.supported.is_some() {
+      self.supported = other.supported;
+    }
+    if other.crop.is_some() {
+      self.crop = other.crop;
+    }
+    if other.masked_areas.is_some() {
+      self.masked_areas = other.masked_areas.clone();
+    }
+    if other.black_levels.is_some() {
+      self.black_levels = other.black_levels.clone();
+    }
+    if other.white_level.is_some() {
+      self.white_level = other.white_level;
+    }
+    if other.cfa.is_some() {
+      self.cfa = other.cfa.clone();
+    }
+    if other.color_matrix.is_some() {
+      self.color_matrix = other.color_matrix.clone();
+    }
+    self.hints.extend(other.hints.iter().map(|(k, v)| (k.clone(), v.clone())));
+  }
+
+  fn check(&self) -> anyhow::Result<()> {
+    if let Some(cfa) = &self.cfa {
+      cfa.pattern()?;
+    }
+    if let Some(m) = &self.color_matrix {
+      if m.len() != 9 {
+        return Err(anyhow::Error::msg(format!("color_matrix must have 9 values, but has {}", m.len())));
+      }
+    }
+    Ok(())
+  }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct CameraFile {
+  #[serde(default)]
+  camera: Vec<Camera>,
+}
+
+#[derive(Clone, Debug)]
+pub struct CameraDb {
+  // Later entries win over earlier ones with the same specificity.
+  cameras: Vec<Camera>,
+}
+
+impl Default for CameraDb {
+  fn default() -> Self {
+    Self::builtin()
+  }
+}
+
+impl CameraDb {
+  pub fn builtin() -> Self {
+    Self::parse(BUILTIN).expect("[BUG] The built-in camera database is broken")
+  }
+
+  pub fn parse(text: &str) -> anyhow::Result<Self> {
+    let file: CameraFile = toml::from_str(text)?;
+    for camera in &file.camera {
+      camera.check().map_err(|err| anyhow::Error::msg(format!(
+        "{} {}: {}", camera.make, camera.model.as_deref().unwrap_or("*"), err)))?;
+    }
+    Ok(Self {
+      cameras: file.camera,
+    })
+  }
+
+  // The built-in database, then the user's one from $AG_CAMERAS or the config directory.
+  pub fn load() -> anyhow::Result<Self> {
+    let mut db = Self::builtin();
+    if let Some(path) = user_db_path() {
+      debug!("Loading camera database: {}", path.display());
+      db.merge_file(&path)?;
+    }
+    Ok(db)
+  }
+
+  pub fn merge_file(&mut self, path: impl AsRef<Path>) -> anyhow::Result<()> {
+    let path = path.as_ref();
+    let text = std::fs::read_to_string(path)
+      .map_err(|err| anyhow::Error::msg(format!("{}: {}", path.display(), err)))?;
+    let other = Self::parse(&text)
+      .map_err(|err| anyhow::Error::msg(format!("{}: {}", path.display(), err)))?;
+    self.cameras.extend(other.cameras);
+    Ok(())
+  }
+
+  pub fn cameras(&self) -> &[Camera] {
+    &self.cameras
+  }
+
+  // Every matching entry merged from the least to the most specific one.
+  pub fn lookup(&self, make: &str, model: &str, mode: Option<&str>) -> Option<Camera> {
+    let mut matches: Vec<&Camera> = self.cameras.iter()
+      .filter(|it| it.matches(make, model, mode))
+      .collect();
+    if matches.is_empty() {
+      return None;
+    }
+    // Stable, so entries loaded later stay later.
+    matches.sort_by_key(|it| it.specificity());
+    let mut camera = Camera {
+      make: make.trim().to_string(),
+      ..Camera::default()
+    };
+    for m in matches {
+      camera.overlay(m);
+    }
+    Some(camera)
+  }
+}
+
+fn user_db_path() -> Option<PathBuf> {
+  if let Some(path) = std::env::var_os(ENV_CAMERAS) {
+    return Some(PathBuf::from(path));
+  }
+  let config_dir = std::env::var_os("XDG_CONFIG_HOME")
+    .map(PathBuf::from)
+    .or_else(|| std::env::var_os("HOME").map(|it| PathBuf::from(it).join(".config")))?;
+  let path = config_dir.join("ag").join("cameras.toml");
+  path.exists().then_some(path)
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  #[test]
+  fn test_builtin() {
+    let db = CameraDb::builtin();
+    let camera = db.lookup("SONY", "ILCE-7RM3", Some("ARW2")).expect("No camera");
+    assert_eq!(camera.hint_u32("arw_bits_per_pixel"), Some(8));
+    assert_eq!(camera.white_level, Some(4095));
+    assert_eq!(camera.color_matrix.as_ref().map(|it| it.len()), Some(9));
+    assert!(camera.is_supported());
+    assert!(!db.lookup("SONY", "ILCE-7RM3", Some("ARW1")).unwrap().is_supported());
+    assert!(db.lookup("Canon", "EOS R5", None).is_none());
+  }
+
+  #[test]
+  fn test_user_overrides() {
+    let mut db = CameraDb::builtin();
+    let user = CameraDb::parse(r#"
+      [[camera]]
+      make = "SONY"
+      model = "ILCE-7RM3"
+      white_level = 4000
+      cfa = { width = 2, height = 2, colors = "GRBG" }
+    "#).unwrap();
+    db.cameras.extend(user.cameras);
+    let camera = db.lookup("sony", "ILCE-7RM3 ", Some("ARW2")).unwrap();
+    assert_eq!(camera.white_level, Some(4000));
+    assert_eq!(camera.black_levels, Some(vec![128]));
+    assert!(matches!(camera.cfa.unwrap().pattern().unwrap()[0], CFAPattern::G));
+  }
+
+  #[test]
+  fn test_invalid() {
+    assert!(CameraDb::parse("[[camera]]\nmake = \"X\"\ncfa = { width = 2, height = 2, colors = \"RGB\" }").is_err());
+    assert!(CameraDb::parse("[[camera]]\nmake = \"X\"\nunknown = 1").is_err());
+  }
+}
@@ -0,0 +1,181 @@
+/*
+Color processing: white balance of the mosaic, the camera's color response from metadata mapping
+camera RGB to XYZ, the output color spaces with their ICC profiles, and tone mapping.
+
+Camera RGB here means the planes of an RGB CFA, 0 = R, 1 = G and 2 = B.
+
+References:
+- [DNG] Chapter 6, Mapping Camera Color Space to CIE XYZ Space
+- [ICC] ICC.1:2001-04, File Format for Color Profiles
+*/
+
+mod matrix;
+pub use matrix::*;
+mod temperature;
+pub use temperature::*;
+mod white_balance;
+pub use white_balance::*;
+mod adaptation;
+pub use adaptation::*;
+mod space;
+pub use space::*;
+mod icc;
+pub use icc::*;
+mod tone;
+pub use tone::*;
+
+use rayon::prelude::*;
+use crate::img::RgbImage;
+
+// Where the camera's matrices don't say, the illuminant is taken as D65.
+pub const DEFAULT_TEMPERATURE: f32 = 6504.0;
+// Temperature estimates closer than this end the search of `CameraColor::camera_to_xyz_d50`.
+const TEMPERATURE_TOLERANCE: f32 = 1.0;
+
+// Matrices measured under one calibration illuminant.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct IlluminantMatrix {
+  // Correlated color temperature of the calibration illuminant, in kelvin.
+  pub temperature: f32,
+  // DNG's ColorMatrix.
+  pub xyz_to_camera: Matrix3,
+  // DNG's ForwardMatrix: white-balanced camera RGB to XYZ (D50).
+  pub forward_matrix: Option<Matrix3>,
+  // DNG's CameraCalibration: the difference of this camera from the model's reference.
+  pub camera_calibration: Option<Matrix3>,
+}
+
+impl IlluminantMatrix {
+  pub fn new(temperature: f32, xyz_to_camera: Matrix3) -> Self {
+    Self {
+      temperature,
+      xyz_to_camera,
+      forward_matrix: None,
+      camera_calibration: None,
+    }
+  }
+}
+
+// Color metadata of a raw file, from the file or the camera database.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct CameraColor {
+  // Camera RGB of a neutral surface under the shot's illuminant, e.g. DNG's AsShotNeutral.
+  pub as_shot_neutral: Option<[f32; 3]>,
+  // DNG's AnalogBalance.
+  pub analog_balance: Option<[f32; 3]>,
+  // One or two calibrations, sorted by temperature.
+  pub matrices: Vec<IlluminantMatrix>,
+  // DNG's BaselineExposure: EV to add for a normal rendering.
+  pub baseline_exposure: Option<f32>,
+}
+
+impl CameraColor {
+  // Matrices for an illuminant of `temperature`, interpolated between the calibration illuminants
+  // linearly in inverse temperature as [DNG] p.80 describes. Forward matrices and calibrations
+  // only interpolate when both illuminants have them.
+  pub fn matrices_at(&self, temperature: f32) -> Option<IlluminantMatrix> {
+    let (first, last) = (self.matrices.first()?, self.matrices.last()?);
+    if temperature <= first.temperature || first.temperature >= last.temperature {
+      return Some(*first);
+    }
+    if temperature >= last.temperature {
+      return Some(*last);
+    }
+    let weight = (1.0 / temperature - 1.0 / last.temperature) / (1.0 / first.temperature - 1.0 / last.temperature);
+    let both = |a: Option<Matrix3>, b: Option<Matrix3>| Some(blend(&a?, &b?, weight));
+    Some(IlluminantMatrix {
+      temperature,
+      xyz_to_camera: blend(&first.xyz_to_camera, &last.xyz_to_camera, weight),
+      forward_matrix: both(first.forward_matrix, last.forward_matrix),
+      camera_calibration: both(first.camera_calibration, last.camera_calibration),
+    })
+  }
+
+  // AnalogBalance * CameraCalibration.
+  fn balance(&self, matrices: &IlluminantMatrix) -> Matrix3 {
+    let analog = diagonal(self.analog_balance.unwrap_or([1.0; 3]));
+    mul(&analog, &matrices.camera_calibration.unwrap_or(IDENTITY))
+  }
+
+  // XYZ to camera for an illuminant of `temperature`, with the analog balance and camera calibration.
+  pub fn xyz_to_camera(&self, temperature: f32) -> Option<Matrix3> {
+    let matrices = self.matrices_at(temperature)?;
+    Some(mul(&self.balance(&matrices), &matrices.xyz_to_camera))
+  }
+
+  // Camera RGB to XYZ (D50) when `neutral` is the camera RGB of white, after [DNG] chapter 6, and the
+  // temperature of that white. The temperature picking the matrices is found by iterating from D50.
+  pub fn camera_to_xyz_d50(&self, neutral: [f32; 3]) -> Option<(Matrix3, f32)> {
+    let mut temperature = 5003.0;
+    let mut white = D50;
+    for _ in 0..20 {
+      white = mul_vec(&invert(&self.xyz_to_camera(temperature)?)?, neutral);
+      let (found, _) = xy_to_temperature(xyz_to_xy(white));
+      let done = (found - temperature).abs() < TEMPERATURE_TOLERANCE;
+      temperature = found;
+      if done {
+        break;
+      }
+    }
+    let matrices = self.matrices_at(temperature)?;
+    let balance = self.balance(&matrices);
+    let matrix = match matrices.forward_matrix {
+      // ForwardMatrix maps white-balanced reference camera RGB to XYZ (D50).
+      Some(forward) => {
+        let inverse = invert(&balance)?;
+        let reference = mul_vec(&inverse, neutral);
+        let white_balance = diagonal(reference.map(|it| if it > 0.0 { 1.0 / it } else { 1.0 }));
+        mul(&forward, &mul(&white_balance, &inverse))
+      }
+      None => {
+        let camera_to_xyz = invert(&mul(&balance, &matrices.xyz_to_camera))?;
+        mul(&adaptation(white, D50), &camera_to_xyz)
+      }
+    };
+    Some((matrix, temperature))
+  }
+}
+
+// Multiplies every pixel by `matrix`.
+pub fn transform(img: &mut RgbImage, matrix: &Matrix3) {
+  img.data_mut().par_chunks_mut(3).for_each(|px| {
+    let out = mul_vec(matrix, [px[0], px[1], px[2]]);
+    px.copy_from_slice(&out);
+  });
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  #[test]
+  fn test_camera_to_xyz() {
+    // A camera seeing XYZ under D65, and twice as sensitive to Z under A.
+    let mut color = CameraColor::default();
+    color.matrices.push(IlluminantMatrix::new(2856.0, diagonal([1.0, 1.0, 2.0])));
+    color.matrices.push(IlluminantMatrix::new(6504.0, IDENTITY));
+    let halfway = color.xyz_to_camera(1.0 / ((1.0 / 2856.0 + 1.0 / 6504.0) / 2.0)).unwrap();
+    assert!((halfway[2][2] - 1.5).abs() < 1e-4);
+
+    // The white found is the neutral itself, which maps to D50.
+    let (matrix, temperature) = color.camera_to_xyz_d50(D65).unwrap();
+    assert!((temperature - 6504.0).abs() < 50.0, "{}", temperature);
+    let white = mul_vec(&matrix, D65);
+    for (w, d) in white.iter().zip(D50) {
+      assert!((w - d).abs() < 1e-3, "{:?}", white);
+    }
+
+    // With forward matrices, white-balanced neutral maps to the forward matrix's white.
+    for it in color.matrices.iter_mut() {
+      it.forward_matrix = Some(diagonal(D50));
+    }
+    let (matrix, _) = color.camera_to_xyz_d50([0.5, 1.0, 0.8]).unwrap();
+    let white = mul_vec(&matrix, [0.5, 1.0, 0.8]);
+    for (w, d) in white.iter().zip(D50) {
+      assert!((w - d).abs() < 1e-3, "{:?}", white);
+    }
+
+    let (t, tint) = xy_to_temperature(temperature_to_xy(4000.0, 5.0));
+    assert!((t - 4000.0).abs() < 2.0 && (tint - 5.0).abs() < 0.1, "{} {}", t, tint);
+  }
+}
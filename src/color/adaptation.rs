@@ -0,0 +1,24 @@
+// Chromatic adaptation with the Bradford transform, as the DNG SDK and ICC profiles use it.
+
+use super::{diagonal, invert, mul, mul_vec, Matrix3};
+
+// White point of XYZ as DNG and ICC profiles use it, with Y = 1.
+pub const D50: [f32; 3] = [0.96422, 1.0, 0.82521];
+// White point of sRGB and most display spaces.
+pub const D65: [f32; 3] = [0.95047, 1.0, 1.08883];
+
+// XYZ to the Bradford cone response.
+const BRADFORD: Matrix3 = [
+  [0.8951, 0.2664, -0.1614],
+  [-0.7502, 1.7135, 0.0367],
+  [0.0389, -0.0685, 1.0296],
+];
+
+// XYZ under the white `from` to XYZ under the white `to`.
+pub fn adaptation(from: [f32; 3], to: [f32; 3]) -> Matrix3 {
+  let (from, to) = (mul_vec(&BRADFORD, from), mul_vec(&BRADFORD, to));
+  let scale = diagonal([0, 1, 2].map(|i| to[i] / from[i].max(f32::EPSILON)));
+  // The Bradford matrix is well conditioned; its inverse always exists.
+  let inverse = invert(&BRADFORD).expect("[BUG] Bradford matrix is singular!");
+  mul(&inverse, &mul(&scale, &BRADFORD))
+}
@@ -0,0 +1,154 @@
+// ICC v2 matrix/TRC display profiles describing a `ColorSpace`, for embedding in output images.
+//
+// Version 2 profiles are read by everything that reads profiles. The colorants are adapted to D50 and
+// the media white point is D50 too, as version 4 requires, so relative and absolute rendering agree.
+//
+// References:
+// - ICC.1:2001-04, File Format for Color Profiles (version 2.4)
+
+use super::{ColorSpace, Transfer, D50};
+
+const HEADER_SIZE: usize = 128;
+const VERSION: u32 = 0x0210_0000;
+// Entries of sampled tone curves.
+const CURVE_POINTS: usize = 1024;
+const COPYRIGHT: &str = "No copyright, use freely";
+
+// The profile of `space`.
+pub fn icc_profile(space: &ColorSpace) -> Vec<u8> {
+  let rgb_to_xyz = space.rgb_to_xyz();
+  let colorant = |c: usize| xyz_tag([rgb_to_xyz[0][c], rgb_to_xyz[1][c], rgb_to_xyz[2][c]]);
+  let curve = curve_tag(&space.transfer());
+  let tags: Vec<(&[u8; 4], Vec<u8>)> = vec![
+    (b"desc", desc_tag(space.description())),
+    (b"cprt", text_tag(COPYRIGHT)),
+    (b"wtpt", xyz_tag(D50)),
+    (b"rXYZ", colorant(0)),
+    (b"gXYZ", colorant(1)),
+    (b"bXYZ", colorant(2)),
+    (b"rTRC", curve.clone()),
+    (b"gTRC", curve.clone()),
+    (b"bTRC", curve),
+  ];
+
+  let mut data = Vec::<u8>::new();
+  let mut table = Vec::<u8>::new();
+  let data_start = HEADER_SIZE + 4 + tags.len() * 12;
+  table.extend((tags.len() as u32).to_be_bytes());
+  for (signature, tag) in &tags {
+    // Tags start on 4-byte boundaries.
+    data.resize(data.len().next_multiple_of(4), 0);
+    table.extend(*signature);
+    table.extend(((data_start + data.len()) as u32).to_be_bytes());
+    table.extend((tag.len() as u32).to_be_bytes());
+    data.extend(tag);
+  }
+  data.resize(data.len().next_multiple_of(4), 0);
+
+  let mut profile = header((data_start + data.len()) as u32);
+  profile.extend(table);
+  profile.extend(data);
+  profile
+}
+
+fn header(size: u32) -> Vec<u8> {
+  let mut header = Vec::<u8>::with_capacity(HEADER_SIZE);
+  header.extend(size.to_be_bytes());
+  // Preferred CMM.
+  header.extend([0; 4]);
+  header.extend(VERSION.to_be_bytes());
+  header.extend(b"mntr");
+  header.extend(b"RGB ");
+  header.extend(b"XYZ ");
+  // Creation date and time, left out so that output is reproducible.
+  header.extend([0; 12]);
+  header.extend(b"acsp");
+  // Platform, flags, manufacturer, model, attributes.
+  header.extend([0; 4 + 4 + 4 + 4 + 8]);
+  // Perceptual rendering intent.
+  header.extend(0_u32.to_be_bytes());
+  header.extend(xyz_number(D50));
+  // Creator, then reserved bytes.
+  header.resize(HEADER_SIZE, 0);
+  header
+}
+
+fn s15_fixed16(v: f32) -> [u8; 4] {
+  ((v as f64 * 65536.0).round() as i32).to_be_bytes()
+}
+
+fn xyz_number(xyz: [f32; 3]) -> Vec<u8> {
+  xyz.iter().flat_map(|it| s15_fixed16(*it)).collect()
+}
+
+fn xyz_tag(xyz: [f32; 3]) -> Vec<u8> {
+  let mut tag = b"XYZ \0\0\0\0".to_vec();
+  tag.extend(xyz_number(xyz));
+  tag
+}
+
+fn text_tag(text: &str) -> Vec<u8> {
+  let mut tag = b"text\0\0\0\0".to_vec();
+  tag.extend(text.as_bytes());
+  tag.push(0);
+  tag
+}
+
+// textDescriptionType: ASCII, with empty Unicode and ScriptCode descriptions.
+fn desc_tag(text: &str) -> Vec<u8> {
+  let mut tag = b"desc\0\0\0\0".to_vec();
+  tag.extend((text.len() as u32 + 1).to_be_bytes());
+  tag.extend(text.as_bytes());
+  tag.push(0);
+  // Unicode language code and count.
+  tag.extend([0; 8]);
+  // ScriptCode code, count and its 67 bytes.
+  tag.extend([0; 2 + 1 + 67]);
+  tag
+}
+
+// From encoded to linear values: the identity, a gamma in u8Fixed8Number, or a sampled curve.
+fn curve_tag(transfer: &Transfer) -> Vec<u8> {
+  let mut tag = b"curv\0\0\0\0".to_vec();
+  match transfer {
+    Transfer::Linear => tag.extend(0_u32.to_be_bytes()),
+    Transfer::Gamma(gamma) => {
+      tag.extend(1_u32.to_be_bytes());
+      tag.extend(((gamma * 256.0).round() as u16).to_be_bytes());
+    }
+    _ => {
+      tag.extend((CURVE_POINTS as u32).to_be_bytes());
+      for i in 0..CURVE_POINTS {
+        let v = transfer.decode(i as f32 / (CURVE_POINTS - 1) as f32);
+        tag.extend(((v.clamp(0.0, 1.0) * 65535.0).round() as u16).to_be_bytes());
+      }
+    }
+  }
+  tag
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  #[test]
+  fn test_icc_profile() {
+    let u32_at = |data: &[u8], at: usize| u32::from_be_bytes(data[at..at + 4].try_into().unwrap());
+    for space in ColorSpace::ALL {
+      let profile = icc_profile(space);
+      assert_eq!(u32_at(&profile, 0) as usize, profile.len());
+      assert_eq!(&profile[36..40], b"acsp");
+      assert_eq!(u32_at(&profile, HEADER_SIZE), 9);
+      // Every tag lies inside the profile, aligned, and starts with its type.
+      for i in 0..9 {
+        let entry = HEADER_SIZE + 4 + i * 12;
+        let (offset, size) = (u32_at(&profile, entry + 4) as usize, u32_at(&profile, entry + 8) as usize);
+        assert!(offset.is_multiple_of(4) && offset + size <= profile.len());
+        let kind = &profile[offset..offset + 4];
+        assert!([b"desc", b"text", b"XYZ ", b"curv"].iter().any(|it| kind == *it), "{:?}", kind);
+      }
+    }
+    // Adobe RGB's gamma is exact in u8Fixed8Number: 2 + 51/256.
+    assert_eq!(curve_tag(&ColorSpace::AdobeRgb.transfer())[12..14], [2, 51]);
+  }
+}
@@ -0,0 +1,49 @@
+// 3x3 matrices, row-major, applied to column vectors.
+
+pub type Matrix3 = [[f32; 3]; 3];
+
+pub const IDENTITY: Matrix3 = [[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]];
+
+// From 9 values in row-major order, as in DNG's ColorMatrix tags.
+pub fn from_row_major(values: &[f64]) -> Option<Matrix3> {
+  if values.len() != 9 {
+    return None;
+  }
+  Some([0, 1, 2].map(|r| [0, 1, 2].map(|c| values[r * 3 + c] as f32)))
+}
+
+pub fn diagonal(v: [f32; 3]) -> Matrix3 {
+  [[v[0], 0.0, 0.0], [0.0, v[1], 0.0], [0.0, 0.0, v[2]]]
+}
+
+pub fn mul(a: &Matrix3, b: &Matrix3) -> Matrix3 {
+  [0, 1, 2].map(|r| [0, 1, 2].map(|c| (0..3).map(|k| a[r][k] * b[k][c]).sum()))
+}
+
+pub fn mul_vec(m: &Matrix3, v: [f32; 3]) -> [f32; 3] {
+  m.map(|row| row[0] * v[0] + row[1] * v[1] + row[2] * v[2])
+}
+
+// `a` weighted by `weight` plus `b` weighted by 1 - `weight`.
+pub fn blend(a: &Matrix3, b: &Matrix3, weight: f32) -> Matrix3 {
+  [0, 1, 2].map(|r| [0, 1, 2].map(|c| a[r][c] * weight + b[r][c] * (1.0 - weight)))
+}
+
+pub fn invert(m: &Matrix3) -> Option<Matrix3> {
+  let det = m[0][0] * (m[1][1] * m[2][2] - m[1][2] * m[2][1]) -
+    m[0][1] * (m[1][0] * m[2][2] - m[1][2] * m[2][0]) +
+    m[0][2] * (m[1][0] * m[2][1] - m[1][1] * m[2][0]);
+  if det.abs() < 1e-6 {
+    return None;
+  }
+  let mut inv = [[0.0_f32; 3]; 3];
+  for (r, row) in inv.iter_mut().enumerate() {
+    for (c, v) in row.iter_mut().enumerate() {
+      // Transposed cofactors.
+      let (r1, r2) = ((c + 1) % 3, (c + 2) % 3);
+      let (c1, c2) = ((r + 1) % 3, (r + 2) % 3);
+      *v = (m[r1][c1] * m[r2][c2] - m[r1][c2] * m[r2][c1]) / det;
+    }
+  }
+  Some(inv)
+}
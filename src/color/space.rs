@@ -0,0 +1,168 @@
+// Output color spaces: primaries, white point and transfer function.
+//
+// Rendered images are linear RGB in the output space until they are saved; the transfer function
+// encodes them then, and the ICC profile (`icc_profile`) describes both to readers.
+
+use super::{adaptation, diagonal, invert, mul, mul_vec, xy_to_xyz, Matrix3, D50, D65, IDENTITY};
+
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum ColorSpace {
+  #[default]
+  Srgb,
+  DisplayP3,
+  AdobeRgb,
+  ProPhoto,
+  LinearRec2020,
+  // CIE XYZ relative to D50, as ICC profiles connect.
+  Xyz,
+}
+
+// Encoding of linear values, 0 to 1, for storage.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Transfer {
+  Linear,
+  // A pure power law with this exponent for decoding.
+  Gamma(f32),
+  // IEC 61966-2-1, also used by Display P3.
+  Srgb,
+  // ROMM RGB (ISO 22028-2): gamma 1.8 with a linear segment near black.
+  ProPhoto,
+}
+
+impl ColorSpace {
+  // Names for the command line, in the order of `ColorSpace::ALL`.
+  pub const NAMES: &'static [&'static str] = &["srgb", "display-p3", "adobe-rgb", "prophoto", "linear-rec2020", "xyz"];
+  pub const ALL: &'static [ColorSpace] = &[
+    ColorSpace::Srgb,
+    ColorSpace::DisplayP3,
+    ColorSpace::AdobeRgb,
+    ColorSpace::ProPhoto,
+    ColorSpace::LinearRec2020,
+    ColorSpace::Xyz,
+  ];
+
+  pub fn from_name(name: &str) -> Option<Self> {
+    Self::NAMES.iter().position(|it| *it == name).map(|idx| Self::ALL[idx])
+  }
+
+  pub fn name(&self) -> &'static str {
+    Self::NAMES[*self as usize]
+  }
+
+  // For the profile description.
+  pub fn description(&self) -> &'static str {
+    match self {
+      ColorSpace::Srgb => "sRGB",
+      ColorSpace::DisplayP3 => "Display P3",
+      ColorSpace::AdobeRgb => "Adobe RGB (1998) compatible",
+      ColorSpace::ProPhoto => "ProPhoto RGB (ROMM)",
+      ColorSpace::LinearRec2020 => "Linear Rec. 2020",
+      ColorSpace::Xyz => "CIE XYZ D50",
+    }
+  }
+
+  // CIE 1931 xy of the red, green and blue primaries. XYZ has none.
+  fn primaries(&self) -> Option<[[f32; 2]; 3]> {
+    match self {
+      ColorSpace::Srgb => Some([[0.64, 0.33], [0.30, 0.60], [0.15, 0.06]]),
+      ColorSpace::DisplayP3 => Some([[0.680, 0.320], [0.265, 0.690], [0.150, 0.060]]),
+      ColorSpace::AdobeRgb => Some([[0.64, 0.33], [0.21, 0.71], [0.15, 0.06]]),
+      ColorSpace::ProPhoto => Some([[0.7347, 0.2653], [0.1596, 0.8404], [0.0366, 0.0001]]),
+      ColorSpace::LinearRec2020 => Some([[0.708, 0.292], [0.170, 0.797], [0.131, 0.046]]),
+      ColorSpace::Xyz => None,
+    }
+  }
+
+  // XYZ of the white point, with Y = 1.
+  pub fn white(&self) -> [f32; 3] {
+    match self {
+      ColorSpace::ProPhoto | ColorSpace::Xyz => D50,
+      _ => D65,
+    }
+  }
+
+  pub fn transfer(&self) -> Transfer {
+    match self {
+      ColorSpace::Srgb | ColorSpace::DisplayP3 => Transfer::Srgb,
+      ColorSpace::AdobeRgb => Transfer::Gamma(563.0 / 256.0),
+      ColorSpace::ProPhoto => Transfer::ProPhoto,
+      ColorSpace::LinearRec2020 | ColorSpace::Xyz => Transfer::Linear,
+    }
+  }
+
+  // Linear RGB to XYZ (D50), Bradford-adapted from the space's white. The columns are the colorants
+  // of the ICC profile.
+  pub fn rgb_to_xyz(&self) -> Matrix3 {
+    let Some(primaries) = self.primaries() else {
+      return IDENTITY;
+    };
+    // Primaries as columns, scaled so that RGB 1, 1, 1 is the white.
+    let columns = primaries.map(xy_to_xyz);
+    let unscaled = [0, 1, 2].map(|r| [0, 1, 2].map(|c| columns[c][r]));
+    let inverse = invert(&unscaled).expect("[BUG] Primaries are collinear!");
+    let to_xyz = mul(&unscaled, &diagonal(mul_vec(&inverse, self.white())));
+    mul(&adaptation(self.white(), D50), &to_xyz)
+  }
+
+  // XYZ (D50) to linear RGB.
+  pub fn xyz_to_rgb(&self) -> Matrix3 {
+    invert(&self.rgb_to_xyz()).expect("[BUG] Color space matrix is singular!")
+  }
+}
+
+impl Transfer {
+  // Linear to encoded.
+  pub fn encode(&self, v: f32) -> f32 {
+    match *self {
+      Transfer::Linear => v,
+      Transfer::Gamma(gamma) => v.powf(1.0 / gamma),
+      Transfer::Srgb if v <= 0.0031308 => v * 12.92,
+      Transfer::Srgb => 1.055 * v.powf(1.0 / 2.4) - 0.055,
+      Transfer::ProPhoto if v < 1.0 / 512.0 => v * 16.0,
+      Transfer::ProPhoto => v.powf(1.0 / 1.8),
+    }
+  }
+
+  // Encoded to linear.
+  pub fn decode(&self, v: f32) -> f32 {
+    match *self {
+      Transfer::Linear => v,
+      Transfer::Gamma(gamma) => v.powf(gamma),
+      Transfer::Srgb if v <= 0.04045 => v / 12.92,
+      Transfer::Srgb => ((v + 0.055) / 1.055).powf(2.4),
+      Transfer::ProPhoto if v < 16.0 / 512.0 => v / 16.0,
+      Transfer::ProPhoto => v.powf(1.8),
+    }
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  #[test]
+  fn test_color_space() {
+    // Bradford-adapted XYZ (D50) to sRGB, as Lindbloom lists it.
+    let expected = [[3.1339, -1.6169, -0.4906], [-0.9788, 1.9161, 0.0335], [0.0719, -0.2290, 1.4052]];
+    let matrix = ColorSpace::Srgb.xyz_to_rgb();
+    for (row, expected) in matrix.iter().zip(expected) {
+      for (v, e) in row.iter().zip(expected) {
+        assert!((v - e).abs() < 1e-3, "{:?}", matrix);
+      }
+    }
+    // Every space maps D50 to its white, which is RGB 1, 1, 1 except in XYZ.
+    for space in ColorSpace::ALL {
+      let white = mul_vec(&space.xyz_to_rgb(), D50);
+      let expected = if *space == ColorSpace::Xyz { D50 } else { [1.0; 3] };
+      for (w, e) in white.iter().zip(expected) {
+        assert!((w - e).abs() < 1e-4, "{}: {:?}", space.name(), white);
+      }
+      for v in [0.0, 0.001, 0.02, 0.18, 0.5, 1.0] {
+        let transfer = space.transfer();
+        assert!((transfer.decode(transfer.encode(v)) - v).abs() < 1e-5, "{}: {}", space.name(), v);
+      }
+      assert_eq!(ColorSpace::from_name(space.name()), Some(*space));
+    }
+    assert!((Transfer::Srgb.encode(0.18) - 0.4614).abs() < 1e-3);
+  }
+}
@@ -0,0 +1,102 @@
+// Correlated color temperature and tint, as chromaticities.
+//
+// Temperatures lie on the Planckian locus; tint moves perpendicular to it in CIE 1960 uv, with Adobe's
+// scale of 1/3000 uv per unit and positive values towards magenta.
+
+// uv per unit of tint.
+const TINT_SCALE: f64 = 1.0 / 3000.0;
+
+// Krystek's rational approximation of the Planckian locus in CIE 1960 uv, for 1000K to 15000K.
+fn planck_uv(temperature: f64) -> (f64, f64) {
+  let t = temperature.clamp(1000.0, 15000.0);
+  let u = (0.860117757 + 1.54118254e-4 * t + 1.28641212e-7 * t * t) / (1.0 + 8.42420235e-4 * t + 7.08145163e-7 * t * t);
+  let v = (0.317398726 + 4.22806245e-5 * t + 4.20481691e-8 * t * t) / (1.0 - 2.89741816e-5 * t + 1.61456053e-7 * t * t);
+  (u, v)
+}
+
+pub fn xyz_to_xy(xyz: [f32; 3]) -> [f32; 2] {
+  let sum = (xyz[0] + xyz[1] + xyz[2]).max(f32::EPSILON);
+  [xyz[0] / sum, xyz[1] / sum]
+}
+
+// CIE 1931 xy of `temperature` in kelvin, moved by `tint`.
+pub fn temperature_to_xy(temperature: f32, tint: f32) -> [f32; 2] {
+  let t = temperature as f64;
+  let (u, v) = planck_uv(t);
+  let (u0, v0) = planck_uv(t - 1.0);
+  let (u1, v1) = planck_uv(t + 1.0);
+  // Normal to the locus, pointing to green (higher v).
+  let (mut nu, mut nv) = (-(v1 - v0), u1 - u0);
+  if nv < 0.0 {
+    (nu, nv) = (-nu, -nv);
+  }
+  let len = (nu * nu + nv * nv).sqrt().max(f64::EPSILON);
+  let offset = -tint as f64 * TINT_SCALE;
+  let (u, v) = (u + nu / len * offset, v + nv / len * offset);
+  let d = 2.0 * u - 8.0 * v + 4.0;
+  [(3.0 * u / d) as f32, (2.0 * v / d) as f32]
+}
+
+// Temperature and tint of the CIE 1931 chromaticity `xy`: the closest point of the Planckian locus
+// in uv, searched in mireds between 1000K and 15000K, and the signed distance from it.
+pub fn xy_to_temperature(xy: [f32; 2]) -> (f32, f32) {
+  let [x, y] = xy.map(|it| it as f64);
+  let d = -2.0 * x + 12.0 * y + 3.0;
+  let (u, v) = (4.0 * x / d, 6.0 * y / d);
+  let distance = |mired: f64| {
+    let (pu, pv) = planck_uv(1e6 / mired);
+    (pu - u).powi(2) + (pv - v).powi(2)
+  };
+  // The distance has a single minimum along the locus, so a ternary search finds it.
+  let (mut lo, mut hi) = (1e6 / 15000.0, 1e6 / 1000.0);
+  for _ in 0..100 {
+    let (a, b) = (lo + (hi - lo) / 3.0, hi - (hi - lo) / 3.0);
+    if distance(a) < distance(b) {
+      hi = b;
+    } else {
+      lo = a;
+    }
+  }
+  let temperature = (1e6 / ((lo + hi) / 2.0)) as f32;
+  // The tint is the offset along the locus normal used by `temperature_to_xy`, which is linear in it.
+  let [x0, y0] = temperature_to_xy(temperature, 0.0);
+  let [x1, y1] = temperature_to_xy(temperature, 1.0);
+  let (dx, dy) = (x1 - x0, y1 - y0);
+  let tint = ((xy[0] - x0) * dx + (xy[1] - y0) * dy) / (dx * dx + dy * dy).max(f32::EPSILON);
+  (temperature, tint)
+}
+
+// XYZ with Y = 1.
+pub fn xy_to_xyz(xy: [f32; 2]) -> [f32; 3] {
+  let [x, y] = xy;
+  let y = y.max(f32::EPSILON);
+  [x / y, 1.0, (1.0 - x - y) / y]
+}
+
+// Temperature of an EXIF LightSource, as used by DNG's CalibrationIlluminant tags.
+pub fn illuminant_temperature(light_source: u16) -> Option<f32> {
+  match light_source {
+    // Tungsten, standard light A.
+    3 | 17 => Some(2856.0),
+    // Daylight, flash, fine weather, D55.
+    1 | 4 | 9 | 20 => Some(5503.0),
+    // Cloudy, D65.
+    10 | 21 => Some(6504.0),
+    // Shade, D75.
+    11 | 22 => Some(7504.0),
+    // Standard light B and C.
+    18 => Some(4874.0),
+    19 => Some(6774.0),
+    // Daylight fluorescent, day white, cool white (also plain fluorescent), white and warm white fluorescent.
+    12 => Some(6430.0),
+    13 => Some(5003.0),
+    2 | 14 => Some(4230.0),
+    15 => Some(3450.0),
+    16 => Some(2940.0),
+    // D50.
+    23 => Some(5003.0),
+    // ISO studio tungsten.
+    24 => Some(3200.0),
+    _ => None,
+  }
+}
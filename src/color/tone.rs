@@ -0,0 +1,225 @@
+// Exposure and tone mapping of linear output RGB, before the transfer function encodes it.
+//
+// Input 1 is the sensor's clipping point after exposure. Every curve maps it to display white, so
+// clipped highlights stay white; filmic and ACES-like curves roll off towards it instead of clipping,
+// lifting the midtones in exchange. Curves apply to each channel.
+//
+// User curves come as control points or CSV files, and work on encoded values, as in editors.
+//
+// References:
+// - John Hable, Filmic Tonemapping Operators, 2010: http://filmicworlds.com/blog/filmic-tonemapping-operators/
+// - Krzysztof Narkowicz, ACES Filmic Tone Mapping Curve, 2016:
+//   https://knarkowicz.wordpress.com/2016/01/06/aces-filmic-tone-mapping-curve/
+// - F. N. Fritsch and R. E. Carlson, Monotone Piecewise Cubic Interpolation, 1980
+
+use std::path::Path;
+use rayon::prelude::*;
+use crate::img::RgbImage;
+use super::Transfer;
+
+// Input of Hable's operator at display white, his W.
+const FILMIC_WHITE: f32 = 11.2;
+// Input of Narkowicz's fit taken as display white. Puts middle gray close to where the filmic curve does.
+const ACES_WHITE: f32 = 1.5;
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub enum ToneCurve {
+  // Only the transfer function of the output space, e.g. the exact sRGB OETF.
+  #[default]
+  None,
+  // Hable's Uncharted 2 operator.
+  Filmic,
+  // Narkowicz's fit of the ACES reference rendering and output transforms.
+  Aces,
+  // Interpolates the user's points.
+  Curve(Curve),
+}
+
+impl ToneCurve {
+  // Curves without parameters, for the command line.
+  pub const NAMES: &'static [&'static str] = &["none", "filmic", "aces"];
+
+  pub fn from_name(name: &str) -> Option<Self> {
+    match name {
+      "none" => Some(ToneCurve::None),
+      "filmic" => Some(ToneCurve::Filmic),
+      "aces" => Some(ToneCurve::Aces),
+      _ => None,
+    }
+  }
+
+  pub fn describe(&self) -> String {
+    match self {
+      ToneCurve::None => "none".to_string(),
+      ToneCurve::Filmic => "filmic".to_string(),
+      ToneCurve::Aces => "aces".to_string(),
+      ToneCurve::Curve(curve) => format!("curve of {} points", curve.points.len()),
+    }
+  }
+
+  // Linear display value of linear scene value `v`. `transfer` encodes the values user curves see.
+  pub fn map(&self, v: f32, transfer: &Transfer) -> f32 {
+    let v = v.max(0.0);
+    match self {
+      ToneCurve::None => v,
+      ToneCurve::Filmic => (hable(v * FILMIC_WHITE) / hable(FILMIC_WHITE)).min(1.0),
+      ToneCurve::Aces => (aces(v * ACES_WHITE) / aces(ACES_WHITE)).min(1.0),
+      ToneCurve::Curve(curve) => transfer.decode(curve.eval(transfer.encode(v.min(1.0))).clamp(0.0, 1.0)),
+    }
+  }
+}
+
+fn hable(x: f32) -> f32 {
+  // Shoulder strength, linear strength, linear angle, toe strength, toe numerator and denominator.
+  let (a, b, c, d, e, f) = (0.15, 0.50, 0.10, 0.20, 0.02, 0.30);
+  (x * (a * x + c * b) + d * e) / (x * (a * x + b) + d * f) - e / f
+}
+
+fn aces(x: f32) -> f32 {
+  x * (2.51 * x + 0.03) / (x * (2.43 * x + 0.59) + 0.14)
+}
+
+// A monotone cubic through points sorted by input, constant beyond the first and last ones.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Curve {
+  points: Vec<(f32, f32)>,
+  // Tangents at the points.
+  slopes: Vec<f32>,
+}
+
+impl Curve {
+  pub fn new(mut points: Vec<(f32, f32)>) -> anyhow::Result<Self> {
+    points.sort_by(|a, b| a.0.total_cmp(&b.0));
+    if points.len() < 2 || points.windows(2).any(|it| it[0].0 == it[1].0) {
+      return Err(anyhow::Error::msg("A tone curve needs at least 2 points with distinct inputs"));
+    }
+    let secants: Vec<f32> = points.windows(2).map(|it| (it[1].1 - it[0].1) / (it[1].0 - it[0].0)).collect();
+    let n = points.len();
+    let mut slopes = vec![0.0_f32; n];
+    slopes[0] = secants[0];
+    slopes[n - 1] = secants[n - 2];
+    for i in 1..n - 1 {
+      // Flat at extrema, keeping the curve monotone between points.
+      slopes[i] = if secants[i - 1] * secants[i] <= 0.0 { 0.0 } else { (secants[i - 1] + secants[i]) / 2.0 };
+    }
+    // Fritsch-Carlson: limit tangents so that no segment overshoots.
+    for (i, secant) in secants.iter().enumerate() {
+      if *secant == 0.0 {
+        slopes[i] = 0.0;
+        slopes[i + 1] = 0.0;
+        continue;
+      }
+      let (a, b) = (slopes[i] / secant, slopes[i + 1] / secant);
+      let norm = (a * a + b * b).sqrt();
+      if norm > 3.0 {
+        slopes[i] = 3.0 * a / norm * secant;
+        slopes[i + 1] = 3.0 * b / norm * secant;
+      }
+    }
+    Ok(Self { points, slopes })
+  }
+
+  // Comma-separated "input:output" pairs, e.g. "0:0,0.25:0.2,0.75:0.85,1:1".
+  pub fn parse(text: &str) -> anyhow::Result<Self> {
+    let points = text.split(',')
+      .map(|pair| {
+        let values = pair.split(':').map(|it| it.trim().parse::<f32>()).collect::<Result<Vec<f32>, _>>();
+        match values.as_deref() {
+          Ok([x, y]) => Ok((*x, *y)),
+          _ => Err(anyhow::Error::msg(format!("Expected \"input:output\", got {:?}", pair))),
+        }
+      })
+      .collect::<anyhow::Result<Vec<_>>>()?;
+    Self::new(points)
+  }
+
+  // CSV with one "input,output" pair per line, or only outputs for evenly spaced inputs from 0 to 1.
+  // `#` starts a comment, and a first line that isn't numbers is taken as a header.
+  pub fn parse_csv(text: &str) -> anyhow::Result<Self> {
+    let mut rows = Vec::<Vec<f32>>::new();
+    let mut header = false;
+    for (i, line) in text.lines().enumerate() {
+      let line = line.split('#').next().unwrap_or_default().trim();
+      if line.is_empty() {
+        continue;
+      }
+      match line.split(',').map(|it| it.trim().parse::<f32>()).collect::<Result<Vec<f32>, _>>() {
+        Ok(row) if (1..=2).contains(&row.len()) => rows.push(row),
+        Err(_) if rows.is_empty() && !header => header = true,
+        _ => return Err(anyhow::Error::msg(format!("Line {}: expected \"input,output\" or \"output\", got {:?}", i + 1, line))),
+      }
+    }
+    if rows.iter().any(|it| it.len() != rows[0].len()) {
+      return Err(anyhow::Error::msg("Every line needs the same number of columns"));
+    }
+    let last = rows.len().saturating_sub(1).max(1) as f32;
+    Self::new(rows.iter().enumerate().map(|(i, row)| match row[..] {
+      [x, y] => (x, y),
+      _ => (i as f32 / last, row[0]),
+    }).collect())
+  }
+
+  pub fn load(path: impl AsRef<Path>) -> anyhow::Result<Self> {
+    let path = path.as_ref();
+    let text = std::fs::read_to_string(path)
+      .map_err(|err| anyhow::Error::msg(format!("{}: {}", path.display(), err)))?;
+    Self::parse_csv(&text).map_err(|err| anyhow::Error::msg(format!("{}: {}", path.display(), err)))
+  }
+
+  pub fn eval(&self, x: f32) -> f32 {
+    let (first, last) = (self.points[0], self.points[self.points.len() - 1]);
+    if x <= first.0 {
+      return first.1;
+    }
+    if x >= last.0 {
+      return last.1;
+    }
+    let i = self.points.partition_point(|it| it.0 <= x) - 1;
+    let ((x0, y0), (x1, y1)) = (self.points[i], self.points[i + 1]);
+    let h = x1 - x0;
+    let t = (x - x0) / h;
+    // Cubic Hermite basis.
+    let (t2, t3) = (t * t, t * t * t);
+    (2.0 * t3 - 3.0 * t2 + 1.0) * y0 + (t3 - 2.0 * t2 + t) * h * self.slopes[i] +
+      (-2.0 * t3 + 3.0 * t2) * y1 + (t3 - t2) * h * self.slopes[i + 1]
+  }
+}
+
+// Scales every value by 2^`exposure` and maps it through `curve`.
+pub fn tone_map(img: &mut RgbImage, curve: &ToneCurve, exposure: f32, transfer: &Transfer) {
+  let gain = exposure.exp2();
+  img.data_mut().par_iter_mut().for_each(|v| *v = curve.map(*v * gain, transfer));
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  #[test]
+  fn test_tone_curve() {
+    // Black stays black and the clipping point becomes white, with midtones in between.
+    for curve in [ToneCurve::None, ToneCurve::Filmic, ToneCurve::Aces] {
+      assert!(curve.map(0.0, &Transfer::Srgb).abs() < 1e-3, "{:?}", curve);
+      assert!((curve.map(1.0, &Transfer::Srgb) - 1.0).abs() < 1e-5, "{:?}", curve);
+      let mid = curve.map(0.18, &Transfer::Srgb);
+      assert!((0.18..0.6).contains(&mid), "{:?}: {}", curve, mid);
+    }
+    assert_eq!(ToneCurve::Filmic.map(4.0, &Transfer::Srgb), 1.0);
+
+    // Through the points, monotone between them, and flat outside.
+    let curve = Curve::parse("0:0, 0.25:0.15, 0.5:0.6, 1:1").unwrap();
+    for (x, y) in [(0.0, 0.0), (0.25, 0.15), (0.5, 0.6), (1.0, 1.0), (-1.0, 0.0), (2.0, 1.0)] {
+      assert!((curve.eval(x) - y).abs() < 1e-6, "{}: {}", x, curve.eval(x));
+    }
+    let values: Vec<f32> = (0..=100).map(|i| curve.eval(i as f32 / 100.0)).collect();
+    assert!(values.windows(2).all(|it| it[1] >= it[0]));
+    assert!(Curve::parse("0:0").is_err() && Curve::parse("0:0,1").is_err());
+
+    // Identity user curves only round-trip through the transfer function.
+    let identity = ToneCurve::Curve(Curve::parse_csv("input,output\n0,0\n1,1\n").unwrap());
+    assert!((identity.map(0.18, &Transfer::Srgb) - 0.18).abs() < 1e-5);
+    let lut = Curve::parse_csv("# inverted\n1\n0.5\n0\n").unwrap();
+    assert!((lut.eval(0.25) - 0.75).abs() < 1e-6);
+    assert!(Curve::parse_csv("0,0\n1\n").is_err());
+  }
+}
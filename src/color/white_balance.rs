@@ -0,0 +1,197 @@
+// White balance: per-plane multipliers for the mosaic, applied before demosaicing.
+//
+// Multipliers are normalized so the smallest one is 1; channels scaled up clip at the white level,
+// which keeps clipped highlights white.
+
+use crate::img::{Cfa, RawImage, Rect};
+use super::{mul_vec, temperature_to_xy, xy_to_xyz, CameraColor};
+
+// Normalized values at or above this count as clipped and are left out of the estimates.
+const CLIPPED: f32 = 0.98;
+// Side of the patches `WhiteBalance::WhitePatch` compares, in CFA periods.
+const PATCH_PERIODS: usize = 8;
+// Share of the brightest patches averaged into the estimate.
+const PATCH_SHARE: f32 = 0.01;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum WhiteBalance {
+  // From the file's metadata.
+  #[default]
+  AsShot,
+  // Makes the mean of the image neutral.
+  GrayWorld,
+  // Makes the brightest unclipped patches neutral.
+  WhitePatch,
+  // Makes the mean of this area neutral, in image pixels.
+  Rect(Rect),
+  // Neutral under an illuminant of this temperature in kelvin and tint, through the camera's matrices.
+  Temperature { temperature: f32, tint: f32 },
+}
+
+impl WhiteBalance {
+  // Modes without parameters, for the command line.
+  pub const NAMES: &'static [&'static str] = &["as-shot", "gray-world", "white-patch"];
+
+  pub fn from_name(name: &str) -> Option<Self> {
+    match name {
+      "as-shot" => Some(WhiteBalance::AsShot),
+      "gray-world" => Some(WhiteBalance::GrayWorld),
+      "white-patch" => Some(WhiteBalance::WhitePatch),
+      _ => None,
+    }
+  }
+}
+
+// Multipliers for each CFA plane of `img`.
+pub fn multipliers(img: &RawImage, mode: &WhiteBalance) -> anyhow::Result<Vec<f32>> {
+  let cfa = img.cfa()?;
+  let neutral = match mode {
+    WhiteBalance::AsShot => {
+      let neutral = img.color().as_shot_neutral
+        .ok_or_else(|| anyhow::Error::msg("The file has no as-shot white balance"))?;
+      rgb_planes(&cfa, neutral)?
+    }
+    WhiteBalance::GrayWorld => plane_means(img, &cfa, &img.visible_area())?,
+    WhiteBalance::WhitePatch => white_patch(img, &cfa)?,
+    WhiteBalance::Rect(rect) => plane_means(img, &cfa, rect)?,
+    WhiteBalance::Temperature { temperature, tint } => {
+      rgb_planes(&cfa, temperature_neutral(img.color(), *temperature, *tint)?)?
+    }
+  };
+  Ok(normalize(&neutral))
+}
+
+// Camera RGB of a neutral surface lit by `temperature` and `tint`.
+pub fn temperature_neutral(color: &CameraColor, temperature: f32, tint: f32) -> anyhow::Result<[f32; 3]> {
+  let matrix = color.xyz_to_camera(temperature)
+    .ok_or_else(|| anyhow::Error::msg("No color matrix is known for this camera"))?;
+  Ok(mul_vec(&matrix, xy_to_xyz(temperature_to_xy(temperature, tint))))
+}
+
+// Scales each pixel above its black level by the multiplier of its plane, up to the white level.
+pub fn apply(img: &mut RawImage, multipliers: &[f32]) -> anyhow::Result<()> {
+  let cfa = img.cfa()?;
+  if multipliers.len() != cfa.planes() {
+    return Err(anyhow::Error::msg(format!("{} multipliers for the {} CFA", multipliers.len(), cfa)));
+  }
+  let (width, height) = (img.width(), img.height());
+  let white = img.white_level() as f32;
+  let blacks: Vec<f32> = (0..height * width).map(|i| img.black_level(i % width, i / width)).collect();
+  for (i, v) in img.data_mut().iter_mut().enumerate() {
+    let black = blacks[i];
+    let scaled = black + (*v as f32 - black).max(0.0) * multipliers[cfa.plane(i % width, i / width)];
+    *v = scaled.min(white).round() as u16;
+  }
+  Ok(())
+}
+
+// One multiplier per plane from the neutral's value in it, the smallest being 1.
+fn normalize(neutral: &[f32]) -> Vec<f32> {
+  let inverse: Vec<f32> = neutral.iter().map(|it| if *it > 0.0 { 1.0 / it } else { 1.0 }).collect();
+  let min = inverse.iter().copied().fold(f32::MAX, f32::min);
+  inverse.iter().map(|it| it / min).collect()
+}
+
+fn rgb_planes(cfa: &Cfa, rgb: [f32; 3]) -> anyhow::Result<Vec<f32>> {
+  if !cfa.is_rgb() {
+    return Err(anyhow::Error::msg(format!("Camera RGB white balance doesn't apply to the {} CFA", cfa)));
+  }
+  Ok(rgb.to_vec())
+}
+
+// Mean of each plane over `rect`, without clipped pixels.
+fn plane_means(img: &RawImage, cfa: &Cfa, rect: &Rect) -> anyhow::Result<Vec<f32>> {
+  let rect = rect.clip(img.width(), img.height());
+  let mut sums = vec![(0.0_f64, 0_u64); cfa.planes()];
+  for y in rect.y as usize..(rect.y + rect.height) as usize {
+    for x in rect.x as usize..(rect.x + rect.width) as usize {
+      let v = img.normalized(x, y);
+      if v < CLIPPED {
+        let sum = &mut sums[cfa.plane(x, y)];
+        sum.0 += v as f64;
+        sum.1 += 1;
+      }
+    }
+  }
+  if sums.iter().any(|(sum, count)| *count == 0 || *sum <= 0.0) {
+    return Err(anyhow::Error::msg(format!(
+      "No unclipped pixels of every color in {}x{} at ({}, {})", rect.width, rect.height, rect.x, rect.y)));
+  }
+  Ok(sums.iter().map(|(sum, count)| (sum / *count as f64) as f32).collect())
+}
+
+// Plane means of the brightest patches without clipped pixels.
+fn white_patch(img: &RawImage, cfa: &Cfa) -> anyhow::Result<Vec<f32>> {
+  let area = img.visible_area();
+  let (pw, ph) = (cfa.width() * PATCH_PERIODS, cfa.height() * PATCH_PERIODS);
+  let mut patches = Vec::<Vec<f32>>::new();
+  for y in (area.y as usize..(area.y + area.height) as usize).step_by(ph) {
+    for x in (area.x as usize..(area.x + area.width) as usize).step_by(pw) {
+      let mut sums = vec![(0.0_f32, 0_u32); cfa.planes()];
+      let mut clipped = false;
+      for py in y..(y + ph).min((area.y + area.height) as usize) {
+        for px in x..(x + pw).min((area.x + area.width) as usize) {
+          let v = img.normalized(px, py);
+          clipped |= v >= CLIPPED;
+          let sum = &mut sums[cfa.plane(px, py)];
+          sum.0 += v;
+          sum.1 += 1;
+        }
+      }
+      if !clipped && sums.iter().all(|(_, count)| *count > 0) {
+        patches.push(sums.iter().map(|(sum, count)| sum / *count as f32).collect());
+      }
+    }
+  }
+  if patches.is_empty() {
+    return Err(anyhow::Error::msg("Every patch of the image is clipped"));
+  }
+  let brightness = |means: &Vec<f32>| means.iter().sum::<f32>();
+  patches.sort_by(|a, b| brightness(b).total_cmp(&brightness(a)));
+  let n = ((patches.len() as f32 * PATCH_SHARE).ceil() as usize).max(1);
+  Ok((0..cfa.planes()).map(|p| patches[..n].iter().map(|it| it[p]).sum::<f32>() / n as f32).collect())
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use crate::color::IlluminantMatrix;
+  use crate::tiff::{CFAPattern, CFAPatternDim};
+
+  #[test]
+  fn test_multipliers() {
+    let pattern = vec![CFAPattern::R, CFAPattern::G, CFAPattern::G, CFAPattern::B];
+    let mut img = RawImage::new(32, 32, pattern, CFAPatternDim { width: 2, height: 2 });
+    img.set_white_level(1000);
+    for y in 0..32 {
+      for x in 0..32 {
+        // A gray scene under a reddish light, brighter to the right by CFA cells.
+        let gain = [0.4, 0.2, 0.1][img.cfa().unwrap().plane(x, y)];
+        img.set(x, y, (gain * (1000.0 + 40.0 * (x / 2) as f32)) as u16);
+      }
+    }
+    let expected = [1.0, 2.0, 4.0];
+    for mode in [WhiteBalance::GrayWorld, WhiteBalance::WhitePatch, WhiteBalance::Rect(Rect { x: 4, y: 4, width: 8, height: 8 })] {
+      let m = multipliers(&img, &mode).unwrap();
+      for (m, e) in m.iter().zip(expected) {
+        assert!((m - e).abs() < 0.02, "{:?}: {:?}", mode, m);
+      }
+    }
+    assert!(multipliers(&img, &WhiteBalance::AsShot).is_err());
+
+    let mut color = CameraColor { as_shot_neutral: Some([0.5, 1.0, 0.25]), ..Default::default() };
+    assert!(temperature_neutral(&color, 5000.0, 0.0).is_err());
+    // With XYZ as the camera space, the neutral is the illuminant's XYZ: x 0.3135 and y 0.3237 at 6504K.
+    color.matrices.push(IlluminantMatrix::new(6504.0, crate::color::IDENTITY));
+    let neutral = temperature_neutral(&color, 6504.0, 0.0).unwrap();
+    assert!((neutral[0] - 0.968).abs() < 0.005 && (neutral[2] - 1.121).abs() < 0.005, "{:?}", neutral);
+    // Magenta tint lowers green, so blue and red rise relative to it.
+    assert!(temperature_neutral(&color, 6504.0, 20.0).unwrap()[2] > neutral[2]);
+    img.set_color(color);
+    assert_eq!(multipliers(&img, &WhiteBalance::AsShot).unwrap(), vec![2.0, 1.0, 4.0]);
+
+    apply(&mut img, &[2.0, 1.0, 4.0]).unwrap();
+    assert_eq!(img.data()[0], 800);
+    assert_eq!(img.data()[30], 1000);
+  }
+}
@@ -0,0 +1,262 @@
+/*
+Demosaicing: interpolates the missing colors of a CFA mosaic into a full-resolution RGB image.
+
+Input is the mosaic normalized to 0-1 by `RawImage::normalize`; output is linear camera RGB.
+Bayer algorithms need a 2x2 RGB pattern, Markesteijn needs X-Trans, and the generic one takes any
+pattern, mapping colors other than R, G and B to RGB by least squares.
+*/
+
+use std::time::{Duration, Instant};
+use log::warn;
+use rayon::prelude::*;
+use crate::img::{Cfa, CfaKind, RawImage, RgbImage};
+
+mod bilinear;
+pub use bilinear::Bilinear;
+mod ahd;
+pub use ahd::Ahd;
+mod rcd;
+pub use rcd::Rcd;
+mod dcb;
+pub use dcb::Dcb;
+mod markesteijn;
+pub use markesteijn::Markesteijn;
+mod generic;
+pub use generic::Generic;
+
+pub trait Demosaic {
+  fn name(&self) -> &'static str;
+  fn supports(&self, cfa: &Cfa) -> bool;
+  fn demosaic(&self, raw: &RawImage) -> anyhow::Result<RgbImage>;
+}
+
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum Algorithm {
+  #[default]
+  Bilinear,
+  Ahd,
+  Rcd,
+  Dcb,
+  Markesteijn,
+  Generic,
+}
+
+impl Algorithm {
+  // Names for the command line, in the order of `Algorithm::ALL`.
+  pub const NAMES: &'static [&'static str] = &["bilinear", "ahd", "rcd", "dcb", "markesteijn", "generic"];
+  pub const ALL: &'static [Algorithm] = &[
+    Algorithm::Bilinear, Algorithm::Ahd, Algorithm::Rcd, Algorithm::Dcb, Algorithm::Markesteijn, Algorithm::Generic,
+  ];
+
+  pub fn from_name(name: &str) -> Option<Self> {
+    Self::NAMES.iter().position(|it| *it == name).map(|idx| Self::ALL[idx])
+  }
+
+  pub fn demosaicer(&self) -> Box<dyn Demosaic> {
+    match self {
+      Algorithm::Bilinear => Box::new(Bilinear),
+      Algorithm::Ahd => Box::new(Ahd),
+      Algorithm::Rcd => Box::new(Rcd),
+      Algorithm::Dcb => Box::new(Dcb::default()),
+      Algorithm::Markesteijn => Box::new(Markesteijn),
+      Algorithm::Generic => Box::new(Generic),
+    }
+  }
+
+  // The fallback for patterns the chosen algorithm doesn't support.
+  pub fn default_for(cfa: &Cfa) -> Self {
+    match cfa.kind() {
+      CfaKind::Bayer => Algorithm::Bilinear,
+      CfaKind::XTrans => Algorithm::Markesteijn,
+      CfaKind::Other => Algorithm::Generic,
+    }
+  }
+}
+
+pub fn demosaic(raw: &RawImage, algorithm: Algorithm) -> anyhow::Result<RgbImage> {
+  let cfa = raw.cfa()?;
+  let demosaicer = algorithm.demosaicer();
+  if demosaicer.supports(&cfa) {
+    return demosaicer.demosaic(raw);
+  }
+  let fallback = Algorithm::default_for(&cfa).demosaicer();
+  warn!("{} doesn't support the {} CFA; using {}", demosaicer.name(), cfa, fallback.name());
+  fallback.demosaic(raw)
+}
+
+#[derive(Clone, Debug)]
+pub struct Timing {
+  pub algorithm: &'static str,
+  pub best: Duration,
+  pub mean: Duration,
+  // Input megapixels per second, from the best run.
+  pub megapixels_per_second: f64,
+}
+
+// Demosaics `raw` `runs` times (at least once), discarding the output.
+pub fn benchmark(raw: &RawImage, algorithm: Algorithm, runs: usize) -> anyhow::Result<Timing> {
+  let demosaicer = algorithm.demosaicer();
+  let runs = runs.max(1);
+  let mut times = Vec::<Duration>::with_capacity(runs);
+  for _ in 0..runs {
+    let start = Instant::now();
+    let rgb = demosaicer.demosaic(raw)?;
+    times.push(start.elapsed());
+    drop(rgb);
+  }
+  let best = times.iter().min().copied().unwrap_or_default();
+  let megapixels = (raw.width() * raw.height()) as f64 / 1e6;
+  Ok(Timing {
+    algorithm: demosaicer.name(),
+    best,
+    mean: times.iter().sum::<Duration>() / runs as u32,
+    megapixels_per_second: megapixels / best.as_secs_f64().max(f64::EPSILON),
+  })
+}
+
+fn bayer_cfa(raw: &RawImage) -> anyhow::Result<Cfa> {
+  let cfa = raw.cfa()?;
+  if cfa.kind() != CfaKind::Bayer {
+    return Err(anyhow::Error::msg(format!("Not a 2x2 Bayer pattern: {}", cfa)));
+  }
+  Ok(cfa)
+}
+
+// Channel (0 = R, 1 = G, 2 = B) of each position of a 2x2 Bayer pattern, row-major.
+fn bayer_layout(raw: &RawImage) -> anyhow::Result<[usize; 4]> {
+  let cfa = bayer_cfa(raw)?;
+  Ok([cfa.plane(0, 0), cfa.plane(1, 0), cfa.plane(0, 1), cfa.plane(1, 1)])
+}
+
+// Mirrors an index around the edges of 0..n, which keeps its CFA parity.
+fn reflect(i: isize, n: usize) -> usize {
+  let n = n as isize;
+  let i = i.abs();
+  let i = if i >= n { 2 * (n - 1) - i } else { i };
+  i.clamp(0, n - 1) as usize
+}
+
+// Rows of the mosaic with coordinates relative to the first one.
+struct Tile<'a> {
+  mosaic: &'a [f32],
+  cfa: &'a Cfa,
+  // Image row of the first row, for the CFA phase.
+  top: usize,
+  width: usize,
+  height: usize,
+}
+
+impl <'a> Tile<'a> {
+  // Mirrored beyond the edges, which keeps the color of 2x2 patterns only.
+  fn idx(&self, x: isize, y: isize) -> usize {
+    reflect(y, self.height) * self.width + reflect(x, self.width)
+  }
+  fn checked_idx(&self, x: isize, y: isize) -> Option<usize> {
+    let inside = x >= 0 && y >= 0 && (x as usize) < self.width && (y as usize) < self.height;
+    inside.then(|| y as usize * self.width + x as usize)
+  }
+  fn color(&self, x: usize, y: usize) -> usize {
+    self.cfa.plane(x, y + self.top)
+  }
+  fn get(&self, x: isize, y: isize) -> f32 {
+    self.mosaic[self.idx(x, y)]
+  }
+}
+
+// Runs `f(tile, start, out)` over strips of `strip` rows in parallel. The tile holds `margin` extra rows
+// above and below where the image has them, and `f` writes the strip starting at tile row `start` to `out`.
+fn by_strips<F>(raw: &RawImage, cfa: &Cfa, strip: usize, margin: usize, f: F) -> anyhow::Result<RgbImage>
+where
+  F: Fn(&Tile, usize, &mut [f32]) + Sync,
+{
+  let mosaic = raw.normalize();
+  let (width, height) = (raw.width(), raw.height());
+  let mut out = RgbImage::new(width, height);
+  if width == 0 || height == 0 {
+    return Ok(out);
+  }
+  out.data_mut().par_chunks_mut(width * 3 * strip).enumerate().for_each(|(i, chunk)| {
+    let y0 = i * strip;
+    let rows = chunk.len() / (width * 3);
+    let top = y0.saturating_sub(margin);
+    let bottom = (y0 + rows + margin).min(height);
+    let tile = Tile {
+      mosaic: &mosaic[top * width..bottom * width],
+      cfa,
+      top,
+      width,
+      height: bottom - top,
+    };
+    f(&tile, y0 - top, chunk);
+  });
+  Ok(out)
+}
+
+// Linear sRGB to XYZ (D65), normalized to the white point. Camera RGB stands in for sRGB here;
+// only distances matter.
+const RGB_TO_XYZ: [[f32; 3]; 3] = [
+  [0.4124 / 0.9505, 0.3576 / 0.9505, 0.1805 / 0.9505],
+  [0.2126, 0.7152, 0.0722],
+  [0.0193 / 1.0890, 0.1192 / 1.0890, 0.9505 / 1.0890],
+];
+
+fn to_lab(rgb: [f32; 3]) -> [f32; 3] {
+  let f = |t: f32| if t > 0.008856 { t.cbrt() } else { 7.787 * t + 16.0 / 116.0 };
+  let [x, y, z] = RGB_TO_XYZ.map(|row| f(row[0] * rgb[0] + row[1] * rgb[1] + row[2] * rgb[2]));
+  [116.0 * y - 16.0, 500.0 * (x - y), 200.0 * (y - z)]
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use crate::tiff::{CFAPattern, CFAPatternDim};
+
+  fn mosaic(width: usize, height: usize, truth: impl Fn(usize, usize) -> f32) -> RawImage {
+    let pattern = vec![CFAPattern::B, CFAPattern::G, CFAPattern::G, CFAPattern::R];
+    let mut raw = RawImage::new(width, height, pattern.clone(), CFAPatternDim { width: 2, height: 2 });
+    raw.set_white_level(1000);
+    for y in 0..height {
+      for x in 0..width {
+        let c = match pattern[(y % 2) * 2 + x % 2] { CFAPattern::R => 0, CFAPattern::G => 1, _ => 2 };
+        raw.set(x, y, (truth(x, c) * 1000.0).round() as u16);
+      }
+    }
+    raw
+  }
+
+  fn max_error(rgb: &RgbImage, truth: impl Fn(usize, usize) -> f32) -> f32 {
+    let mut error = 0.0_f32;
+    for y in 0..rgb.height() {
+      for x in 0..rgb.width() {
+        for (c, v) in rgb.get(x, y).iter().enumerate() {
+          error = error.max((v - truth(x, c)).abs());
+        }
+      }
+    }
+    error
+  }
+
+  #[test]
+  fn test_algorithms() {
+    let (width, height) = (20, 70);
+    let flat = |_: usize, c: usize| [0.1_f32, 0.5, 0.9][c];
+    // Gray with a vertical edge.
+    let edge = |x: usize, _: usize| if x < 9 { 0.2_f32 } else { 0.8 };
+    let flat_raw = mosaic(width, height, flat);
+    let edge_raw = mosaic(width, height, edge);
+    let bilinear = max_error(&demosaic(&edge_raw, Algorithm::Bilinear).unwrap(), edge);
+    let cfa = flat_raw.cfa().unwrap();
+    for algorithm in Algorithm::ALL {
+      let error = max_error(&demosaic(&flat_raw, *algorithm).unwrap(), flat);
+      assert!(error < 1e-5, "{:?}: error {} in a flat area", algorithm, error);
+      let error = max_error(&demosaic(&edge_raw, *algorithm).unwrap(), edge);
+      if algorithm.demosaicer().supports(&cfa) && !matches!(algorithm, Algorithm::Bilinear | Algorithm::Generic) {
+        assert!(error < bilinear / 2.0, "{:?}: error {} at an edge, bilinear {}", algorithm, error, bilinear);
+      }
+    }
+    let raw = flat_raw;
+    let timing = benchmark(&raw, Algorithm::Rcd, 2).unwrap();
+    assert_eq!(timing.algorithm, "rcd");
+    assert!(timing.best <= timing.mean);
+  }
+}
@@ -0,0 +1,175 @@
+/*
+Adaptive Homogeneity-Directed demosaicing.
+
+K. Hirakawa and T. W. Parks, "Adaptive homogeneity-directed demosaicing algorithm", IEEE TIP 14(3), 2005.
+Follows the structure of dcraw's ahd_interpolate(): green is interpolated horizontally and vertically,
+both candidates are completed and converted to CIELab, and each pixel takes the direction whose
+neighborhood is more homogeneous.
+*/
+
+use crate::img::{Cfa, CfaKind, RawImage, RgbImage};
+use super::{bayer_cfa, by_strips, reflect, to_lab, Demosaic, Tile};
+
+// Rows per independently processed strip, and rows of context above and below it.
+const STRIP: usize = 32;
+const MARGIN: usize = 4;
+
+const H: usize = 0;
+const V: usize = 1;
+
+pub struct Ahd;
+
+impl Demosaic for Ahd {
+  fn name(&self) -> &'static str {
+    "ahd"
+  }
+
+  fn supports(&self, cfa: &Cfa) -> bool {
+    cfa.kind() == CfaKind::Bayer
+  }
+
+  fn demosaic(&self, raw: &RawImage) -> anyhow::Result<RgbImage> {
+    by_strips(raw, &bayer_cfa(raw)?, STRIP, MARGIN, interpolate)
+  }
+}
+
+// Green interpolated along rows (H) and columns (V), with a Laplacian correction from the pixel's own color.
+fn green(tile: &Tile) -> [Vec<f32>; 2] {
+  let mut green = [tile.mosaic.to_vec(), tile.mosaic.to_vec()];
+  for y in 0..tile.height {
+    for x in 0..tile.width {
+      if tile.color(x, y) == 1 {
+        continue;
+      }
+      let (xi, yi) = (x as isize, y as isize);
+      let c = 2.0 * tile.get(xi, yi);
+      let (l, r) = (tile.get(xi - 1, yi), tile.get(xi + 1, yi));
+      let h = (l + r) / 2.0 + (c - tile.get(xi - 2, yi) - tile.get(xi + 2, yi)) / 4.0;
+      let (u, d) = (tile.get(xi, yi - 1), tile.get(xi, yi + 1));
+      let v = (u + d) / 2.0 + (c - tile.get(xi, yi - 2) - tile.get(xi, yi + 2)) / 4.0;
+      // Clamped to the neighbors to avoid overshoot.
+      green[H][y * tile.width + x] = h.clamp(l.min(r), l.max(r));
+      green[V][y * tile.width + x] = v.clamp(u.min(d), u.max(d));
+    }
+  }
+  green
+}
+
+// Red and blue from color differences to the interpolated green.
+fn complete(tile: &Tile, green: &[f32]) -> Vec<[f32; 3]> {
+  let mut rgb = vec![[0.0_f32; 3]; tile.mosaic.len()];
+  let diff = |x: isize, y: isize| {
+    let i = tile.idx(x, y);
+    tile.mosaic[i] - green[i]
+  };
+  for y in 0..tile.height {
+    for x in 0..tile.width {
+      let i = y * tile.width + x;
+      let (xi, yi) = (x as isize, y as isize);
+      let own = tile.color(x, y);
+      let g = green[i];
+      let mut px = [0.0, g, 0.0];
+      if own == 1 {
+        // Red and blue are on opposite axes; R = 0 and B = 2.
+        let horizontal = tile.color(reflect(xi + 1, tile.width), y);
+        px[horizontal] = g + (diff(xi - 1, yi) + diff(xi + 1, yi)) / 2.0;
+        px[2 - horizontal] = g + (diff(xi, yi - 1) + diff(xi, yi + 1)) / 2.0;
+      } else {
+        px[own] = tile.mosaic[i];
+        px[2 - own] = g + (diff(xi - 1, yi - 1) + diff(xi + 1, yi - 1) + diff(xi - 1, yi + 1) + diff(xi + 1, yi + 1)) / 4.0;
+      }
+      rgb[i] = px;
+    }
+  }
+  rgb
+}
+
+// Number of 4-neighbors close to each pixel in CIELab, per direction.
+fn homogeneity(tile: &Tile, lab: &[Vec<[f32; 3]>; 2]) -> [Vec<u8>; 2] {
+  let mut homo = [vec![0_u8; tile.mosaic.len()], vec![0_u8; tile.mosaic.len()]];
+  for y in 0..tile.height {
+    for x in 0..tile.width {
+      let i = y * tile.width + x;
+      let (xi, yi) = (x as isize, y as isize);
+      let n = [tile.idx(xi - 1, yi), tile.idx(xi + 1, yi), tile.idx(xi, yi - 1), tile.idx(xi, yi + 1)];
+      let ldiff = |d: usize, j: usize| (lab[d][i][0] - lab[d][j][0]).abs();
+      let abdiff = |d: usize, j: usize| (lab[d][i][1] - lab[d][j][1]).powi(2) + (lab[d][i][2] - lab[d][j][2]).powi(2);
+      // Thresholds from the direction each candidate interpolated along.
+      let leps = ldiff(H, n[0]).max(ldiff(H, n[1])).min(ldiff(V, n[2]).max(ldiff(V, n[3])));
+      let abeps = abdiff(H, n[0]).max(abdiff(H, n[1])).min(abdiff(V, n[2]).max(abdiff(V, n[3])));
+      for (d, homo) in homo.iter_mut().enumerate() {
+        homo[i] = n.iter().filter(|j| ldiff(d, **j) <= leps && abdiff(d, **j) <= abeps).count() as u8;
+      }
+    }
+  }
+  homo
+}
+
+// Writes `out.len() / 3 / width` rows starting at tile row `start`.
+fn interpolate(tile: &Tile, start: usize, out: &mut [f32]) {
+  let green = green(tile);
+  let rgb = [complete(tile, &green[H]), complete(tile, &green[V])];
+  let lab = [
+    rgb[H].iter().map(|it| to_lab(*it)).collect::<Vec<_>>(),
+    rgb[V].iter().map(|it| to_lab(*it)).collect::<Vec<_>>(),
+  ];
+  let homo = homogeneity(tile, &lab);
+  for (row, out) in out.chunks_exact_mut(tile.width * 3).enumerate() {
+    let y = start + row;
+    for x in 0..tile.width {
+      let (xi, yi) = (x as isize, y as isize);
+      let mut score = [0_u32; 2];
+      for dy in -1..=1 {
+        for dx in -1..=1 {
+          let j = tile.idx(xi + dx, yi + dy);
+          score[H] += homo[H][j] as u32;
+          score[V] += homo[V][j] as u32;
+        }
+      }
+      let i = y * tile.width + x;
+      let px = match score[H].cmp(&score[V]) {
+        std::cmp::Ordering::Greater => rgb[H][i],
+        std::cmp::Ordering::Less => rgb[V][i],
+        std::cmp::Ordering::Equal => [0, 1, 2].map(|c| (rgb[H][i][c] + rgb[V][i][c]) / 2.0),
+      };
+      out[x * 3..x * 3 + 3].copy_from_slice(&px);
+    }
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use crate::demosaic::Bilinear;
+  use crate::tiff::{CFAPattern, CFAPatternDim};
+
+  #[test]
+  fn test_ahd() {
+    use CFAPattern::{B, G, R};
+    let (width, height) = (16, 40);
+    // Gray with a vertical edge: bilinear smears it, AHD interpolates along it.
+    let truth = |x: usize| if x < 7 { 0.2_f32 } else { 0.8 };
+    let mut raw = RawImage::new(width, height, vec![G, R, B, G], CFAPatternDim { width: 2, height: 2 });
+    raw.set_white_level(1000);
+    for y in 0..height {
+      for x in 0..width {
+        raw.set(x, y, (truth(x) * 1000.0) as u16);
+      }
+    }
+    let max_error = |rgb: &RgbImage| {
+      let mut error = 0.0_f32;
+      for y in 0..height {
+        for x in 0..width {
+          for v in rgb.get(x, y) {
+            error = error.max((v - truth(x)).abs());
+          }
+        }
+      }
+      error
+    };
+    let ahd = max_error(&Ahd.demosaic(&raw).unwrap());
+    let bilinear = max_error(&Bilinear.demosaic(&raw).unwrap());
+    assert!(ahd < 1e-6, "AHD error {}", ahd);
+    assert!(bilinear > 0.1, "Bilinear error {}", bilinear);
+  }
+}
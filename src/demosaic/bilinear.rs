@@ -0,0 +1,81 @@
+use rayon::prelude::*;
+use crate::img::{Cfa, CfaKind, RawImage, RgbImage};
+use super::{bayer_layout, Demosaic};
+
+// Each missing color is the mean of the pixels of that color in the 3x3 neighborhood.
+pub struct Bilinear;
+
+impl Demosaic for Bilinear {
+  fn name(&self) -> &'static str {
+    "bilinear"
+  }
+
+  fn supports(&self, cfa: &Cfa) -> bool {
+    cfa.kind() == CfaKind::Bayer
+  }
+
+  fn demosaic(&self, raw: &RawImage) -> anyhow::Result<RgbImage> {
+    let layout = bayer_layout(raw)?;
+    let mosaic = raw.normalize();
+    let (width, height) = (raw.width(), raw.height());
+    let mut out = RgbImage::new(width, height);
+    out.data_mut().par_chunks_mut(width * 3).enumerate().for_each(|(y, row)| {
+      for x in 0..width {
+        let own = layout[(y % 2) * 2 + x % 2];
+        let mut sum = [0.0_f32; 3];
+        let mut count = [0_u32; 3];
+        for ny in y.saturating_sub(1)..(y + 2).min(height) {
+          for nx in x.saturating_sub(1)..(x + 2).min(width) {
+            let c = layout[(ny % 2) * 2 + nx % 2];
+            sum[c] += mosaic[ny * width + nx];
+            count[c] += 1;
+          }
+        }
+        for c in 0..3 {
+          row[x * 3 + c] = if c == own {
+            mosaic[y * width + x]
+          } else if count[c] > 0 {
+            sum[c] / count[c] as f32
+          } else {
+            0.0
+          };
+        }
+      }
+    });
+    Ok(out)
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use crate::tiff::{CFAPattern, CFAPatternDim};
+
+  #[test]
+  fn test_bilinear() {
+    use CFAPattern::{B, G, R};
+    for pattern in [vec![R, G, G, B], vec![G, R, B, G], vec![G, B, R, G], vec![B, G, G, R]] {
+      let mut raw = RawImage::new(6, 4, pattern.clone(), CFAPatternDim { width: 2, height: 2 });
+      raw.set_white_level(1000);
+      // A flat color: R = 0.1, G = 0.5, B = 0.9.
+      for y in 0..4 {
+        for x in 0..6 {
+          let v = match pattern[(y % 2) * 2 + x % 2] {
+            R => 100,
+            G => 500,
+            _ => 900,
+          };
+          raw.set(x, y, v);
+        }
+      }
+      let rgb = Bilinear.demosaic(&raw).unwrap();
+      for y in 0..4 {
+        for x in 0..6 {
+          assert_eq!(rgb.get(x, y), [0.1, 0.5, 0.9], "{:?} at ({}, {})", pattern, x, y);
+        }
+      }
+    }
+    let raw = RawImage::new(2, 2, vec![R, G, R, B], CFAPatternDim { width: 2, height: 2 });
+    assert!(Bilinear.demosaic(&raw).is_err());
+  }
+}
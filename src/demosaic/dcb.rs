@@ -0,0 +1,149 @@
+/*
+DCB demosaicing, after Jacek Góźdź's dcb_demosaicing in LibRaw.
+
+The core passes only: green starts bilinear, then each iteration refines it with color differences
+(dcb_hid2), maps the locally smoother direction (dcb_map) and re-interpolates green along it
+(dcb_correction). Red and blue come from green-corrected neighbors (dcb_color). The false color
+suppression and enhancement passes of the original are left out.
+*/
+
+use crate::img::{Cfa, CfaKind, RawImage, RgbImage};
+use super::{bayer_cfa, by_strips, Demosaic, Tile};
+
+const STRIP: usize = 64;
+// Rows of context each pass reads.
+const MARGIN_PER_ITERATION: usize = 4;
+const MARGIN_BASE: usize = 4;
+
+pub struct Dcb {
+  iterations: usize,
+}
+
+impl Default for Dcb {
+  fn default() -> Self {
+    Self {
+      iterations: 2,
+    }
+  }
+}
+
+impl Demosaic for Dcb {
+  fn name(&self) -> &'static str {
+    "dcb"
+  }
+
+  fn supports(&self, cfa: &Cfa) -> bool {
+    cfa.kind() == CfaKind::Bayer
+  }
+
+  fn demosaic(&self, raw: &RawImage) -> anyhow::Result<RgbImage> {
+    let margin = MARGIN_BASE + MARGIN_PER_ITERATION * self.iterations;
+    by_strips(raw, &bayer_cfa(raw)?, STRIP, margin, |tile, start, out| interpolate(tile, self.iterations, start, out))
+  }
+}
+
+fn interpolate(tile: &Tile, iterations: usize, start: usize, out: &mut [f32]) {
+  let (width, height) = (tile.width, tile.height);
+  let at = |x: usize, y: usize, dx: isize, dy: isize| tile.idx(x as isize + dx, y as isize + dy);
+  let mut rgb = [vec![0.0_f32; tile.mosaic.len()], vec![0.0_f32; tile.mosaic.len()], vec![0.0_f32; tile.mosaic.len()]];
+  for y in 0..height {
+    for x in 0..width {
+      rgb[tile.color(x, y)][y * width + x] = tile.mosaic[y * width + x];
+    }
+  }
+
+  // dcb_hid: green at red and blue pixels from the four green neighbors.
+  for y in 0..height {
+    for x in 0..width {
+      if tile.color(x, y) != 1 {
+        let g = |dx, dy| tile.mosaic[at(x, y, dx, dy)];
+        rgb[1][y * width + x] = (g(-1, 0) + g(1, 0) + g(0, -1) + g(0, 1)) / 4.0;
+      }
+    }
+  }
+
+  let mut map = vec![false; tile.mosaic.len()];
+  for _ in 0..iterations {
+    // dcb_hid2: green from the green of same-color pixels two away, corrected by the pixel's own color.
+    let mut green = rgb[1].clone();
+    for y in 0..height {
+      for x in 0..width {
+        let own = tile.color(x, y);
+        if own == 1 {
+          continue;
+        }
+        let sum = |plane: &[f32]| plane[at(x, y, 0, -2)] + plane[at(x, y, 0, 2)] + plane[at(x, y, -2, 0)] + plane[at(x, y, 2, 0)];
+        let i = y * width + x;
+        green[i] = (sum(&rgb[1]) / 4.0 + rgb[own][i] - sum(&rgb[own]) / 4.0).clamp(0.0, 1.0);
+      }
+    }
+    rgb[1] = green;
+
+    // dcb_map: true where green runs smoother vertically than horizontally.
+    for y in 0..height {
+      for x in 0..width {
+        let g = |dx, dy| rgb[1][at(x, y, dx, dy)];
+        let (l, r, u, d) = (g(-1, 0), g(1, 0), g(0, -1), g(0, 1));
+        map[y * width + x] = if g(0, 0) > (l + r + u + d) / 4.0 {
+          l.min(r) + l + r < u.min(d) + u + d
+        } else {
+          l.max(r) + l + r > u.max(d) + u + d
+        };
+      }
+    }
+
+    // dcb_correction: green along the direction the neighborhood votes for.
+    for y in 0..height {
+      for x in 0..width {
+        if tile.color(x, y) == 1 {
+          continue;
+        }
+        let m = |dx, dy| map[at(x, y, dx, dy)] as u32 as f32;
+        let current = 4.0 * m(0, 0) +
+          2.0 * (m(0, 1) + m(0, -1) + m(1, 0) + m(-1, 0)) +
+          m(0, 2) + m(0, -2) + m(2, 0) + m(-2, 0);
+        let g = |dx, dy| rgb[1][at(x, y, dx, dy)];
+        // Neighbors on the axes are green pixels, which this pass doesn't change.
+        let v = ((16.0 - current) * (g(-1, 0) + g(1, 0)) / 2.0 + current * (g(0, -1) + g(0, 1)) / 2.0) / 16.0;
+        rgb[1][y * width + x] = v;
+      }
+    }
+  }
+
+  // dcb_color: red and blue at blue and red pixels from the diagonals, then both at green pixels.
+  for y in 0..height {
+    for x in 0..width {
+      let own = tile.color(x, y);
+      if own == 1 {
+        continue;
+      }
+      let other = 2 - own;
+      let diagonal = |plane: &[f32]| plane[at(x, y, -1, -1)] + plane[at(x, y, 1, -1)] + plane[at(x, y, -1, 1)] + plane[at(x, y, 1, 1)];
+      let i = y * width + x;
+      let v = (4.0 * rgb[1][i] - diagonal(&rgb[1]) + diagonal(&rgb[other])) / 4.0;
+      rgb[other][i] = v.clamp(0.0, 1.0);
+    }
+  }
+  for y in 0..height {
+    for x in 0..width {
+      if tile.color(x, y) != 1 {
+        continue;
+      }
+      let i = y * width + x;
+      let horizontal = tile.color(at(x, y, 1, 0) % width, y);
+      for (channel, (dx, dy)) in [(horizontal, (1, 0)), (2 - horizontal, (0, 1))] {
+        let (a, b) = (at(x, y, -dx, -dy), at(x, y, dx, dy));
+        let v = (2.0 * rgb[1][i] - rgb[1][a] - rgb[1][b] + rgb[channel][a] + rgb[channel][b]) / 2.0;
+        rgb[channel][i] = v.clamp(0.0, 1.0);
+      }
+    }
+  }
+
+  for (row, out) in out.chunks_exact_mut(width * 3).enumerate() {
+    let y = start + row;
+    for x in 0..width {
+      let i = y * width + x;
+      out[x * 3..x * 3 + 3].copy_from_slice(&[rgb[0][i], rgb[1][i], rgb[2][i]]);
+    }
+  }
+}
@@ -0,0 +1,120 @@
+use rayon::prelude::*;
+use crate::color;
+use crate::img::{Cfa, RawImage, RgbImage};
+use super::Demosaic;
+
+// Each missing color is the mean of that color over the smallest square window around the pixel
+// holding it. Works for any pattern; colors other than R, G and B are mapped to RGB by least squares.
+pub struct Generic;
+
+// Response of each TIFF/EP color to R, G and B, taking C = G + B, M = R + B, Y = R + G and W = R + G + B.
+fn primaries(code: u8) -> Option<[f32; 3]> {
+  match code {
+    0 => Some([1.0, 0.0, 0.0]),
+    1 => Some([0.0, 1.0, 0.0]),
+    2 => Some([0.0, 0.0, 1.0]),
+    3 => Some([0.0, 1.0, 1.0]),
+    4 => Some([1.0, 0.0, 1.0]),
+    5 => Some([1.0, 1.0, 0.0]),
+    6 => Some([1.0, 1.0, 1.0]),
+    _ => None,
+  }
+}
+
+// RGB contribution of each plane: the pseudo-inverse of the plane primaries.
+fn projection(cfa: &Cfa) -> anyhow::Result<Vec<[f32; 3]>> {
+  let primaries = cfa.colors().iter()
+    .map(|it| primaries(*it).ok_or_else(|| anyhow::Error::msg(format!("Unknown CFA color: {}", it))))
+    .collect::<anyhow::Result<Vec<[f32; 3]>>>()?;
+  let mut ata = [[0.0_f32; 3]; 3];
+  for p in &primaries {
+    for r in 0..3 {
+      for c in 0..3 {
+        ata[r][c] += p[r] * p[c];
+      }
+    }
+  }
+  let inv = color::invert(&ata).ok_or_else(|| anyhow::Error::msg(format!("Can't map the colors of the {} CFA to RGB", cfa)))?;
+  Ok(primaries.iter().map(|p| [0, 1, 2].map(|r| inv[r][0] * p[0] + inv[r][1] * p[1] + inv[r][2] * p[2])).collect())
+}
+
+impl Demosaic for Generic {
+  fn name(&self) -> &'static str {
+    "generic"
+  }
+
+  fn supports(&self, cfa: &Cfa) -> bool {
+    projection(cfa).is_ok()
+  }
+
+  fn demosaic(&self, raw: &RawImage) -> anyhow::Result<RgbImage> {
+    let cfa = raw.cfa()?;
+    let projection = projection(&cfa)?;
+    let mosaic = raw.normalize();
+    let (width, height) = (raw.width(), raw.height());
+    let max_radius = cfa.width().max(cfa.height());
+    let mut out = RgbImage::new(width, height);
+    out.data_mut().par_chunks_mut(width * 3).enumerate().for_each(|(y, row)| {
+      let mut values = vec![0.0_f32; cfa.planes()];
+      for x in 0..width {
+        let own = cfa.plane(x, y);
+        for (plane, value) in values.iter_mut().enumerate() {
+          *value = if plane == own {
+            mosaic[y * width + x]
+          } else {
+            (1..=max_radius).find_map(|r| {
+              let (mut sum, mut count) = (0.0_f32, 0_u32);
+              for ny in y.saturating_sub(r)..(y + r + 1).min(height) {
+                for nx in x.saturating_sub(r)..(x + r + 1).min(width) {
+                  if cfa.plane(nx, ny) == plane {
+                    sum += mosaic[ny * width + nx];
+                    count += 1;
+                  }
+                }
+              }
+              (count > 0).then(|| sum / count as f32)
+            }).unwrap_or(0.0)
+          };
+        }
+        for c in 0..3 {
+          row[x * 3 + c] = values.iter().zip(&projection).map(|(v, p)| v * p[c]).sum::<f32>();
+        }
+      }
+    });
+    Ok(out)
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use crate::tiff::{CFAPattern, CFAPatternDim};
+
+  #[test]
+  fn test_four_colors() {
+    use CFAPattern::{G, Unknown};
+    // Cyan, yellow / green, magenta.
+    let pattern = vec![Unknown(3), Unknown(5), G, Unknown(4)];
+    let mut raw = RawImage::new(6, 6, pattern.clone(), CFAPatternDim { width: 2, height: 2 });
+    raw.set_white_level(1000);
+    let (r, g, b) = (100, 300, 200);
+    for y in 0..6 {
+      for x in 0..6 {
+        raw.set(x, y, match pattern[(y % 2) * 2 + x % 2] {
+          Unknown(3) => g + b,
+          Unknown(5) => r + g,
+          Unknown(4) => r + b,
+          _ => g,
+        });
+      }
+    }
+    let rgb = Generic.demosaic(&raw).unwrap();
+    for v in rgb.data().chunks_exact(3) {
+      for (v, expected) in v.iter().zip([0.1, 0.3, 0.2]) {
+        assert!((v - expected).abs() < 1e-5, "{:?}", v);
+      }
+    }
+    let white = Cfa::new(&CFAPatternDim { width: 1, height: 1 }, &[Unknown(6)]).unwrap();
+    assert!(!Generic.supports(&white));
+  }
+}
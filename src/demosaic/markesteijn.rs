@@ -0,0 +1,246 @@
+/*
+Markesteijn demosaicing for Fuji X-Trans, one pass.
+
+Follows the outline of dcraw's xtrans_interpolate(): green is interpolated in four directions
+(horizontal, vertical and both diagonals) and clamped to the surrounding greens, red and blue follow
+from color differences to each green candidate, and each pixel averages the directions whose CIELab
+neighborhood is most homogeneous. Interpolation weights are plain linear ones rather than dcraw's
+tuned kernels.
+*/
+
+use crate::img::{Cfa, CfaKind, RawImage, RgbImage};
+use super::{by_strips, to_lab, Demosaic, Tile};
+
+const STRIP: usize = 48;
+// Rows of context: 3 for green, 2 for red and blue, 1 for the derivatives, 1 for homogeneity, 2 for the sums.
+const MARGIN: usize = 10;
+
+// Horizontal, vertical and the two diagonals.
+const DIRECTIONS: [(isize, isize); 4] = [(1, 0), (0, 1), (1, 1), (1, -1)];
+
+pub struct Markesteijn;
+
+impl Demosaic for Markesteijn {
+  fn name(&self) -> &'static str {
+    "markesteijn"
+  }
+
+  fn supports(&self, cfa: &Cfa) -> bool {
+    cfa.kind() == CfaKind::XTrans
+  }
+
+  fn demosaic(&self, raw: &RawImage) -> anyhow::Result<RgbImage> {
+    let cfa = raw.cfa()?;
+    if !self.supports(&cfa) {
+      return Err(anyhow::Error::msg(format!("Not an X-Trans pattern: {}", cfa)));
+    }
+    by_strips(raw, &cfa, STRIP, MARGIN, interpolate)
+  }
+}
+
+// Green at red and blue pixels along each direction, from the nearest green on either side.
+fn green(tile: &Tile) -> [Vec<f32>; 4] {
+  let mut green = [(); 4].map(|_| tile.mosaic.to_vec());
+  for y in 0..tile.height {
+    for x in 0..tile.width {
+      if tile.color(x, y) == 1 {
+        continue;
+      }
+      let (xi, yi) = (x as isize, y as isize);
+      let i = y * tile.width + x;
+      let (mut lo, mut hi) = (f32::MAX, f32::MIN);
+      for (dx, dy) in [(-1, -1), (0, -1), (1, -1), (-1, 0), (1, 0), (-1, 1), (0, 1), (1, 1)] {
+        if let Some(j) = tile.checked_idx(xi + dx, yi + dy) {
+          if tile.color((xi + dx) as usize, (yi + dy) as usize) == 1 {
+            lo = lo.min(tile.mosaic[j]);
+            hi = hi.max(tile.mosaic[j]);
+          }
+        }
+      }
+      for (d, (dx, dy)) in DIRECTIONS.iter().enumerate() {
+        // Value and distance of the nearest green towards `sign`.
+        let side = |sign: isize| -> Option<(f32, f32)> {
+          for k in 1..=3 {
+            let (nx, ny) = (xi + sign * k * dx, yi + sign * k * dy);
+            let j = tile.checked_idx(nx, ny)?;
+            if tile.color(nx as usize, ny as usize) == 1 {
+              return Some((tile.mosaic[j], k as f32));
+            }
+          }
+          None
+        };
+        let g = match (side(1), side(-1)) {
+          (Some((a, ka)), Some((b, kb))) => (a * kb + b * ka) / (ka + kb),
+          (Some((a, _)), None) | (None, Some((a, _))) => a,
+          (None, None) if lo <= hi => (lo + hi) / 2.0,
+          (None, None) => tile.mosaic[i],
+        };
+        // Every X-Trans red and blue pixel touches a green, so the clamp always applies away from the edges.
+        green[d][i] = if lo <= hi { g.clamp(lo, hi) } else { g };
+      }
+    }
+  }
+  green
+}
+
+// Red and blue from the mean color difference to `green` of the nearest pixels of that color.
+fn complete(tile: &Tile, green: &[f32]) -> Vec<[f32; 3]> {
+  let mut rgb = vec![[0.0_f32; 3]; tile.mosaic.len()];
+  for y in 0..tile.height {
+    for x in 0..tile.width {
+      let i = y * tile.width + x;
+      let own = tile.color(x, y);
+      let mut px = [0.0, green[i], 0.0];
+      px[own] = tile.mosaic[i];
+      for c in [0, 2] {
+        if c == own {
+          continue;
+        }
+        // X-Trans has every color within two pixels.
+        let diff = (1..=2).find_map(|r: isize| {
+          let (mut sum, mut count) = (0.0_f32, 0_u32);
+          for dy in -r..=r {
+            for dx in -r..=r {
+              let (nx, ny) = (x as isize + dx, y as isize + dy);
+              if let Some(j) = tile.checked_idx(nx, ny) {
+                if tile.color(nx as usize, ny as usize) == c {
+                  sum += tile.mosaic[j] - green[j];
+                  count += 1;
+                }
+              }
+            }
+          }
+          (count > 0).then(|| sum / count as f32)
+        }).unwrap_or(0.0);
+        px[c] = (green[i] + diff).clamp(0.0, 1.0);
+      }
+      rgb[i] = px;
+    }
+  }
+  rgb
+}
+
+fn interpolate(tile: &Tile, start: usize, out: &mut [f32]) {
+  let (width, height) = (tile.width, tile.height);
+  let green = green(tile);
+  let rgb = green.map(|it| complete(tile, &it));
+  let lab = rgb.each_ref().map(|it| it.iter().map(|px| to_lab(*px)).collect::<Vec<_>>());
+
+  // Squared CIELab change along each direction.
+  let mut derivative = [(); 4].map(|_| vec![0.0_f32; tile.mosaic.len()]);
+  for (d, (dx, dy)) in DIRECTIONS.iter().enumerate() {
+    for y in 0..height {
+      for x in 0..width {
+        let i = y * width + x;
+        for sign in [-1, 1] {
+          if let Some(j) = tile.checked_idx(x as isize + sign * dx, y as isize + sign * dy) {
+            derivative[d][i] += (0..3).map(|c| (lab[d][i][c] - lab[d][j][c]).powi(2)).sum::<f32>();
+          }
+        }
+      }
+    }
+  }
+
+  // Neighbors within 8 times the smallest derivative at the pixel, per direction.
+  let mut homogeneity = [(); 4].map(|_| vec![0_u8; tile.mosaic.len()]);
+  for y in 0..height {
+    for x in 0..width {
+      let i = y * width + x;
+      let threshold = derivative.iter().map(|it| it[i]).fold(f32::MAX, f32::min) * 8.0;
+      for (d, homogeneity) in homogeneity.iter_mut().enumerate() {
+        let mut count = 0_u8;
+        for dy in -1..=1 {
+          for dx in -1..=1 {
+            if let Some(j) = tile.checked_idx(x as isize + dx, y as isize + dy) {
+              count += (derivative[d][j] <= threshold) as u8;
+            }
+          }
+        }
+        homogeneity[i] = count;
+      }
+    }
+  }
+
+  for (row, out) in out.chunks_exact_mut(width * 3).enumerate() {
+    let y = start + row;
+    for x in 0..width {
+      let mut score = [0_u32; 4];
+      for dy in -2..=2 {
+        for dx in -2..=2 {
+          if let Some(j) = tile.checked_idx(x as isize + dx, y as isize + dy) {
+            for (d, score) in score.iter_mut().enumerate() {
+              *score += homogeneity[d][j] as u32;
+            }
+          }
+        }
+      }
+      // Directions close to the best one are averaged, as in dcraw.
+      let best = score.iter().copied().max().unwrap_or(0);
+      let threshold = best - best / 8;
+      let i = y * width + x;
+      let mut px = [0.0_f32; 3];
+      let mut count = 0;
+      for d in (0..4).filter(|d| score[*d] >= threshold) {
+        for c in 0..3 {
+          px[c] += rgb[d][i][c];
+        }
+        count += 1;
+      }
+      out[x * 3..x * 3 + 3].copy_from_slice(&px.map(|it| it / count as f32));
+    }
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use crate::demosaic::Generic;
+  use crate::tiff::{CFAPattern, CFAPatternDim};
+
+  #[test]
+  fn test_xtrans() {
+    use CFAPattern::{B, G, R};
+    let pattern = vec![
+      G, G, R, G, G, B,
+      G, G, B, G, G, R,
+      B, R, G, R, B, G,
+      G, G, B, G, G, R,
+      G, G, R, G, G, B,
+      R, B, G, B, R, G,
+    ];
+    let (width, height) = (24, 60);
+    let mosaic = |truth: &dyn Fn(usize, usize) -> [f32; 3]| {
+      let mut raw = RawImage::new(width, height, pattern.clone(), CFAPatternDim { width: 6, height: 6 });
+      raw.set_white_level(1000);
+      for y in 0..height {
+        for x in 0..width {
+          let c = match pattern[(y % 6) * 6 + x % 6] { R => 0, G => 1, _ => 2 };
+          raw.set(x, y, (truth(x, y)[c] * 1000.0).round() as u16);
+        }
+      }
+      raw
+    };
+    let max_error = |rgb: &RgbImage, truth: &dyn Fn(usize, usize) -> [f32; 3]| {
+      let mut error = 0.0_f32;
+      for y in 0..height {
+        for x in 0..width {
+          for (v, t) in rgb.get(x, y).iter().zip(truth(x, y)) {
+            error = error.max((v - t).abs());
+          }
+        }
+      }
+      error
+    };
+
+    let flat = |_: usize, _: usize| [0.2, 0.5, 0.7];
+    let error = max_error(&Markesteijn.demosaic(&mosaic(&flat)).unwrap(), &flat);
+    assert!(error < 1e-5, "error {} in a flat area", error);
+
+    // A diagonal gray edge.
+    let edge = |x: usize, y: usize| if x + y < 40 { [0.2; 3] } else { [0.8; 3] };
+    let raw = mosaic(&edge);
+    let error = max_error(&Markesteijn.demosaic(&raw).unwrap(), &edge);
+    let generic = max_error(&Generic.demosaic(&raw).unwrap(), &edge);
+    assert!(error < generic, "error {} at an edge, generic {}", error, generic);
+  }
+}
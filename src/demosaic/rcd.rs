@@ -0,0 +1,182 @@
+/*
+Ratio Corrected Demosaicing.
+
+Luis Sanz Rodríguez, RCD v2.3 (https://github.com/LuisSR/RCD-Demosaicing), as in librtprocess.
+Green is interpolated along the direction with less high-pass energy, from neighbors scaled by the ratio
+of a low-pass filtered mosaic. Red and blue follow from color differences along the smoother diagonal
+or axis. Borders are handled by mirroring instead of a bilinear fallback.
+*/
+
+use crate::img::{Cfa, CfaKind, RawImage, RgbImage};
+use super::{bayer_cfa, by_strips, Demosaic, Tile};
+
+const STRIP: usize = 64;
+// Context rows the steps add up to: 4 for the direction, 4 for green, 4 for red and blue.
+const MARGIN: usize = 12;
+
+const EPS: f32 = 1e-5;
+const EPSSQ: f32 = 1e-10;
+
+pub struct Rcd;
+
+impl Demosaic for Rcd {
+  fn name(&self) -> &'static str {
+    "rcd"
+  }
+
+  fn supports(&self, cfa: &Cfa) -> bool {
+    cfa.kind() == CfaKind::Bayer
+  }
+
+  fn demosaic(&self, raw: &RawImage) -> anyhow::Result<RgbImage> {
+    by_strips(raw, &bayer_cfa(raw)?, STRIP, MARGIN, interpolate)
+  }
+}
+
+// The local value unless the neighborhood is more decided.
+fn discriminate(central: f32, neighbourhood: f32) -> f32 {
+  if (0.5 - central).abs() < (0.5 - neighbourhood).abs() { neighbourhood } else { central }
+}
+
+// Squared high-pass filter along (dx, dy), applied to every pixel.
+fn high_pass(tile: &Tile, dx: isize, dy: isize) -> Vec<f32> {
+  let mut out = vec![0.0_f32; tile.mosaic.len()];
+  for y in 0..tile.height {
+    for x in 0..tile.width {
+      let c = |k: isize| tile.get(x as isize + k * dx, y as isize + k * dy);
+      let v = (c(-3) - c(-1) - c(1) + c(3)) - 3.0 * (c(-2) + c(2)) + 6.0 * c(0);
+      out[y * tile.width + x] = v * v;
+    }
+  }
+  out
+}
+
+// Ratio of the energy along (dx, dy) to the total, from two high-pass maps summed over three pixels.
+fn direction(tile: &Tile, a: &[f32], (adx, ady): (isize, isize), b: &[f32], (bdx, bdy): (isize, isize)) -> Vec<f32> {
+  let mut out = vec![0.0_f32; tile.mosaic.len()];
+  for y in 0..tile.height {
+    for x in 0..tile.width {
+      let (xi, yi) = (x as isize, y as isize);
+      let i = y * tile.width + x;
+      let a_stat = EPSSQ.max(a[tile.idx(xi - adx, yi - ady)] + a[i] + a[tile.idx(xi + adx, yi + ady)]);
+      let b_stat = EPSSQ.max(b[tile.idx(xi - bdx, yi - bdy)] + b[i] + b[tile.idx(xi + bdx, yi + bdy)]);
+      out[i] = a_stat / (a_stat + b_stat);
+    }
+  }
+  out
+}
+
+fn interpolate(tile: &Tile, start: usize, out: &mut [f32]) {
+  let (width, height) = (tile.width, tile.height);
+  let cfa = tile.mosaic;
+  let at = |x: usize, y: usize, dx: isize, dy: isize| tile.idx(x as isize + dx, y as isize + dy);
+  let c = |x: usize, y: usize, dx: isize, dy: isize| cfa[at(x, y, dx, dy)];
+
+  // Step 1: vertical and horizontal discrimination. Near 0 favors vertical, near 1 horizontal.
+  let vh_dir = direction(tile, &high_pass(tile, 0, 1), (0, 1), &high_pass(tile, 1, 0), (1, 0));
+
+  // Step 2: low-pass filter of the mosaic at red and blue pixels.
+  let mut lpf = vec![0.0_f32; cfa.len()];
+  for y in 0..height {
+    for x in 0..width {
+      if tile.color(x, y) != 1 {
+        lpf[y * width + x] = c(x, y, 0, 0) +
+          0.5 * (c(x, y, 0, -1) + c(x, y, 0, 1) + c(x, y, -1, 0) + c(x, y, 1, 0)) +
+          0.25 * (c(x, y, -1, -1) + c(x, y, 1, -1) + c(x, y, -1, 1) + c(x, y, 1, 1));
+      }
+    }
+  }
+
+  let mut rgb = [vec![0.0_f32; cfa.len()], vec![0.0_f32; cfa.len()], vec![0.0_f32; cfa.len()]];
+  for y in 0..height {
+    for x in 0..width {
+      rgb[tile.color(x, y)][y * width + x] = cfa[y * width + x];
+    }
+  }
+
+  // Step 3: green at red and blue pixels.
+  for y in 0..height {
+    for x in 0..width {
+      if tile.color(x, y) == 1 {
+        continue;
+      }
+      let i = y * width + x;
+      let neighbourhood = 0.25 * (vh_dir[at(x, y, -1, -1)] + vh_dir[at(x, y, 1, -1)] + vh_dir[at(x, y, -1, 1)] + vh_dir[at(x, y, 1, 1)]);
+      let disc = discriminate(vh_dir[i], neighbourhood);
+      // Gradient and ratio-corrected estimate towards (dx, dy).
+      let grad = |dx: isize, dy: isize| EPS +
+        (c(x, y, -dx, -dy) - c(x, y, dx, dy)).abs() + (c(x, y, 0, 0) - c(x, y, 2 * dx, 2 * dy)).abs() +
+        (c(x, y, dx, dy) - c(x, y, 3 * dx, 3 * dy)).abs() + (c(x, y, 2 * dx, 2 * dy) - c(x, y, 4 * dx, 4 * dy)).abs();
+      let est = |dx: isize, dy: isize| {
+        let far = lpf[at(x, y, 2 * dx, 2 * dy)];
+        c(x, y, dx, dy) * (1.0 + (lpf[i] - far) / (EPS + lpf[i] + far))
+      };
+      let (n_grad, s_grad, w_grad, e_grad) = (grad(0, -1), grad(0, 1), grad(-1, 0), grad(1, 0));
+      let v_est = (s_grad * est(0, -1) + n_grad * est(0, 1)) / (n_grad + s_grad);
+      let h_est = (w_grad * est(1, 0) + e_grad * est(-1, 0)) / (e_grad + w_grad);
+      rgb[1][i] = (disc * h_est + (1.0 - disc) * v_est).clamp(0.0, 1.0);
+    }
+  }
+
+  // Step 4.1: diagonal discrimination. Near 0 favors the P (\) diagonal, near 1 the Q (/) one.
+  let pq_dir = direction(tile, &high_pass(tile, 1, 1), (1, 1), &high_pass(tile, -1, 1), (-1, 1));
+
+  // Step 4.2: red at blue pixels and blue at red ones.
+  for y in 0..height {
+    for x in 0..width {
+      let own = tile.color(x, y);
+      if own == 1 {
+        continue;
+      }
+      let i = y * width + x;
+      let neighbourhood = 0.25 * (pq_dir[at(x, y, -1, -1)] + pq_dir[at(x, y, 1, -1)] + pq_dir[at(x, y, -1, 1)] + pq_dir[at(x, y, 1, 1)]);
+      let disc = discriminate(pq_dir[i], neighbourhood);
+      let other = 2 - own;
+      let (plane, green) = (&rgb[other], &rgb[1]);
+      let grad = |dx: isize, dy: isize| EPS +
+        (plane[at(x, y, dx, dy)] - plane[at(x, y, -dx, -dy)]).abs() +
+        (plane[at(x, y, dx, dy)] - plane[at(x, y, 3 * dx, 3 * dy)]).abs() +
+        (green[i] - green[at(x, y, 2 * dx, 2 * dy)]).abs();
+      let est = |dx: isize, dy: isize| plane[at(x, y, dx, dy)] - green[at(x, y, dx, dy)];
+      let (nw_grad, ne_grad, sw_grad, se_grad) = (grad(-1, -1), grad(1, -1), grad(-1, 1), grad(1, 1));
+      let p_est = (nw_grad * est(1, 1) + se_grad * est(-1, -1)) / (nw_grad + se_grad);
+      let q_est = (ne_grad * est(-1, 1) + sw_grad * est(1, -1)) / (ne_grad + sw_grad);
+      let v = (green[i] + (1.0 - disc) * p_est + disc * q_est).clamp(0.0, 1.0);
+      rgb[other][i] = v;
+    }
+  }
+
+  // Step 4.3: red and blue at green pixels.
+  for y in 0..height {
+    for x in 0..width {
+      if tile.color(x, y) != 1 {
+        continue;
+      }
+      let i = y * width + x;
+      let neighbourhood = 0.25 * (vh_dir[at(x, y, -1, -1)] + vh_dir[at(x, y, 1, -1)] + vh_dir[at(x, y, -1, 1)] + vh_dir[at(x, y, 1, 1)]);
+      let disc = discriminate(vh_dir[i], neighbourhood);
+      for channel in [0, 2] {
+        let (plane, green) = (&rgb[channel], &rgb[1]);
+        let grad = |dx: isize, dy: isize| EPS +
+          (green[i] - green[at(x, y, 2 * dx, 2 * dy)]).abs() +
+          (plane[at(x, y, dx, dy)] - plane[at(x, y, -dx, -dy)]).abs() +
+          (plane[at(x, y, dx, dy)] - plane[at(x, y, 3 * dx, 3 * dy)]).abs();
+        let est = |dx: isize, dy: isize| plane[at(x, y, dx, dy)] - green[at(x, y, dx, dy)];
+        let (n_grad, s_grad, w_grad, e_grad) = (grad(0, -1), grad(0, 1), grad(-1, 0), grad(1, 0));
+        let v_est = (n_grad * est(0, 1) + s_grad * est(0, -1)) / (n_grad + s_grad);
+        let h_est = (e_grad * est(-1, 0) + w_grad * est(1, 0)) / (e_grad + w_grad);
+        let v = (green[i] + (1.0 - disc) * v_est + disc * h_est).clamp(0.0, 1.0);
+        // Green pixels only read red and blue at red and blue pixels, so this can be written in place.
+        rgb[channel][i] = v;
+      }
+    }
+  }
+
+  for (row, out) in out.chunks_exact_mut(width * 3).enumerate() {
+    let y = start + row;
+    for x in 0..width {
+      let i = y * width + x;
+      out[x * 3..x * 3 + 3].copy_from_slice(&[rgb[0][i], rgb[1][i], rgb[2][i]]);
+    }
+  }
+}
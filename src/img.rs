@@ -1,16 +1,108 @@
-use std::cmp::min;
-use std::fs::File;
-use std::io::BufWriter;
-use std::path::Path;
-use png::BitDepth;
+use serde::Deserialize;
+use crate::color::CameraColor;
 use crate::tiff::{CFAPatternDim, CFAPattern};
 
+mod optical_black;
+mod bad_pixels;
+mod cfa;
+mod rgb;
+mod scale;
+
+pub use bad_pixels::BadPixelMap;
+pub use cfa::{Cfa, CfaKind};
+pub use rgb::RgbImage;
+pub use scale::Scale;
+
+// Rectangle in sensor pixels.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct Rect {
+  pub x: u32,
+  pub y: u32,
+  pub width: u32,
+  pub height: u32,
+}
+
+impl Rect {
+  // From DNG's (top, left, bottom, right).
+  pub fn from_tlbr(top: u32, left: u32, bottom: u32, right: u32) -> Self {
+    Self {
+      x: left,
+      y: top,
+      width: right.saturating_sub(left),
+      height: bottom.saturating_sub(top),
+    }
+  }
+  // Edges past the last column and row, stopping at u32::MAX.
+  pub fn right(&self) -> u32 {
+    self.x.saturating_add(self.width)
+  }
+  pub fn bottom(&self) -> u32 {
+    self.y.saturating_add(self.height)
+  }
+  pub fn intersect(&self, other: &Rect) -> Self {
+    let x = self.x.max(other.x);
+    let y = self.y.max(other.y);
+    let right = self.right().min(other.right());
+    let bottom = self.bottom().min(other.bottom());
+    Self::from_tlbr(y, x, bottom.max(y), right.max(x))
+  }
+  // From "x,y,width,height".
+  pub fn parse(text: &str) -> anyhow::Result<Self> {
+    let values = text.split(',')
+      .map(|it| it.trim().parse::<u32>())
+      .collect::<Result<Vec<u32>, _>>();
+    match values.as_deref() {
+      Ok([x, y, width, height]) if x.checked_add(*width).is_some() && y.checked_add(*height).is_some() =>
+        Ok(Self { x: *x, y: *y, width: *width, height: *height }),
+      Ok([_, _, _, _]) => Err(anyhow::Error::msg(format!("{:?} ends past {} pixels", text, u32::MAX))),
+      _ => Err(anyhow::Error::msg(format!("Expected \"x,y,width,height\", got {:?}", text))),
+    }
+  }
+  // Grown by `margin` on every side, stopping at 0 and u32::MAX.
+  pub fn expand(&self, margin: u32) -> Self {
+    let x = self.x.saturating_sub(margin);
+    let y = self.y.saturating_sub(margin);
+    Self::from_tlbr(y, x, self.bottom().saturating_add(margin), self.right().saturating_add(margin))
+  }
+  pub fn contains(&self, x: u32, y: u32) -> bool {
+    x >= self.x && y >= self.y && x < self.right() && y < self.bottom()
+  }
+  // Clipped to a width x height image.
+  pub fn clip(&self, width: usize, height: usize) -> Self {
+    let x = (self.x as usize).min(width);
+    let y = (self.y as usize).min(height);
+    Self {
+      x: x as u32,
+      y: y as u32,
+      width: (self.width as usize).min(width - x) as u32,
+      height: (self.height as usize).min(height - y) as u32,
+    }
+  }
+}
+
 pub struct RawImage {
   width: usize,
   height: usize,
   data: Vec<u16>,
   cfa_pattern: Vec<CFAPattern>,
   cfa_dim: CFAPatternDim,
+  // One per CFA position, row-major.
+  black_levels: Vec<u16>,
+  // Optional per-row and per-column additions to the black levels, from optical black.
+  black_row_offsets: Vec<f32>,
+  black_col_offsets: Vec<f32>,
+  white_level: u16,
+  // Optical black areas outside the active area.
+  masked_areas: Vec<Rect>,
+  // Area of the sensor holding image data. The whole image if None.
+  active_area: Option<Rect>,
+  // Area to show by default, in sensor coordinates. The active area if None.
+  default_crop: Option<Rect>,
+  // Known defects, e.g. from the file; detected ones are not added here.
+  bad_pixels: BadPixelMap,
+  // White balance and color matrices from the file or the camera database.
+  color: CameraColor,
 }
 
 impl RawImage {
@@ -24,6 +116,15 @@ impl RawImage {
       width,
       height,
       data: vec![0; width * height],
+      black_levels: vec![0; cfa_dim.width * cfa_dim.height],
+      black_row_offsets: Vec::new(),
+      black_col_offsets: Vec::new(),
+      white_level: u16::MAX,
+      masked_areas: Vec::new(),
+      active_area: None,
+      default_crop: None,
+      bad_pixels: BadPixelMap::default(),
+      color: CameraColor::default(),
       cfa_pattern,
       cfa_dim,
     }
@@ -34,106 +135,240 @@ impl RawImage {
   pub fn height(&self) -> usize {
     self.height
   }
+  #[cfg(test)]
   pub fn data(&self) -> &Vec<u16> {
     &self.data
   }
+  pub fn data_mut(&mut self) -> &mut [u16] {
+    &mut self.data
+  }
   fn calc_idx(&self, x: usize, y: usize) -> usize {
     self.width * y + x
   }
+  fn cfa_idx(&self, x: usize, y: usize) -> usize {
+    (y % self.cfa_dim.height) * self.cfa_dim.width + (x % self.cfa_dim.width)
+  }
+  #[cfg(test)]
+  pub fn cfa_pattern(&self) -> &[CFAPattern] {
+    &self.cfa_pattern
+  }
+  pub fn cfa(&self) -> anyhow::Result<Cfa> {
+    Cfa::new(&self.cfa_dim, &self.cfa_pattern)
+  }
+  pub fn cfa_dim(&self) -> &CFAPatternDim {
+    &self.cfa_dim
+  }
 
-  pub fn set(&mut self, x: usize, y: usize, v: u16) {
-    let idx = self.calc_idx(x,y);
-    self.data[idx] = v;
+  pub fn black_levels(&self) -> &[u16] {
+    &self.black_levels
+  }
+  pub fn white_level(&self) -> u16 {
+    self.white_level
+  }
+  // One level for every position, or one per CFA position in row-major order.
+  pub fn set_black_levels(&mut self, levels: &[u16]) -> anyhow::Result<()> {
+    let n = self.cfa_dim.width * self.cfa_dim.height;
+    self.black_levels = match levels.len() {
+      1 => vec![levels[0]; n],
+      len if len == n => levels.to_vec(),
+      len => return Err(anyhow::Error::msg(format!("{} black levels for a CFA of {} colors", len, n))),
+    };
+    Ok(())
+  }
+  // Levels given as R, G, G, B, e.g. Sony SR2 and DNG-style camera databases.
+  pub fn set_black_levels_rggb(&mut self, levels: [u16; 4]) {
+    let mut greens = 0;
+    self.black_levels = self.cfa_pattern.iter().map(|c| match c {
+      CFAPattern::R => levels[0],
+      CFAPattern::G => {
+        greens += 1;
+        if greens == 1 { levels[1] } else { levels[2] }
+      }
+      CFAPattern::B => levels[3],
+      CFAPattern::Unknown(_) => levels[1],
+    }).collect();
+  }
+  pub fn set_white_level(&mut self, level: u16) {
+    self.white_level = level;
   }
 
-  pub fn get(&self, x: usize, y: usize) -> (u16, u16, u16) {
-    let idx = self.calc_idx(x,y);
-    let color = self.data[idx] << 4;
-    let row = y % self.cfa_dim.height;
-    let col = x % self.cfa_dim.width;
-    match self.cfa_pattern[row * self.cfa_dim.width + col] {
-      CFAPattern::R => (color, 0, 0),
-      CFAPattern::G => (0, color, 0),
-      CFAPattern::B => (0, 0, color),
-      CFAPattern::Unknown(_) => (color, color, color),
-    }
+  #[cfg(test)]
+  pub fn masked_areas(&self) -> &[Rect] {
+    &self.masked_areas
+  }
+  pub fn set_masked_areas(&mut self, areas: Vec<Rect>) {
+    self.masked_areas = areas;
   }
 
-  pub fn get_mixed(&self, x: usize, y: usize) -> (u16, u16, u16) {
-    // FIXME: better de-noising
-    let mut colors = [Vec::<u16>::new(), Vec::<u16>::new(), Vec::<u16>::new()];
-    for dy in 0..self.cfa_dim.height {
-      for dx in 0..self.cfa_dim.width {
-        let x = min(x + dx, self.width - 1);
-        let y = min(y + dy, self.height - 1);
-        let idx = self.calc_idx(x, y);
-        let color = self.data[idx] << 4;
-        match self.cfa_pattern[(y % self.cfa_dim.height) * self.cfa_dim.width + (x % self.cfa_dim.width)] {
-          CFAPattern::R => colors[0].push(color),
-          CFAPattern::G => colors[1].push(color),
-          CFAPattern::B => colors[2].push(color),
-          CFAPattern::Unknown(_) => {
-            colors[0].push(color);
-            colors[1].push(color);
-            colors[2].push(color);
-          },
-        };
+  pub fn set_active_area(&mut self, area: Option<Rect>) {
+    self.active_area = area;
+  }
+  pub fn set_default_crop(&mut self, crop: Option<Rect>) {
+    self.default_crop = crop;
+  }
+  pub fn bad_pixels(&self) -> &BadPixelMap {
+    &self.bad_pixels
+  }
+  pub fn set_bad_pixels(&mut self, map: BadPixelMap) {
+    self.bad_pixels = map;
+  }
+  pub fn color(&self) -> &CameraColor {
+    &self.color
+  }
+  pub fn set_color(&mut self, color: CameraColor) {
+    self.color = color;
+  }
+
+  // The default crop within the active area, clipped to the image.
+  pub fn visible_area(&self) -> Rect {
+    let full = Rect { x: 0, y: 0, width: self.width as u32, height: self.height as u32 };
+    let active = self.active_area.unwrap_or(full).clip(self.width, self.height);
+    match self.default_crop {
+      Some(crop) => crop.intersect(&active),
+      None => active,
+    }
+  }
+  // A copy holding only `visible_area()`, with the CFA pattern and levels shifted to match.
+  pub fn cropped(&self) -> RawImage {
+    self.cropped_to(&self.visible_area())
+  }
+  // A copy holding only `rect`, clipped to the image. The CFA pattern and levels are shifted to match,
+  // and areas and bad pixels move to the new coordinates.
+  pub fn cropped_to(&self, rect: &Rect) -> RawImage {
+    let area = rect.clip(self.width, self.height);
+    let (ox, oy) = (area.x as usize, area.y as usize);
+    let (width, height) = (area.width as usize, area.height as usize);
+    let (cw, ch) = (self.cfa_dim.width, self.cfa_dim.height);
+    let mut cfa_pattern = Vec::<CFAPattern>::with_capacity(cw * ch);
+    let mut black_levels = Vec::<u16>::with_capacity(cw * ch);
+    for y in 0..ch {
+      for x in 0..cw {
+        let idx = self.cfa_idx(x + ox, y + oy);
+        cfa_pattern.push(self.cfa_pattern[idx].clone());
+        black_levels.push(self.black_levels[idx]);
       }
     }
+    let mut data = Vec::<u16>::with_capacity(width * height);
+    for y in oy..oy + height {
+      data.extend_from_slice(&self.data[self.calc_idx(ox, y)..self.calc_idx(ox + width, y)]);
+    }
+    let slice = |offsets: &Vec<f32>, start: usize, len: usize| -> Vec<f32> {
+      offsets.get(start..start + len).map(|it| it.to_vec()).unwrap_or_default()
+    };
+    let shift = |r: &Rect| {
+      let r = r.intersect(&area);
+      Rect { x: r.x - area.x, y: r.y - area.y, ..r }
+    };
+    let mut bad_pixels = BadPixelMap::default();
+    for (x, y) in self.bad_pixels.iter().filter(|(x, y)| area.contains(*x, *y)) {
+      bad_pixels.insert(x - area.x, y - area.y);
+    }
+    RawImage {
+      width,
+      height,
+      data,
+      cfa_pattern,
+      cfa_dim: self.cfa_dim.clone(),
+      black_levels,
+      black_row_offsets: slice(&self.black_row_offsets, oy, height),
+      black_col_offsets: slice(&self.black_col_offsets, ox, width),
+      white_level: self.white_level,
+      masked_areas: self.masked_areas.iter().map(shift).filter(|it| it.width > 0 && it.height > 0).collect(),
+      active_area: self.active_area.as_ref().map(shift),
+      default_crop: self.default_crop.as_ref().map(shift),
+      bad_pixels,
+      color: self.color.clone(),
+    }
+  }
 
-    fn average_color(colors: &Vec<u16>) -> u16 {
-      let mut sum = 0.0_f32;
-      let count = colors.len() as f32;
-      for color in colors {
-        sum += *color as f32 / 65535.0;
+  // Black level at (x, y), including row and column offsets.
+  pub fn black_level(&self, x: usize, y: usize) -> f32 {
+    self.black_levels[self.cfa_idx(x, y)] as f32 +
+      self.black_row_offsets.get(y).copied().unwrap_or(0.0) +
+      self.black_col_offsets.get(x).copied().unwrap_or(0.0)
+  }
+
+  // Value at (x, y) scaled to 0-1 between its black level and the white level.
+  pub fn normalized(&self, x: usize, y: usize) -> f32 {
+    let black = self.black_level(x, y);
+    let range = (self.white_level as f32 - black).max(1.0);
+    ((self.data[self.calc_idx(x, y)] as f32 - black) / range).clamp(0.0, 1.0)
+  }
+  // Linear 0-1 mosaic, row-major.
+  pub fn normalize(&self) -> Vec<f32> {
+    let mut out = Vec::<f32>::with_capacity(self.data.len());
+    for y in 0..self.height {
+      for x in 0..self.width {
+        out.push(self.normalized(x, y));
       }
-      let avg = sum / count;
-      (avg.powf(1.0/2.2) * 65535.0) as u16
     }
-    let r = average_color(&colors[0]);
-    let g = average_color(&colors[1]);
-    let b = average_color(&colors[2]);
-    (r as u16, g as u16, b as u16)
+    out
   }
 
-  pub fn save_to_file(&self, path: impl AsRef<Path>, high_bits: bool) -> anyhow::Result<()> {
-    let file = File::create(path)?;
-    let mut writer = BufWriter::new(file);
-    self.save(writer, high_bits)
+  #[cfg(test)]
+  pub fn set(&mut self, x: usize, y: usize, v: u16) {
+    let idx = self.calc_idx(x,y);
+    self.data[idx] = v;
   }
 
-  pub fn save<F: std::io::Write>(&self, writer: BufWriter<F>, high_bits: bool) -> anyhow::Result<()> {
-    let mut encoder = png::Encoder::new(writer, self.width as u32, self.height as u32); // Width is 2 pixels and height is 1.
-    encoder.set_color(png::ColorType::Rgb);
-    if high_bits {
-      encoder.set_depth(BitDepth::Sixteen);
-    } else {
-      encoder.set_depth(BitDepth::Eight);
-    }
-    let mut writer = encoder.write_header()?;
-    let pixels = self.create_pixels(high_bits);
-    writer.write_image_data(&pixels).map_err(|it| anyhow::Error::from(it))
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  #[test]
+  fn test_normalize() {
+    let pattern = vec![CFAPattern::R, CFAPattern::G, CFAPattern::G, CFAPattern::B];
+    let mut img = RawImage::new(2, 2, pattern, CFAPatternDim { width: 2, height: 2 });
+    img.set_black_levels_rggb([100, 200, 300, 400]);
+    img.set_white_level(1100);
+    img.set(0, 0, 600);
+    img.set(1, 0, 100);
+    img.set(0, 1, 1100);
+    img.set(1, 1, 2000);
+    assert_eq!(img.black_levels(), &[100, 200, 300, 400]);
+    assert_eq!(img.normalize(), vec![0.5, 0.0, 1.0, 1.0]);
+    assert!(img.set_black_levels(&[1, 2]).is_err());
   }
 
-  fn create_pixels(&self, high_bits: bool) -> Vec<u8> {
-    let mut buff = Vec::<u8>::new();
-    for y in 0..self.height {
-      for x in 0..self.width {
-        let (r, g, b) = self.get_mixed(x, y);
-        if high_bits {
-          buff.push((r >> 8) as u8);
-          buff.push((r & 0xff) as u8);
-          buff.push((g >> 8) as u8);
-          buff.push((g & 0xff) as u8);
-          buff.push((b >> 8) as u8);
-          buff.push((b & 0xff) as u8);
-        } else {
-          buff.push((r >> 8) as u8);
-          buff.push((g >> 8) as u8);
-          buff.push((b >> 8) as u8);
-        }
+  #[test]
+  fn test_cropped() {
+    let pattern = vec![CFAPattern::R, CFAPattern::G, CFAPattern::G, CFAPattern::B];
+    let mut img = RawImage::new(6, 5, pattern, CFAPatternDim { width: 2, height: 2 });
+    for y in 0..5 {
+      for x in 0..6 {
+        img.set(x, y, (y * 10 + x) as u16);
       }
     }
-    buff
+    img.set_black_levels_rggb([1, 2, 3, 4]);
+    img.set_active_area(Some(Rect { x: 1, y: 0, width: 5, height: 5 }));
+    img.set_default_crop(Some(Rect { x: 1, y: 1, width: 3, height: 10 }));
+    assert_eq!(img.visible_area(), Rect { x: 1, y: 1, width: 3, height: 4 });
+    let cropped = img.cropped();
+    assert_eq!((cropped.width(), cropped.height()), (3, 4));
+    assert_eq!(cropped.data()[0], 11);
+    assert_eq!(cropped.data()[3], 21);
+    // (1, 1) is B in the original pattern.
+    assert!(matches!(cropped.cfa_pattern()[0], CFAPattern::B));
+    assert_eq!(cropped.black_levels(), &[4, 3, 2, 1]);
+
+    img.set_masked_areas(vec![Rect { x: 0, y: 0, width: 1, height: 5 }]);
+    let roi = img.cropped_to(&Rect::parse("0,2,4,2").unwrap());
+    assert_eq!(roi.masked_areas(), &[Rect { x: 0, y: 0, width: 1, height: 2 }]);
+    assert_eq!(roi.visible_area(), Rect { x: 1, y: 0, width: 3, height: 2 });
+    assert!(Rect::parse("1,2,3").is_err());
+  }
+
+  #[test]
+  fn test_rect_bounds() {
+    let edge = Rect { x: u32::MAX - 2, y: 5, width: 2, height: 10 };
+    assert_eq!(edge.expand(16), Rect { x: u32::MAX - 18, y: 0, width: 18, height: 31 });
+    assert!(edge.contains(u32::MAX - 1, 14) && !edge.contains(u32::MAX, 14));
+    let huge = Rect { x: 4, y: 4, width: u32::MAX, height: u32::MAX };
+    assert_eq!(huge.intersect(&edge), Rect { x: u32::MAX - 2, y: 5, width: 2, height: 10 });
+    assert_eq!(Rect::parse("4294967290,0,5,1").unwrap().right(), u32::MAX);
+    assert!(Rect::parse("4294967290,0,6,1").is_err());
+    assert!(Rect::parse("0,1,1,4294967295").is_err());
   }
 }
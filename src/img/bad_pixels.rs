@@ -0,0 +1,188 @@
+// Stuck, hot and dead pixel detection and interpolation over same-color CFA neighbors.
+//
+// Map files are plain text with one "x y" pair per line in sensor pixels; `#` starts a comment.
+
+use std::collections::BTreeSet;
+use std::path::Path;
+use super::{RawImage, Rect};
+
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct BadPixelMap {
+  // (x, y) in sensor pixels, sorted by row.
+  pixels: BTreeSet<(u32, u32)>,
+}
+
+impl BadPixelMap {
+  pub fn parse(text: &str) -> anyhow::Result<Self> {
+    let mut map = Self::default();
+    for (i, line) in text.lines().enumerate() {
+      let line = line.split('#').next().unwrap_or_default().trim();
+      if line.is_empty() {
+        continue;
+      }
+      let coords = line.split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|it| !it.is_empty())
+        .map(|it| it.parse::<u32>())
+        .collect::<Result<Vec<u32>, _>>();
+      match coords.as_deref() {
+        Ok([x, y]) => map.insert(*x, *y),
+        _ => return Err(anyhow::Error::msg(format!("Line {}: expected \"x y\", got {:?}", i + 1, line))),
+      }
+    }
+    Ok(map)
+  }
+
+  pub fn load(path: impl AsRef<Path>) -> anyhow::Result<Self> {
+    let path = path.as_ref();
+    let text = std::fs::read_to_string(path)
+      .map_err(|err| anyhow::Error::msg(format!("{}: {}", path.display(), err)))?;
+    Self::parse(&text).map_err(|err| anyhow::Error::msg(format!("{}: {}", path.display(), err)))
+  }
+
+  pub fn save(&self, path: impl AsRef<Path>) -> anyhow::Result<()> {
+    std::fs::write(path, self.to_string())?;
+    Ok(())
+  }
+
+  pub fn insert(&mut self, x: u32, y: u32) {
+    // Row first, so files list pixels in reading order.
+    self.pixels.insert((y, x));
+  }
+
+  // Every pixel of `rect`, which should be clipped to the image first.
+  pub fn insert_rect(&mut self, rect: &Rect) {
+    for y in rect.y..rect.y.saturating_add(rect.height) {
+      for x in rect.x..rect.x.saturating_add(rect.width) {
+        self.insert(x, y);
+      }
+    }
+  }
+
+  pub fn extend(&mut self, other: &BadPixelMap) {
+    self.pixels.extend(other.pixels.iter().copied());
+  }
+
+  pub fn contains(&self, x: u32, y: u32) -> bool {
+    self.pixels.contains(&(y, x))
+  }
+
+  pub fn len(&self) -> usize {
+    self.pixels.len()
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.pixels.is_empty()
+  }
+
+  // (x, y) pairs.
+  pub fn iter(&self) -> impl Iterator<Item = (u32, u32)> + '_ {
+    self.pixels.iter().map(|(y, x)| (*x, *y))
+  }
+}
+
+impl std::fmt::Display for BadPixelMap {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    writeln!(f, "# x y")?;
+    for (x, y) in self.iter() {
+      writeln!(f, "{} {}", x, y)?;
+    }
+    Ok(())
+  }
+}
+
+impl RawImage {
+  // Same-color neighbors one CFA period away in each of the 8 directions.
+  fn same_color_neighbors(&self, x: usize, y: usize) -> impl Iterator<Item = (usize, usize)> + '_ {
+    let (cw, ch) = (self.cfa_dim.width as isize, self.cfa_dim.height as isize);
+    let (width, height) = (self.width as isize, self.height as isize);
+    [(-1, -1), (0, -1), (1, -1), (-1, 0), (1, 0), (-1, 1), (0, 1), (1, 1)].into_iter()
+      .map(move |(dx, dy)| (x as isize + dx * cw, y as isize + dy * ch))
+      .filter(move |(x, y)| *x >= 0 && *y >= 0 && *x < width && *y < height)
+      .map(|(x, y)| (x as usize, y as usize))
+  }
+
+  // Pixels in `area` standing out from every same-color neighbor by more than `threshold`,
+  // as a fraction of the range between black and white.
+  pub fn detect_bad_pixels(&self, area: &Rect, threshold: f32) -> BadPixelMap {
+    let area = area.clip(self.width, self.height);
+    let mut map = BadPixelMap::default();
+    for y in area.y as usize..(area.y + area.height) as usize {
+      for x in area.x as usize..(area.x + area.width) as usize {
+        let (mut min, mut max, mut count) = (f32::MAX, f32::MIN, 0);
+        for (nx, ny) in self.same_color_neighbors(x, y) {
+          let v = self.normalized(nx, ny);
+          min = min.min(v);
+          max = max.max(v);
+          count += 1;
+        }
+        // Corners of tiny images don't have enough neighbors to tell.
+        if count < 3 {
+          continue;
+        }
+        let v = self.normalized(x, y);
+        if v - max > threshold || min - v > threshold {
+          map.insert(x as u32, y as u32);
+        }
+      }
+    }
+    map
+  }
+
+  // Replaces each pixel in `map` with the mean of its good same-color neighbors.
+  // Returns the number of pixels fixed; ones without a good neighbor are left as they are.
+  pub fn fix_bad_pixels(&mut self, map: &BadPixelMap) -> usize {
+    let mut fixed = Vec::<(usize, u16)>::with_capacity(map.len());
+    for (x, y) in map.iter() {
+      let (x, y) = (x as usize, y as usize);
+      if x >= self.width || y >= self.height {
+        continue;
+      }
+      let (mut sum, mut count) = (0_u32, 0_u32);
+      for (nx, ny) in self.same_color_neighbors(x, y) {
+        if !map.contains(nx as u32, ny as u32) {
+          sum += self.data[self.calc_idx(nx, ny)] as u32;
+          count += 1;
+        }
+      }
+      if let Some(mean) = (sum + count / 2).checked_div(count) {
+        fixed.push((self.calc_idx(x, y), mean as u16));
+      }
+    }
+    // Written afterwards, so every pixel is interpolated from original values.
+    for (idx, v) in &fixed {
+      self.data[*idx] = *v;
+    }
+    fixed.len()
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use crate::tiff::{CFAPattern, CFAPatternDim};
+
+  #[test]
+  fn test_detect_and_fix() {
+    let pattern = vec![CFAPattern::R, CFAPattern::G, CFAPattern::G, CFAPattern::B];
+    let mut img = RawImage::new(8, 8, pattern, CFAPatternDim { width: 2, height: 2 });
+    img.set_white_level(1000);
+    for y in 0..8 {
+      for x in 0..8 {
+        img.set(x, y, 100 + 100 * img.cfa_idx(x, y) as u16);
+      }
+    }
+    img.set(4, 4, 1000);
+    img.set(3, 5, 0);
+    let full = Rect { x: 0, y: 0, width: 8, height: 8 };
+    let map = img.detect_bad_pixels(&full, 0.1);
+    assert_eq!(map.iter().collect::<Vec<_>>(), vec![(4, 4), (3, 5)]);
+
+    assert_eq!(img.fix_bad_pixels(&map), 2);
+    assert_eq!(img.data()[img.calc_idx(4, 4)], 100);
+    assert_eq!(img.data()[img.calc_idx(3, 5)], 400);
+    assert!(img.detect_bad_pixels(&full, 0.1).is_empty());
+
+    assert_eq!(BadPixelMap::parse(&map.to_string()).unwrap(), map);
+    assert!(BadPixelMap::parse("1 2 3").is_err());
+  }
+}
@@ -0,0 +1,142 @@
+// Color filter array of any repeat size and number of colors.
+//
+// Colors are numbered as planes in the order of their TIFF/EP codes, so the planes of RGB patterns
+// are 0 = R, 1 = G and 2 = B. [TIFF/EP] p.26: 0 = Red, 1 = Green, 2 = Blue, 3 = Cyan, 4 = Magenta, 5 = Yellow, 6 = White
+
+use std::fmt::{Display, Formatter};
+use crate::tiff::{CFAPattern, CFAPatternDim};
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum CfaKind {
+  // 2x2 RGB with the greens on a diagonal.
+  Bayer,
+  // Fuji's 6x6 RGB pattern with 20 greens.
+  XTrans,
+  Other,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Cfa {
+  width: usize,
+  height: usize,
+  // Plane of each position, row-major.
+  pattern: Vec<usize>,
+  // TIFF/EP color code of each plane.
+  colors: Vec<u8>,
+}
+
+fn color_code(color: &CFAPattern) -> u8 {
+  match color {
+    CFAPattern::R => 0,
+    CFAPattern::G => 1,
+    CFAPattern::B => 2,
+    CFAPattern::Unknown(c) => *c,
+  }
+}
+
+impl Cfa {
+  pub fn new(dim: &CFAPatternDim, pattern: &[CFAPattern]) -> anyhow::Result<Self> {
+    if dim.width == 0 || dim.height == 0 || pattern.len() != dim.width * dim.height {
+      return Err(anyhow::Error::msg(format!(
+        "CFA pattern has {} colors for {}x{}", pattern.len(), dim.width, dim.height)));
+    }
+    let codes: Vec<u8> = pattern.iter().map(color_code).collect();
+    let mut colors = codes.clone();
+    colors.sort_unstable();
+    colors.dedup();
+    Ok(Self {
+      width: dim.width,
+      height: dim.height,
+      pattern: codes.iter().map(|c| colors.iter().position(|it| it == c).unwrap_or_default()).collect(),
+      colors,
+    })
+  }
+
+  pub fn width(&self) -> usize {
+    self.width
+  }
+  pub fn height(&self) -> usize {
+    self.height
+  }
+  // Plane of the pixel at (x, y) in the image.
+  pub fn plane(&self, x: usize, y: usize) -> usize {
+    self.pattern[(y % self.height) * self.width + (x % self.width)]
+  }
+  pub fn planes(&self) -> usize {
+    self.colors.len()
+  }
+  // TIFF/EP color code of each plane.
+  pub fn colors(&self) -> &[u8] {
+    &self.colors
+  }
+  pub fn is_rgb(&self) -> bool {
+    self.colors == [0, 1, 2]
+  }
+
+  pub fn kind(&self) -> CfaKind {
+    if !self.is_rgb() {
+      return CfaKind::Other;
+    }
+    let count = |plane: usize| self.pattern.iter().filter(|it| **it == plane).count();
+    let p = &self.pattern;
+    match (self.width, self.height) {
+      // Being RGB, the other diagonal holds red and blue.
+      (2, 2) if (p[0] == 1 && p[3] == 1) || (p[1] == 1 && p[2] == 1) => CfaKind::Bayer,
+      (6, 6) if count(0) == 8 && count(1) == 20 && count(2) == 8 => CfaKind::XTrans,
+      _ => CfaKind::Other,
+    }
+  }
+}
+
+impl Display for Cfa {
+  fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    write!(f, "{}x{} ", self.width, self.height)?;
+    for plane in &self.pattern {
+      let letter = match self.colors[*plane] {
+        0 => 'R',
+        1 => 'G',
+        2 => 'B',
+        3 => 'C',
+        4 => 'M',
+        5 => 'Y',
+        6 => 'W',
+        _ => '?',
+      };
+      write!(f, "{}", letter)?;
+    }
+    Ok(())
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use crate::tiff::CFAPattern::{B, G, R, Unknown};
+
+  fn cfa(width: usize, height: usize, pattern: Vec<CFAPattern>) -> Cfa {
+    Cfa::new(&CFAPatternDim { width, height }, &pattern).unwrap()
+  }
+
+  #[test]
+  fn test_kind() {
+    assert_eq!(cfa(2, 2, vec![G, B, R, G]).kind(), CfaKind::Bayer);
+    assert_eq!(cfa(2, 2, vec![R, G, B, G]).kind(), CfaKind::Other);
+    let xtrans = cfa(6, 6, vec![
+      G, G, R, G, G, B,
+      G, G, B, G, G, R,
+      B, R, G, R, B, G,
+      G, G, B, G, G, R,
+      G, G, R, G, G, B,
+      R, B, G, B, R, G,
+    ]);
+    assert_eq!(xtrans.kind(), CfaKind::XTrans);
+    assert_eq!(xtrans.plane(8, 7), 2);
+    // Cyan, yellow, green and magenta.
+    let cygm = cfa(2, 2, vec![Unknown(3), Unknown(5), G, Unknown(4)]);
+    assert_eq!(cygm.kind(), CfaKind::Other);
+    assert_eq!(cygm.colors(), &[1, 3, 4, 5]);
+    assert_eq!(cygm.plane(0, 1), 0);
+    assert_eq!(cygm.to_string(), "2x2 CYGM");
+    assert!(Cfa::new(&CFAPatternDim { width: 2, height: 2 }, &[R, G]).is_err());
+  }
+}
@@ -0,0 +1,129 @@
+// Black level estimation from optical black (masked) areas.
+//
+// [DNG] p.39 MaskedAreas: "used to estimate the black level, and to remove row or column pattern noise"
+
+use super::{RawImage, Rect};
+
+#[derive(Clone, Debug, Default)]
+pub struct BlackEstimate {
+  // One per CFA position, row-major.
+  pub levels: Vec<f32>,
+  // Offset of each row from `levels`, from masked areas covering the row. Empty if none does.
+  pub row_offsets: Vec<f32>,
+  // Offset of each column from `levels`, from masked areas covering the column. Empty if none does.
+  pub col_offsets: Vec<f32>,
+}
+
+fn median(values: &mut [f32]) -> Option<f32> {
+  if values.is_empty() {
+    return None;
+  }
+  let mid = values.len() / 2;
+  let (_, m, _) = values.select_nth_unstable_by(mid, |a, b| a.total_cmp(b));
+  Some(*m)
+}
+
+impl RawImage {
+  // None when there is no masked area, or it misses a CFA color.
+  pub fn estimate_black(&self) -> Option<BlackEstimate> {
+    let areas: Vec<Rect> = self.masked_areas.iter()
+      .map(|it| it.clip(self.width, self.height))
+      .filter(|it| it.width > 0 && it.height > 0)
+      .collect();
+    if areas.is_empty() {
+      return None;
+    }
+    let n = self.cfa_dim.width * self.cfa_dim.height;
+    let mut samples = vec![Vec::<f32>::new(); n];
+    for area in &areas {
+      for y in area.y as usize..(area.y + area.height) as usize {
+        for x in area.x as usize..(area.x + area.width) as usize {
+          samples[self.cfa_idx(x, y)].push(self.data[self.calc_idx(x, y)] as f32);
+        }
+      }
+    }
+    // Medians keep hot pixels in the masked area out of the levels.
+    let levels = samples.iter_mut().map(|it| median(it)).collect::<Option<Vec<f32>>>()?;
+
+    // Columns masked on the left or right cover whole rows, and rows masked on top or bottom whole columns.
+    let offsets = |len: usize, vertical: bool| -> Vec<f32> {
+      let mut offsets = vec![0.0_f32; len];
+      let mut found = false;
+      for (i, offset) in offsets.iter_mut().enumerate() {
+        let mut diffs = Vec::<f32>::new();
+        for area in &areas {
+          let (start, end, across) = if vertical {
+            (area.y as usize, (area.y + area.height) as usize, area.x as usize..(area.x + area.width) as usize)
+          } else {
+            (area.x as usize, (area.x + area.width) as usize, area.y as usize..(area.y + area.height) as usize)
+          };
+          if i < start || i >= end {
+            continue;
+          }
+          for j in across {
+            let (x, y) = if vertical { (j, i) } else { (i, j) };
+            diffs.push(self.data[self.calc_idx(x, y)] as f32 - levels[self.cfa_idx(x, y)]);
+          }
+        }
+        if let Some(m) = median(&mut diffs) {
+          *offset = m;
+          found = true;
+        }
+      }
+      if found { offsets } else { Vec::new() }
+    };
+    // An area spanning the full height gives row offsets, one spanning the full width column offsets.
+    let has_vertical = areas.iter().any(|it| it.height as usize == self.height);
+    let has_horizontal = areas.iter().any(|it| it.width as usize == self.width);
+    Some(BlackEstimate {
+      row_offsets: if has_vertical { offsets(self.height, true) } else { Vec::new() },
+      col_offsets: if has_horizontal { offsets(self.width, false) } else { Vec::new() },
+      levels,
+    })
+  }
+
+  // Replaces the black levels with ones measured in the masked areas.
+  // With `pattern_noise`, also removes row and column banding measured there.
+  pub fn apply_optical_black(&mut self, pattern_noise: bool) -> bool {
+    let Some(estimate) = self.estimate_black() else {
+      return false;
+    };
+    self.black_levels = estimate.levels.iter().map(|it| it.round().clamp(0.0, u16::MAX as f32) as u16).collect();
+    if pattern_noise {
+      self.black_row_offsets = estimate.row_offsets;
+      self.black_col_offsets = estimate.col_offsets;
+    }
+    true
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use crate::tiff::{CFAPattern, CFAPatternDim};
+
+  #[test]
+  fn test_estimate_black() {
+    let pattern = vec![CFAPattern::R, CFAPattern::G, CFAPattern::G, CFAPattern::B];
+    let (width, height) = (8, 6);
+    let mut img = RawImage::new(width, height, pattern, CFAPatternDim { width: 2, height: 2 });
+    for y in 0..height {
+      for x in 0..width {
+        // Per-channel levels 100..103, plus a band of 10 on row 3.
+        let v = 100 + img.cfa_idx(x, y) as u16 + if y == 3 { 10 } else { 0 };
+        img.set(x, y, if x >= 2 { 1000 } else { v });
+      }
+    }
+    // A hot pixel in the masked area.
+    img.set(0, 0, 4000);
+    img.set_masked_areas(vec![Rect { x: 0, y: 0, width: 2, height: 6 }]);
+    let estimate = img.estimate_black().unwrap();
+    assert_eq!(estimate.levels, vec![100.0, 101.0, 102.0, 103.0]);
+    assert_eq!(estimate.row_offsets[3], 10.0);
+    assert_eq!(estimate.row_offsets[4], 0.0);
+    assert!(estimate.col_offsets.is_empty());
+
+    assert!(img.apply_optical_black(true));
+    assert_eq!(img.black_level(4, 3), 102.0 + 10.0);
+  }
+}
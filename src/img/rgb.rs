@@ -0,0 +1,110 @@
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::Path;
+use std::borrow::Cow;
+use png::BitDepth;
+use crate::color::{self, ColorSpace, Transfer};
+use super::Rect;
+
+// Full-resolution linear RGB, interleaved and row-major.
+#[derive(Clone, Debug)]
+pub struct RgbImage {
+  width: usize,
+  height: usize,
+  data: Vec<f32>,
+}
+
+impl RgbImage {
+  pub fn new(width: usize, height: usize) -> Self {
+    Self {
+      width,
+      height,
+      data: vec![0.0; width * height * 3],
+    }
+  }
+  pub fn width(&self) -> usize {
+    self.width
+  }
+  pub fn height(&self) -> usize {
+    self.height
+  }
+  #[cfg(test)]
+  pub fn data(&self) -> &[f32] {
+    &self.data
+  }
+  pub fn data_mut(&mut self) -> &mut [f32] {
+    &mut self.data
+  }
+  pub fn get(&self, x: usize, y: usize) -> [f32; 3] {
+    let idx = (self.width * y + x) * 3;
+    [self.data[idx], self.data[idx + 1], self.data[idx + 2]]
+  }
+  pub fn set(&mut self, x: usize, y: usize, rgb: [f32; 3]) {
+    let idx = (self.width * y + x) * 3;
+    self.data[idx..idx + 3].copy_from_slice(&rgb);
+  }
+
+  // A copy holding only `rect`, clipped to the image.
+  pub fn cropped(&self, rect: &Rect) -> RgbImage {
+    let rect = rect.clip(self.width, self.height);
+    let (x, width) = (rect.x as usize, rect.width as usize);
+    let mut out = RgbImage::new(width, rect.height as usize);
+    for (row, y) in (rect.y as usize..(rect.y + rect.height) as usize).enumerate() {
+      let start = (self.width * y + x) * 3;
+      out.data[row * width * 3..(row + 1) * width * 3].copy_from_slice(&self.data[start..start + width * 3]);
+    }
+    out
+  }
+
+  // Box-filtered by `factor` in both directions. Trailing rows and columns short of a full box are dropped.
+  pub fn downscaled(&self, factor: usize) -> RgbImage {
+    let factor = factor.max(1);
+    let mut out = RgbImage::new(self.width / factor, self.height / factor);
+    let scale = 1.0 / (factor * factor) as f32;
+    for y in 0..out.height {
+      for x in 0..out.width {
+        let mut px = [0.0_f32; 3];
+        for sy in y * factor..(y + 1) * factor {
+          for sx in x * factor..(x + 1) * factor {
+            for (acc, v) in px.iter_mut().zip(self.get(sx, sy)) {
+              *acc += v;
+            }
+          }
+        }
+        out.set(x, y, px.map(|it| it * scale));
+      }
+    }
+    out
+  }
+
+  // Saves linear values in `space` as PNG, encoded with its transfer function and with its ICC profile.
+  pub fn save_to_file(&self, path: impl AsRef<Path>, high_bits: bool, space: &ColorSpace) -> anyhow::Result<()> {
+    let file = File::create(path)?;
+    let writer = BufWriter::new(file);
+    self.save(writer, high_bits, space)
+  }
+
+  pub fn save<F: std::io::Write>(&self, writer: BufWriter<F>, high_bits: bool, space: &ColorSpace) -> anyhow::Result<()> {
+    let mut info = png::Info::with_size(self.width as u32, self.height as u32);
+    info.color_type = png::ColorType::Rgb;
+    info.bit_depth = if high_bits { BitDepth::Sixteen } else { BitDepth::Eight };
+    info.icc_profile = Some(Cow::Owned(color::icc_profile(space)));
+    let encoder = png::Encoder::with_info(writer, info)?;
+    let mut writer = encoder.write_header()?;
+    let pixels = self.create_pixels(high_bits, &space.transfer());
+    writer.write_image_data(&pixels).map_err(anyhow::Error::from)
+  }
+
+  fn create_pixels(&self, high_bits: bool, transfer: &Transfer) -> Vec<u8> {
+    let mut buff = Vec::<u8>::with_capacity(self.data.len() * if high_bits { 2 } else { 1 });
+    for v in &self.data {
+      let v = (transfer.encode(v.clamp(0.0, 1.0)) * 65535.0).round() as u16;
+      if high_bits {
+        buff.extend(v.to_be_bytes());
+      } else {
+        buff.push((v >> 8) as u8);
+      }
+    }
+    buff
+  }
+}
@@ -0,0 +1,160 @@
+// Reduced-size decoding for previews.
+//
+// Half size turns each 2x2 Bayer cell into one RGB pixel without demosaicing. Quarter and eighth size
+// first keep one CFA cell out of every 2x2 or 4x4 cells (`RawImage::decimated`), which lets decoders
+// skip the rows in between, and then go on as half size.
+
+use rayon::prelude::*;
+use super::{BadPixelMap, CfaKind, RawImage, Rect, RgbImage};
+
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum Scale {
+  #[default]
+  Full,
+  Half,
+  Quarter,
+  Eighth,
+}
+
+impl Scale {
+  // Names for the command line, in the order of `Scale::ALL`.
+  pub const NAMES: &'static [&'static str] = &["full", "half", "quarter", "eighth"];
+  pub const ALL: &'static [Scale] = &[Scale::Full, Scale::Half, Scale::Quarter, Scale::Eighth];
+
+  pub fn from_name(name: &str) -> Option<Self> {
+    Self::NAMES.iter().position(|it| *it == name).map(|idx| Self::ALL[idx])
+  }
+
+  pub fn name(&self) -> &'static str {
+    Self::NAMES[*self as usize]
+  }
+
+  // Output pixels per side of one input pixel, e.g. 4 for quarter size.
+  pub fn factor(&self) -> usize {
+    match self {
+      Scale::Full => 1,
+      Scale::Half => 2,
+      Scale::Quarter => 4,
+      Scale::Eighth => 8,
+    }
+  }
+
+  // CFA cells per side that `RawImage::decimated` reduces to one.
+  pub fn decimation(&self) -> usize {
+    (self.factor() / 2).max(1)
+  }
+
+  // Whether row `y` survives decimation, for a CFA `cfa_height` rows high. Decoders may skip the others.
+  pub fn keeps_row(&self, y: usize, cfa_height: usize) -> bool {
+    (y / cfa_height.max(1)).is_multiple_of(self.decimation())
+  }
+}
+
+// Position of `v` once `k` - 1 of every `k` cells of `period` pixels are dropped.
+fn decimate(v: u32, period: usize, k: usize) -> u32 {
+  let (period, block) = (period as u32, (period * k) as u32);
+  (v / block) * period + (v % block).min(period)
+}
+
+impl RawImage {
+  // A mosaic holding the top left CFA cell of every `scale.decimation()` x `scale.decimation()` cells.
+  // The pattern and black levels stay as they are; areas and bad pixels move to the new coordinates.
+  pub fn decimated(&self, scale: Scale) -> RawImage {
+    let k = scale.decimation();
+    let (cw, ch) = (self.cfa_dim.width, self.cfa_dim.height);
+    let keep_x = |x: usize| (x / cw).is_multiple_of(k);
+    let keep_y = |y: usize| (y / ch).is_multiple_of(k);
+    let xs: Vec<usize> = (0..self.width).filter(|x| keep_x(*x)).collect();
+    let ys: Vec<usize> = (0..self.height).filter(|y| keep_y(*y)).collect();
+    let mut data = Vec::<u16>::with_capacity(xs.len() * ys.len());
+    for y in &ys {
+      data.extend(xs.iter().map(|x| self.data[self.calc_idx(*x, *y)]));
+    }
+    let rect = |r: &Rect| {
+      let (x, y) = (decimate(r.x, cw, k), decimate(r.y, ch, k));
+      Rect::from_tlbr(y, x, decimate(r.bottom(), ch, k), decimate(r.right(), cw, k))
+    };
+    let offsets = |offsets: &Vec<f32>, keep: &dyn Fn(usize) -> bool| -> Vec<f32> {
+      offsets.iter().enumerate().filter(|(i, _)| keep(*i)).map(|(_, v)| *v).collect()
+    };
+    let mut bad_pixels = BadPixelMap::default();
+    for (x, y) in self.bad_pixels.iter().filter(|(x, y)| keep_x(*x as usize) && keep_y(*y as usize)) {
+      bad_pixels.insert(decimate(x, cw, k), decimate(y, ch, k));
+    }
+    RawImage {
+      width: xs.len(),
+      height: ys.len(),
+      data,
+      cfa_pattern: self.cfa_pattern.clone(),
+      cfa_dim: self.cfa_dim.clone(),
+      black_levels: self.black_levels.clone(),
+      black_row_offsets: offsets(&self.black_row_offsets, &keep_y),
+      black_col_offsets: offsets(&self.black_col_offsets, &keep_x),
+      white_level: self.white_level,
+      masked_areas: self.masked_areas.iter().map(rect).filter(|it| it.width > 0 && it.height > 0).collect(),
+      active_area: self.active_area.as_ref().map(rect),
+      default_crop: self.default_crop.as_ref().map(rect),
+      bad_pixels,
+      color: self.color.clone(),
+    }
+  }
+
+  // One RGB pixel per 2x2 Bayer cell, with the two greens averaged. Trailing odd rows and columns are dropped.
+  pub fn superpixel(&self) -> anyhow::Result<RgbImage> {
+    let cfa = self.cfa()?;
+    if cfa.kind() != CfaKind::Bayer {
+      return Err(anyhow::Error::msg(format!("Half size needs a 2x2 Bayer pattern: {}", cfa)));
+    }
+    let (width, height) = (self.width / 2, self.height / 2);
+    let mut out = RgbImage::new(width, height);
+    if width == 0 {
+      return Ok(out);
+    }
+    out.data_mut().par_chunks_mut(width * 3).enumerate().for_each(|(y, row)| {
+      for x in 0..width {
+        let mut px = [0.0_f32; 3];
+        for (dx, dy) in [(0, 0), (1, 0), (0, 1), (1, 1)] {
+          let (sx, sy) = (x * 2 + dx, y * 2 + dy);
+          px[cfa.plane(sx, sy)] += self.normalized(sx, sy);
+        }
+        px[1] /= 2.0;
+        row[x * 3..x * 3 + 3].copy_from_slice(&px);
+      }
+    });
+    Ok(out)
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use crate::tiff::{CFAPattern, CFAPatternDim};
+
+  #[test]
+  fn test_scale() {
+    let pattern = vec![CFAPattern::G, CFAPattern::R, CFAPattern::B, CFAPattern::G];
+    let mut img = RawImage::new(9, 8, pattern, CFAPatternDim { width: 2, height: 2 });
+    img.set_white_level(100);
+    for y in 0..8 {
+      for x in 0..9 {
+        img.set(x, y, (y * 10 + x) as u16);
+      }
+    }
+    img.set_default_crop(Some(Rect { x: 1, y: 2, width: 7, height: 6 }));
+
+    let half = img.superpixel().unwrap();
+    assert_eq!((half.width(), half.height()), (4, 4));
+    // G 0 and 11, R 1, B 10.
+    assert_eq!(half.get(0, 0), [0.01, 0.055, 0.1]);
+
+    let quarter = img.decimated(Scale::Quarter);
+    assert_eq!((quarter.width(), quarter.height()), (5, 4));
+    assert_eq!(quarter.data()[..5], [0, 1, 4, 5, 8]);
+    assert_eq!(quarter.data()[5..10], [10, 11, 14, 15, 18]);
+    assert_eq!(quarter.data()[10], 40);
+    assert_eq!(quarter.visible_area(), Rect { x: 1, y: 2, width: 3, height: 2 });
+    assert!(Scale::Quarter.keeps_row(5, 2));
+    assert!(!Scale::Quarter.keeps_row(2, 2));
+    assert_eq!(Scale::from_name("eighth").map(|it| it.factor()), Some(8));
+  }
+}
@@ -1,3 +1,4 @@
+use std::path::PathBuf;
 use clap::{Arg, ArgAction, value_parser};
 
 mod app;
@@ -5,6 +6,9 @@ mod tiff;
 mod raw;
 mod stream;
 mod img;
+mod camera;
+mod demosaic;
+mod color;
 
 fn app() -> clap::Command {
   clap::Command::new("ag")
@@ -19,7 +23,7 @@ fn app() -> clap::Command {
           .help("Show verbose message"))
       .subcommand(clap::Command::new("render")
           .arg(Arg::new("input.arw")
-              .help("File path to load")
+              .help("File path to load, or - for standard input")
               .index(1)
               .action(ArgAction::Set)
               .value_parser(value_parser!(String))
@@ -29,7 +33,239 @@ fn app() -> clap::Command {
               .index(2)
               .action(ArgAction::Set)
               .value_parser(value_parser!(String))
+              .required(true))
+          .arg(Arg::new("threads")
+              .long("threads")
+              .short('j')
+              .help("Number of decoding threads. 0 uses every core")
+              .action(ArgAction::Set)
+              .value_parser(value_parser!(usize))
+              .default_value("0"))
+          .arg(Arg::new("camera-db")
+              .long("camera-db")
+              .help("Camera database (TOML) to use over the built-in one")
+              .action(ArgAction::Set)
+              .value_parser(value_parser!(String)))
+          .arg(Arg::new("optical-black")
+              .long("optical-black")
+              .help("Measure black levels in the masked (optical black) areas of the sensor")
+              .action(ArgAction::SetTrue))
+          .arg(Arg::new("pattern-noise")
+              .long("pattern-noise")
+              .help("Remove row/column banding measured in the masked areas. Implies --optical-black")
+              .action(ArgAction::SetTrue))
+          .arg(Arg::new("detect-bad-pixels")
+              .long("detect-bad-pixels")
+              .help("Find and interpolate hot, stuck and dead pixels")
+              .action(ArgAction::SetTrue))
+          .arg(Arg::new("bad-pixel-threshold")
+              .long("bad-pixel-threshold")
+              .help("How far a pixel must stand out from its same-color neighbors, as a fraction of the range")
+              .action(ArgAction::Set)
+              .value_parser(value_parser!(f32))
+              .default_value("0.1"))
+          .arg(Arg::new("bad-pixel-map")
+              .long("bad-pixel-map")
+              .help("File with known bad pixels, one \"x y\" per line, to interpolate")
+              .action(ArgAction::Set)
+              .value_parser(value_parser!(String)))
+          .arg(Arg::new("save-bad-pixel-map")
+              .long("save-bad-pixel-map")
+              .help("Write the detected bad pixels to this file for --bad-pixel-map. Implies --detect-bad-pixels")
+              .action(ArgAction::Set)
+              .value_parser(value_parser!(String)))
+          .arg(Arg::new("demosaic")
+              .long("demosaic")
+              .help("Demosaicing algorithm. Bayer: bilinear (default), or ahd, rcd and dcb for cleaner edges and textures. \
+                X-Trans: markesteijn (default). Other patterns: generic (default)")
+              .action(ArgAction::Set)
+              .value_parser(clap::builder::PossibleValuesParser::new(demosaic::Algorithm::NAMES)))
+          .arg(Arg::new("uncropped")
+              .long("uncropped")
+              .help("Keep the full sensor area, including masked areas, instead of the default crop")
+              .action(ArgAction::SetTrue))
+          .arg(Arg::new("scale")
+              .long("scale")
+              .help("Output size. half takes each 2x2 Bayer cell as one pixel without demosaicing; \
+                quarter and eighth also skip decoding the rows they don't use")
+              .action(ArgAction::Set)
+              .value_parser(clap::builder::PossibleValuesParser::new(img::Scale::NAMES))
+              .default_value("full"))
+          .arg(Arg::new("crop")
+              .long("crop")
+              .help("Render only this region, given as x,y,width,height in sensor pixels (as in --uncropped output). \
+                Only the rows around it are decoded")
+              .action(ArgAction::Set)
+              .value_parser(value_parser!(String)))
+          .arg(Arg::new("white-balance")
+              .long("white-balance")
+              .help("White balance: as-shot from the file (default), gray-world to make the image average neutral, \
+                or white-patch to make its brightest unclipped patches neutral")
+              .action(ArgAction::Set)
+              .value_parser(clap::builder::PossibleValuesParser::new(color::WhiteBalance::NAMES))
+              .conflicts_with_all(["white-balance-rect", "temperature"]))
+          .arg(Arg::new("white-balance-rect")
+              .long("white-balance-rect")
+              .help("Make the average of this region neutral, given as x,y,width,height in sensor pixels")
+              .action(ArgAction::Set)
+              .value_parser(value_parser!(String))
+              .conflicts_with("temperature"))
+          .arg(Arg::new("temperature")
+              .long("temperature")
+              .help("White balance for an illuminant of this color temperature in kelvin, through the camera's color matrices")
+              .action(ArgAction::Set)
+              .value_parser(value_parser!(f32)))
+          .arg(Arg::new("tint")
+              .long("tint")
+              .help("Tint for --temperature: positive towards magenta, negative towards green")
+              .action(ArgAction::Set)
+              .value_parser(value_parser!(f32))
+              .allow_negative_numbers(true)
+              .requires("temperature")
+              .default_value("0"))
+          .arg(Arg::new("color-space")
+              .long("color-space")
+              .help("Output color space, embedded as an ICC profile: srgb, display-p3, adobe-rgb, prophoto, \
+                linear-rec2020 or xyz (D50)")
+              .action(ArgAction::Set)
+              .value_parser(clap::builder::PossibleValuesParser::new(color::ColorSpace::NAMES))
+              .default_value("srgb"))
+          .arg(Arg::new("exposure")
+              .long("exposure")
+              .help("Exposure compensation in EV, added to the file's BaselineExposure")
+              .action(ArgAction::Set)
+              .value_parser(value_parser!(f32))
+              .allow_negative_numbers(true)
+              .default_value("0"))
+          .arg(Arg::new("ignore-baseline-exposure")
+              .long("ignore-baseline-exposure")
+              .help("Don't apply the BaselineExposure the file recommends")
+              .action(ArgAction::SetTrue))
+          .arg(Arg::new("tone-curve")
+              .long("tone-curve")
+              .help("Tone curve: none for only the output's transfer function (the exact sRGB OETF for srgb), \
+                filmic or aces to roll highlights off instead of clipping them")
+              .action(ArgAction::Set)
+              .value_parser(clap::builder::PossibleValuesParser::new(color::ToneCurve::NAMES))
+              .conflicts_with_all(["tone-curve-points", "tone-curve-file"]))
+          .arg(Arg::new("tone-curve-points")
+              .long("tone-curve-points")
+              .help("Tone curve through these points of encoded values, given as input:output pairs separated by \
+                commas, e.g. 0:0,0.25:0.2,0.75:0.85,1:1")
+              .action(ArgAction::Set)
+              .value_parser(value_parser!(String))
+              .conflicts_with("tone-curve-file"))
+          .arg(Arg::new("tone-curve-file")
+              .long("tone-curve-file")
+              .help("Tone curve from a CSV file of encoded values: input,output lines, or only outputs for \
+                evenly spaced inputs")
+              .action(ArgAction::Set)
+              .value_parser(value_parser!(String))))
+      .subcommand(clap::Command::new("dump")
+          .about("Dump every strip and tile as raw bytes, with a manifest.json describing them")
+          .arg(Arg::new("input.arw")
+              .help("File path to load, or - for standard input")
+              .index(1)
+              .action(ArgAction::Set)
+              .value_parser(value_parser!(String))
+              .required(true))
+          .arg(Arg::new("output-dir")
+              .help("Directory to write dumps into")
+              .index(2)
+              .action(ArgAction::Set)
+              .value_parser(value_parser!(String))
               .required(true)))
+      .subcommand(clap::Command::new("layout")
+          .about("Show which byte ranges belong to what, and report overlaps, gaps and truncation")
+          .arg(Arg::new("input.arw")
+              .help("File path to load, or - for standard input")
+              .index(1)
+              .action(ArgAction::Set)
+              .value_parser(value_parser!(String))
+              .required(true)))
+      .subcommand(clap::Command::new("get")
+          .about("Print tag values selected by a path such as `ifd0/subifd[0]/ImageWidth` or `**/CFAPattern`")
+          .arg(Arg::new("input.arw")
+              .help("File path to load, or - for standard input")
+              .index(1)
+              .action(ArgAction::Set)
+              .value_parser(value_parser!(String))
+              .required(true))
+          .arg(Arg::new("query")
+              .help("Path to the tag")
+              .index(2)
+              .action(ArgAction::Set)
+              .value_parser(value_parser!(String))
+              .required(true)))
+      .subcommand(clap::Command::new("validate")
+          .about("Check conformance to TIFF, TIFF/EP or DNG")
+          .arg(Arg::new("input.arw")
+              .help("File path to load, or - for standard input")
+              .index(1)
+              .action(ArgAction::Set)
+              .value_parser(value_parser!(String))
+              .required(true))
+          .arg(Arg::new("profile")
+              .long("profile")
+              .help("Specification to check against. Detected from the file by default")
+              .action(ArgAction::Set)
+              .value_parser(["tiff", "tiff-ep", "dng"]))
+          .arg(Arg::new("strict")
+              .long("strict")
+              .help("Fail on warnings too")
+              .action(ArgAction::SetTrue)))
+      .subcommand(clap::Command::new("cameras")
+          .about("Show camera database entries. With make and model, shows the merged entry for that camera")
+          .arg(Arg::new("make")
+              .index(1)
+              .action(ArgAction::Set)
+              .value_parser(value_parser!(String)))
+          .arg(Arg::new("model")
+              .index(2)
+              .action(ArgAction::Set)
+              .value_parser(value_parser!(String)))
+          .arg(Arg::new("mode")
+              .long("mode")
+              .help("Format variant, e.g. ARW2")
+              .action(ArgAction::Set)
+              .value_parser(value_parser!(String)))
+          .arg(Arg::new("camera-db")
+              .long("camera-db")
+              .help("Camera database (TOML) to use over the built-in one")
+              .action(ArgAction::Set)
+              .value_parser(value_parser!(String))))
+      .subcommand(clap::Command::new("benchmark")
+          .about("Time demosaicing algorithms on a decoded file")
+          .arg(Arg::new("input.arw")
+              .help("File path to load, or - for standard input")
+              .index(1)
+              .action(ArgAction::Set)
+              .value_parser(value_parser!(String))
+              .required(true))
+          .arg(Arg::new("demosaic")
+              .long("demosaic")
+              .help("Algorithms to time. All of them by default")
+              .action(ArgAction::Append)
+              .value_parser(clap::builder::PossibleValuesParser::new(demosaic::Algorithm::NAMES)))
+          .arg(Arg::new("runs")
+              .long("runs")
+              .short('n')
+              .help("Runs per algorithm; the best and mean times are shown")
+              .action(ArgAction::Set)
+              .value_parser(value_parser!(usize))
+              .default_value("3"))
+          .arg(Arg::new("threads")
+              .long("threads")
+              .short('j')
+              .help("Threads for decoding and demosaicing. 0 uses every core")
+              .action(ArgAction::Set)
+              .value_parser(value_parser!(usize))
+              .default_value("0"))
+          .arg(Arg::new("camera-db")
+              .long("camera-db")
+              .help("Camera database (TOML) to use over the built-in one")
+              .action(ArgAction::Set)
+              .value_parser(value_parser!(String))))
 }
 
 fn setup_logger(log_level: log::LevelFilter) -> Result<(), fern::InitError> {
@@ -69,7 +305,85 @@ fn main() -> anyhow::Result<()> {
       let m = m.subcommand_matches("render").unwrap();
       let input = m.get_one::<String>("input.arw").expect("[BUG] No input!");
       let output = m.get_one::<String>("output.png").expect("[BUG] No output!");
-      app::render(input, output)
+      let white_balance = if let Some(rect) = m.get_one::<String>("white-balance-rect") {
+        color::WhiteBalance::Rect(img::Rect::parse(rect)?)
+      } else if let Some(temperature) = m.get_one::<f32>("temperature") {
+        let tint = *m.get_one::<f32>("tint").expect("[BUG] No tint!");
+        color::WhiteBalance::Temperature { temperature: *temperature, tint }
+      } else {
+        m.get_one::<String>("white-balance").and_then(|it| color::WhiteBalance::from_name(it)).unwrap_or_default()
+      };
+      let tone_curve = if let Some(points) = m.get_one::<String>("tone-curve-points") {
+        color::ToneCurve::Curve(color::Curve::parse(points)?)
+      } else if let Some(path) = m.get_one::<String>("tone-curve-file") {
+        color::ToneCurve::Curve(color::Curve::load(path)?)
+      } else {
+        m.get_one::<String>("tone-curve").and_then(|it| color::ToneCurve::from_name(it)).unwrap_or_default()
+      };
+      let options = app::RenderOptions {
+        threads: *m.get_one::<usize>("threads").expect("[BUG] No threads!"),
+        camera_db: m.get_one::<String>("camera-db").map(PathBuf::from),
+        optical_black: m.get_flag("optical-black"),
+        pattern_noise: m.get_flag("pattern-noise"),
+        detect_bad_pixels: m.get_flag("detect-bad-pixels"),
+        bad_pixel_threshold: *m.get_one::<f32>("bad-pixel-threshold").expect("[BUG] No threshold!"),
+        bad_pixel_map: m.get_one::<String>("bad-pixel-map").map(PathBuf::from),
+        save_bad_pixel_map: m.get_one::<String>("save-bad-pixel-map").map(PathBuf::from),
+        demosaic: m.get_one::<String>("demosaic").and_then(|it| demosaic::Algorithm::from_name(it)),
+        uncropped: m.get_flag("uncropped"),
+        scale: m.get_one::<String>("scale").and_then(|it| img::Scale::from_name(it)).unwrap_or_default(),
+        crop: m.get_one::<String>("crop").map(|it| img::Rect::parse(it)).transpose()?,
+        white_balance,
+        color_space: m.get_one::<String>("color-space").and_then(|it| color::ColorSpace::from_name(it)).unwrap_or_default(),
+        exposure: *m.get_one::<f32>("exposure").expect("[BUG] No exposure!"),
+        ignore_baseline_exposure: m.get_flag("ignore-baseline-exposure"),
+        tone_curve,
+      };
+      app::render(input, output, &options)
+    }
+    "dump" => {
+      let m = m.subcommand_matches("dump").unwrap();
+      let input = m.get_one::<String>("input.arw").expect("[BUG] No input!");
+      let output_dir = m.get_one::<String>("output-dir").expect("[BUG] No output directory!");
+      app::dump(input, output_dir)
+    }
+    "layout" => {
+      let m = m.subcommand_matches("layout").unwrap();
+      let input = m.get_one::<String>("input.arw").expect("[BUG] No input!");
+      app::layout(input)
+    }
+    "get" => {
+      let m = m.subcommand_matches("get").unwrap();
+      let input = m.get_one::<String>("input.arw").expect("[BUG] No input!");
+      let query = m.get_one::<String>("query").expect("[BUG] No query!");
+      app::get(input, query)
+    }
+    "validate" => {
+      let m = m.subcommand_matches("validate").unwrap();
+      let input = m.get_one::<String>("input.arw").expect("[BUG] No input!");
+      let profile = m.get_one::<String>("profile").map(|it| it.as_str());
+      let strict = m.get_flag("strict");
+      app::validate(input, profile, strict)
+    }
+    "cameras" => {
+      let m = m.subcommand_matches("cameras").unwrap();
+      let camera_db = m.get_one::<String>("camera-db");
+      let make = m.get_one::<String>("make").map(|it| it.as_str());
+      let model = m.get_one::<String>("model").map(|it| it.as_str());
+      let mode = m.get_one::<String>("mode").map(|it| it.as_str());
+      app::cameras(camera_db, make, model, mode)
+    }
+    "benchmark" => {
+      let m = m.subcommand_matches("benchmark").unwrap();
+      let input = m.get_one::<String>("input.arw").expect("[BUG] No input!");
+      let algorithms: Vec<demosaic::Algorithm> = match m.get_many::<String>("demosaic") {
+        Some(names) => names.filter_map(|it| demosaic::Algorithm::from_name(it)).collect(),
+        None => demosaic::Algorithm::ALL.to_vec(),
+      };
+      let runs = *m.get_one::<usize>("runs").expect("[BUG] No runs!");
+      let threads = *m.get_one::<usize>("threads").expect("[BUG] No threads!");
+      let camera_db = m.get_one::<String>("camera-db").map(PathBuf::from);
+      app::benchmark(input, camera_db, &algorithms, runs, threads)
     }
     cmd => {
       Err(anyhow::Error::msg(format!("Unknown command: {}", cmd)))
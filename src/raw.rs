@@ -2,3 +2,9 @@ mod decoder;
 pub use decoder::*;
 mod decompressor;
 pub use decompressor::*;
+mod registry;
+pub use registry::*;
+mod sr2;
+pub use sr2::*;
+mod dng_tags;
+pub use dng_tags::*;
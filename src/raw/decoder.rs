@@ -1,8 +1,16 @@
 mod arw;
 pub use arw::ArwDecoder;
-pub use crate::img::RawImage;
+pub use crate::img::{RawImage, Rect};
 
 pub trait RawDecoder {
+  // Short format name, e.g. "ARW".
+  fn name(&self) -> &'static str;
   fn is_acceptable(&self) -> bool;
-  fn decode(&mut self) -> Result<RawImage, anyhow::Error>;
+  // Format variant of this file, e.g. "ARW2", if the decoder can tell.
+  fn variant(&self) -> Option<String> {
+    None
+  }
+  // Decodes the region of interest `roi` in sensor pixels, or the whole image if None. The result is
+  // cropped to it, with the metadata shifted to match; decoders skip the rows or tiles outside where they can.
+  fn decode(&mut self, roi: Option<Rect>) -> Result<RawImage, anyhow::Error>;
 }
@@ -1,8 +1,11 @@
-use std::fmt::format;
-use log::info;
-use crate::raw::Arw2Decompressor;
+use log::{debug, warn};
+use crate::color::{self, CameraColor, IlluminantMatrix};
+use crate::raw::{Arw2Decompressor, DngColorTags, DngRawTags, Sr2};
 use crate::raw::decoder::RawImage;
-use crate::tiff::{Compression, Entry, Tiff};
+use crate::img::{Rect, Scale};
+use crate::tiff::{Compression, Entry, ImageFileDirectory, Tiff};
+use std::sync::Arc;
+use crate::camera::{Camera, CameraDb};
 use crate::stream::ByteStream;
 use super::RawDecoder;
 
@@ -16,9 +19,16 @@ libraw:
 - https://github.com/LibRaw/LibRaw/blob/adcb898a00746c8aa886eb06cc9f5a1cb1834fca/src/metadata/tiff.cpp#L1815-L1838
 */
 
+// ARW2 decodes 11-bit values shifted into 12 bits.
+const ARW2_WHITE_LEVEL: u16 = 4095;
+
 pub struct ArwDecoder<'a> {
   stream: &'a mut ByteStream,
   tiff: &'a Tiff,
+  // 0 uses every core.
+  threads: usize,
+  cameras: Arc<CameraDb>,
+  scale: Scale,
 }
 
 impl <'a> ArwDecoder<'a>  {
@@ -26,19 +36,35 @@ impl <'a> ArwDecoder<'a>  {
     Self {
       stream,
       tiff,
+      threads: 0,
+      cameras: Arc::new(CameraDb::builtin()),
+      scale: Scale::Full,
     }
   }
-}
-
-impl <'a> RawDecoder for ArwDecoder<'a> {
-  fn is_acceptable(&self) -> bool {
-    if let Some(ifd) = self.tiff.root_ifd() {
+  pub fn with_cameras(mut self, cameras: Arc<CameraDb>) -> Self {
+    self.cameras = cameras;
+    self
+  }
+  fn camera(&self) -> Option<Camera> {
+    let root = self.tiff.root_ifd()?;
+    self.cameras.lookup(root.make()?, root.model().unwrap_or_default(), self.variant().as_deref())
+  }
+  pub fn with_threads(mut self, threads: usize) -> Self {
+    self.threads = threads;
+    self
+  }
+  pub fn with_scale(mut self, scale: Scale) -> Self {
+    self.scale = scale;
+    self
+  }
+  pub fn accepts(tiff: &Tiff) -> bool {
+    if let Some(ifd) = tiff.root_ifd() {
       return ifd.make() == Some("SONY");
     }
     false
   }
-
-  fn decode(&mut self) -> Result<RawImage, anyhow::Error> {
+  // The first IFD holding strips, which is the raw image in ARW files.
+  fn raw_ifd(&self) -> Option<&'a ImageFileDirectory> {
     let ifds = self.tiff.filter_ifd_recursive(|it|
       it.find(|e|
         if let Entry::StripOffsets(_) = e {
@@ -48,10 +74,42 @@ impl <'a> RawDecoder for ArwDecoder<'a> {
         }
       ).is_some()
     );
-    if ifds.is_empty() {
-      return Err(anyhow::Error::msg("No IFDs"));
+    ifds.first().copied()
+  }
+}
+
+impl <'a> RawDecoder for ArwDecoder<'a> {
+  fn name(&self) -> &'static str {
+    "ARW"
+  }
+
+  fn is_acceptable(&self) -> bool {
+    Self::accepts(self.tiff)
+  }
+
+  fn variant(&self) -> Option<String> {
+    let ifd = self.raw_ifd()?;
+    match ifd.compression()? {
+      Compression::SonyARW => {
+        let width = ifd.image_width()? as usize;
+        let height = ifd.image_height()? as usize;
+        let count = *ifd.strip_byte_counts()?.first()? as usize;
+        // ARW2 packs every pixel into 8 bits: 32 pixels per 32 bytes.
+        if count == width * height {
+          Some("ARW2".to_string())
+        } else {
+          Some("ARW1".to_string())
+        }
+      }
+      Compression::NoCompression => Some("uncompressed".to_string()),
+      it => Some(format!("{:?}", it)),
     }
-    let ifd = ifds[0];
+  }
+
+  fn decode(&mut self, roi: Option<Rect>) -> Result<RawImage, anyhow::Error> {
+    let Some(ifd) = self.raw_ifd() else {
+      return Err(anyhow::Error::msg("No IFDs"));
+    };
     let compression = ifd.compression();
     let width = ifd.image_width();
     let height = ifd.image_height();
@@ -108,29 +166,131 @@ impl <'a> RawDecoder for ArwDecoder<'a> {
       return Err(anyhow::Error::msg("CFA Repeat Pattern Dim not found"));
     }
     let cfa_dim = cfa_dim.unwrap();
+    let camera = self.camera();
+    let (cfa_pattern, cfa_dim) = match camera.as_ref().and_then(|it| it.cfa.as_ref()) {
+      Some(cfa) => (cfa.pattern()?, cfa.dim()),
+      None => (cfa_pattern.clone(), cfa_dim),
+    };
     let offset = offsets[0];
     let count = counts[0];
-    let mut bpp = bpp[0];
-    for ifd in self.tiff.filter_ifd_recursive(|it| it.make().is_some()) {
-      if ifd.make().unwrap() == "SONY" {
-        bpp = 8;
-      }
-    }
+    let bpp = camera.as_ref()
+      .and_then(|it| it.hint_u32("arw_bits_per_pixel"))
+      .unwrap_or(bpp[0] as u32);
     let is_v1 = (count as usize) * 8 != (width as usize) * (height as usize) * (bpp as usize);
     if is_v1 {
       height += 8;
       return Err(anyhow::Error::msg("ARW v1 is not supported"));
     }
-    let mut decoder = Arw2Decompressor::new(
-      self.stream,
-      self.tiff,
-      width as usize,
-      height as usize,
-      offset as usize,
-      count as usize,
-      cfa_pattern,
-      cfa_dim,
-    );
-    decoder.decode()
+    let mut img = {
+      let data = self.stream.fetch_slice(offset as u64, count as usize)?;
+      let mut decoder = Arw2Decompressor::new(
+        &data,
+        width as usize,
+        height as usize,
+        &cfa_pattern,
+        cfa_dim,
+      ).with_threads(self.threads).with_scale(self.scale);
+      decoder.decode(roi)?
+    };
+    let sr2 = match Sr2::read(self.stream, self.tiff) {
+      Ok(sr2) => sr2.unwrap_or_default(),
+      Err(err) => {
+        warn!("Failed to read SR2: {}", err);
+        Sr2::default()
+      }
+    };
+    self.apply_levels(&mut img, ifd, &sr2, camera.as_ref())?;
+    self.apply_color(&mut img, &sr2, camera.as_ref())?;
+    if let Some(roi) = roi {
+      img = img.cropped_to(&roi);
+    }
+    if self.scale.decimation() > 1 {
+      return Ok(img.decimated(self.scale));
+    }
+    Ok(img)
+  }
+}
+
+impl <'a> ArwDecoder<'a> {
+  // Levels from SR2, then DNG tags, then the camera database.
+  fn apply_levels(&mut self, img: &mut RawImage, ifd: &ImageFileDirectory, sr2: &Sr2, camera: Option<&Camera>) -> anyhow::Result<()> {
+    // SR2 levels are 14-bit on recent bodies, while ARW2 decodes into 12 bits.
+    let shift = sr2.white_level()
+      .map(|white| (0..16).find(|s| (white >> s) <= ARW2_WHITE_LEVEL).unwrap_or(0))
+      .unwrap_or(0);
+    let dng = DngRawTags::read(self.stream, ifd)?;
+    let cfa = img.cfa_dim().clone();
+    if let Some(black) = sr2.black_levels_rggb() {
+      img.set_black_levels_rggb(black.map(|it| it >> shift));
+    } else if let Some(black) = dng.black_for_cfa(cfa.height, cfa.width) {
+      img.set_black_levels(&black)?;
+    } else if let Some(black) = camera.and_then(|it| it.black_levels.as_ref()) {
+      let black: Vec<u16> = black.iter().map(|it| *it as u16).collect();
+      img.set_black_levels(&black)?;
+    }
+    let white = sr2.white_level().map(|it| it >> shift)
+      .or(dng.white)
+      .or(camera.and_then(|it| it.white_level).map(|it| it as u16))
+      .unwrap_or(ARW2_WHITE_LEVEL);
+    img.set_white_level(white);
+    if !dng.masked_areas.is_empty() {
+      img.set_masked_areas(dng.masked_areas);
+    } else if let Some(areas) = camera.and_then(|it| it.masked_areas.clone()) {
+      img.set_masked_areas(areas);
+    }
+    if !dng.bad_pixels.is_empty() {
+      debug!("Bad pixels from DNG: {}", dng.bad_pixels.len());
+      img.set_bad_pixels(dng.bad_pixels);
+    }
+    // The DNG default crop is relative to the active area; the camera database one is in sensor pixels.
+    img.set_active_area(dng.active_area);
+    if let Some(crop) = dng.default_crop {
+      let (ox, oy) = dng.active_area.map(|it| (it.x, it.y)).unwrap_or((0, 0));
+      img.set_default_crop(Some(Rect { x: crop.x + ox, y: crop.y + oy, ..crop }));
+    } else {
+      img.set_default_crop(camera.and_then(|it| it.crop));
+    }
+    debug!("Black levels: {:?}, white level: {}", img.black_levels(), img.white_level());
+    Ok(())
+  }
+
+  // White balance from SR2, then DNG tags; color matrices from DNG tags, then the camera database.
+  // Matrices for other than three colors are left out.
+  fn apply_color(&mut self, img: &mut RawImage, sr2: &Sr2, camera: Option<&Camera>) -> anyhow::Result<()> {
+    let dng = match self.tiff.root_ifd() {
+      Some(ifd) => DngColorTags::read(self.stream, ifd)?,
+      None => DngColorTags::default(),
+    };
+    let as_shot_neutral = sr2.white_balance_rggb()
+      .filter(|it| it.iter().all(|v| *v > 0.0))
+      .map(|[r, g1, g2, b]| {
+        let g = (g1 + g2) / 2.0;
+        [g / r, 1.0, g / b]
+      })
+      .or_else(|| dng.as_shot_neutral.as_ref()
+        .filter(|it| it.len() == 3)
+        .map(|it| [it[0] as f32, it[1] as f32, it[2] as f32]));
+    let mut matrices: Vec<IlluminantMatrix> = dng.calibrations.iter()
+      .filter_map(|it| Some(IlluminantMatrix {
+        temperature: color::illuminant_temperature(it.illuminant).unwrap_or(color::DEFAULT_TEMPERATURE),
+        xyz_to_camera: color::from_row_major(&it.color_matrix)?,
+        forward_matrix: it.forward_matrix.as_deref().and_then(color::from_row_major),
+        camera_calibration: it.camera_calibration.as_deref().and_then(color::from_row_major),
+      }))
+      .collect();
+    // The camera database has a single matrix, for D65.
+    if matrices.is_empty() {
+      if let Some(m) = camera.and_then(|it| it.color_matrix.as_ref()).and_then(|it| color::from_row_major(it)) {
+        matrices.push(IlluminantMatrix::new(color::DEFAULT_TEMPERATURE, m));
+      }
+    }
+    matrices.sort_by(|a, b| a.temperature.total_cmp(&b.temperature));
+    debug!("As-shot neutral: {:?}, color matrices: {}", as_shot_neutral, matrices.len());
+    let analog_balance = dng.analog_balance.as_ref()
+      .filter(|it| it.len() == 3)
+      .map(|it| [it[0] as f32, it[1] as f32, it[2] as f32]);
+    let baseline_exposure = dng.baseline_exposure.map(|it| it as f32);
+    img.set_color(CameraColor { as_shot_neutral, analog_balance, matrices, baseline_exposure });
+    Ok(())
   }
 }
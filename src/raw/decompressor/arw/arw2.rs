@@ -10,89 +10,217 @@ rawspeed
 
 */
 
-use crate::stream::{BitStream, ByteStream};
+use rayon::prelude::*;
+use crate::stream::BitStream;
+use crate::img::{Rect, Scale};
 use crate::raw::RawImage;
-use crate::tiff::{CFAPatternDim, CFAPattern, Tiff};
+use crate::tiff::{CFAPatternDim, CFAPattern};
 
 pub struct Arw2Decompressor<'a> {
-  stream: &'a mut ByteStream,
-  tiff: &'a Tiff,
+  // The whole strip. Each row takes `width` bytes.
+  data: &'a [u8],
   width: usize,
   height: usize,
-  data_offset: usize,
-  data_size: usize,
   cfa_pattern: &'a Vec<CFAPattern>,
   cfa_dim: CFAPatternDim,
+  // 0 uses every core.
+  threads: usize,
+  // Rows `Scale::keeps_row` drops, counted from the top of the region of interest, are left at 0.
+  scale: Scale,
 }
 
 impl <'a> Arw2Decompressor<'a> {
   pub fn new(
-    stream: &'a mut ByteStream,
-    tiff: &'a Tiff,
+    data: &'a [u8],
     width: usize,
     height: usize,
-    data_offset: usize,
-    data_size: usize,
     cfa_pattern: &'a Vec<CFAPattern>,
     cfa_dim: CFAPatternDim,
   ) -> Self {
     Self {
-      stream,
-      tiff,
+      data,
       width,
       height,
-      data_offset,
-      data_size,
       cfa_pattern,
       cfa_dim,
+      threads: 0,
+      scale: Scale::Full,
     }
   }
 
-  pub fn decode(&mut self) -> Result<RawImage, anyhow::Error> {
+  pub fn with_threads(mut self, threads: usize) -> Self {
+    self.threads = threads;
+    self
+  }
+
+  pub fn with_scale(mut self, scale: Scale) -> Self {
+    self.scale = scale;
+    self
+  }
+
+  // Decodes the rows covering `roi`, or every row if None. Other rows are left at 0.
+  pub fn decode(&mut self, roi: Option<Rect>) -> Result<RawImage, anyhow::Error> {
     let mut img = RawImage::new(
       self.width,
       self.height,
       self.cfa_pattern.clone(),
       self.cfa_dim.clone(),
     );
-    for y in 0..self.height {
-      let offset = self.data_offset + self.width * y;
-      self.stream.seek(offset as u64)?;
-      let mut bits = BitStream::new(&mut self.stream);
-      let mut x = 0;
-      while x < self.width {
-        let max = bits.read_bits(11)?;
-        let min = bits.read_bits(11)?;
-        let i_max = bits.read_bits(4)?;
-        let i_min = bits.read_bits(4)?;
-        if i_max == i_min {
-          return Err(anyhow::Error::msg("ARW2 invariant failed, same pixel is both min and max"))
-        }
-        let mut sh = 0;
-        while (sh < 4) && ((0x80 << sh) <= (max - min)) {
-          sh += 1;
-        }
-        for i in 0..16 {
-          let p =
-            if i == i_max {
-              max
-            } else {
-              if i == i_min {
-                min
-              } else {
-                let p = (bits.read_bits(7)? << sh) + min;
-                std::cmp::min(0x7ff, p)
-              }
-            };
-          img.set(x + (i*2) as usize, y,(p << 1) as u16);
-        }
-        if (x & 1) != 0 {
-          x += 31;
+    if self.data.len() < self.width * self.height {
+      return Err(anyhow::Error::msg(format!(
+        "ARW2 strip is too short: {} bytes for {}x{}", self.data.len(), self.width, self.height)));
+    }
+    let pool = rayon::ThreadPoolBuilder::new()
+      .num_threads(self.threads)
+      .build()?;
+    let (data, width, scale, cfa_height) = (self.data, self.width, self.scale, self.cfa_dim.height);
+    let roi = roi.unwrap_or(Rect { x: 0, y: 0, width: self.width as u32, height: self.height as u32 })
+      .clip(self.width, self.height);
+    let rows = roi.y as usize..(roi.y + roi.height) as usize;
+    // Rows are independent, each one starts at `width * y` in the strip. Blocks interleave 32 columns,
+    // so rows are decoded whole.
+    pool.install(|| {
+      img.data_mut()
+        .par_chunks_mut(width)
+        .enumerate()
+        .filter(|(y, _)| rows.contains(y) && scale.keeps_row(y - rows.start, cfa_height))
+        .try_for_each(|(y, row)| decode_row(&data[width * y..width * (y + 1)], row))
+    })?;
+    Ok(img)
+  }
+}
+
+fn decode_row(data: &[u8], row: &mut [u16]) -> anyhow::Result<()> {
+  let width = row.len();
+  let mut bits = BitStream::new(data);
+  let mut x = 0;
+  while x < width {
+    let max = bits.read_bits(11)?;
+    let min = bits.read_bits(11)?;
+    let i_max = bits.read_bits(4)?;
+    let i_min = bits.read_bits(4)?;
+    if i_max == i_min {
+      return Err(anyhow::Error::msg("ARW2 invariant failed, same pixel is both min and max"))
+    }
+    let mut sh = 0;
+    while (sh < 4) && ((0x80 << sh) <= (max - min)) {
+      sh += 1;
+    }
+    for i in 0..16 {
+      let p =
+        if i == i_max {
+          max
+        } else if i == i_min {
+          min
         } else {
-          x += 1;
-        }
+          let p = (bits.read_bits(7)? << sh) + min;
+          std::cmp::min(0x7ff, p)
+        };
+      let px = x + (i * 2) as usize;
+      if px < width {
+        row[px] = (p << 1) as u16;
       }
     }
-    Ok(img)
+    if (x & 1) != 0 {
+      x += 31;
+    } else {
+      x += 1;
+    }
+  }
+  Ok(())
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  // Packs (value, bits) pairs least significant bit first.
+  fn pack(fields: &[(u32, u8)]) -> Vec<u8> {
+    let mut out = Vec::<u8>::new();
+    let (mut acc, mut n) = (0_u64, 0_u32);
+    for (v, bits) in fields {
+      acc |= (*v as u64) << n;
+      n += *bits as u32;
+      while n >= 8 {
+        out.push(acc as u8);
+        acc >>= 8;
+        n -= 8;
+      }
+    }
+    out
+  }
+
+  fn block(max: u32, min: u32, i_max: u32, i_min: u32, delta: u32) -> Vec<(u32, u8)> {
+    let mut fields = vec![(max, 11), (min, 11), (i_max, 4), (i_min, 4)];
+    fields.extend((0..14).map(|i| ((delta + i) & 0x7f, 7)));
+    fields
+  }
+
+  #[test]
+  fn test_decode_row() {
+    let data = pack(&[block(200, 10, 0, 1, 5), block(300, 20, 15, 2, 0)].concat());
+    let mut row = vec![0_u16; 32];
+    decode_row(&data, &mut row).unwrap();
+    assert_eq!(row[0], 200 << 1);
+    assert_eq!(row[2], 10 << 1);
+    // max - min = 190 needs a shift of 1.
+    assert_eq!(row[4], (10 + (5 << 1)) << 1);
+    assert_eq!(row[31], 300 << 1);
+    assert_eq!(row[5], 20 << 1);
+    // max - min = 280 needs a shift of 2.
+    assert_eq!(row[1], 20 << 1);
+    assert_eq!(row[3], (20 + (1 << 2)) << 1);
+  }
+
+  #[test]
+  fn test_threads() {
+    let (width, height) = (32, 64);
+    let data: Vec<u8> = (0..height as u32)
+      .flat_map(|y| pack(&[block(100 + y, y, y % 16, (y + 1) % 16, y), block(500, 7, 3, 4, y)].concat()))
+      .collect();
+    let pattern = vec![CFAPattern::R, CFAPattern::G, CFAPattern::G, CFAPattern::B];
+    let dim = CFAPatternDim { width: 2, height: 2 };
+    let single = Arw2Decompressor::new(&data, width, height, &pattern, dim.clone())
+      .with_threads(1)
+      .decode(None)
+      .unwrap();
+    let multi = Arw2Decompressor::new(&data, width, height, &pattern, dim.clone())
+      .with_threads(4)
+      .decode(None)
+      .unwrap();
+    assert_eq!(single.data(), multi.data());
+    let roi = Arw2Decompressor::new(&data, width, height, &pattern, dim)
+      .decode(Some(Rect { x: 4, y: 10, width: 3, height: 2 }))
+      .unwrap();
+    assert_eq!(roi.data()[width * 10..width * 12], single.data()[width * 10..width * 12]);
+    assert!(roi.data()[..width * 10].iter().chain(&roi.data()[width * 12..]).all(|it| *it == 0));
+  }
+
+  #[test]
+  fn test_roi_matches_crop() {
+    let (width, height) = (64, 40);
+    let data: Vec<u8> = (0..height as u32)
+      .flat_map(|y| (0..2).flat_map(move |i| {
+        pack(&[block(200 + y, y + i, y % 16, (y + 5) % 16, y), block(900, 3 * i, 7, 2, y + i)].concat())
+      }))
+      .collect();
+    let pattern = vec![CFAPattern::R, CFAPattern::G, CFAPattern::G, CFAPattern::B];
+    let dim = CFAPatternDim { width: 2, height: 2 };
+    let full = Arw2Decompressor::new(&data, width, height, &pattern, dim.clone()).decode(None).unwrap();
+    // Inside, across the edges, and reaching past u32::MAX as render's margin does.
+    let crops = [
+      Rect { x: 5, y: 7, width: 20, height: 9 },
+      Rect { x: 50, y: 30, width: 100, height: 100 },
+      Rect { x: 0, y: 39, width: u32::MAX, height: u32::MAX - 39 },
+    ];
+    for crop in crops {
+      let roi = Arw2Decompressor::new(&data, width, height, &pattern, dim.clone())
+        .decode(Some(crop.expand(16)))
+        .unwrap();
+      let (a, b) = (roi.cropped_to(&crop), full.cropped_to(&crop));
+      assert_eq!((a.width(), a.height()), (b.width(), b.height()), "{:?}", crop);
+      assert!(a.width() > 0 && a.height() > 0);
+      assert_eq!(a.data(), b.data(), "{:?}", crop);
+    }
   }
 }
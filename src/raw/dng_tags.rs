@@ -0,0 +1,249 @@
+use byteordered::{Endian, Endianness};
+use crate::img::{BadPixelMap, Rect};
+use crate::stream::ByteStream;
+use crate::tiff::ImageFileDirectory;
+use crate::tiff::query::Value;
+
+/* [DNG] p.27-39 */
+const TAG_BLACK_LEVEL_REPEAT_DIM: u16 = 50713;
+const TAG_BLACK_LEVEL: u16 = 50714;
+const TAG_WHITE_LEVEL: u16 = 50717;
+const TAG_DEFAULT_CROP_ORIGIN: u16 = 50719;
+const TAG_DEFAULT_CROP_SIZE: u16 = 50720;
+const TAG_ACTIVE_AREA: u16 = 50829;
+const TAG_MASKED_AREAS: u16 = 50830;
+const TAG_OPCODE_LIST1: u16 = 51008;
+const TAG_COLOR_MATRIX1: u16 = 50721;
+const TAG_COLOR_MATRIX2: u16 = 50722;
+const TAG_CAMERA_CALIBRATION1: u16 = 50723;
+const TAG_CAMERA_CALIBRATION2: u16 = 50724;
+const TAG_ANALOG_BALANCE: u16 = 50727;
+const TAG_AS_SHOT_NEUTRAL: u16 = 50728;
+const TAG_BASELINE_EXPOSURE: u16 = 50730;
+const TAG_CALIBRATION_ILLUMINANT1: u16 = 50778;
+const TAG_CALIBRATION_ILLUMINANT2: u16 = 50779;
+const TAG_FORWARD_MATRIX1: u16 = 50964;
+const TAG_FORWARD_MATRIX2: u16 = 50965;
+
+/* [DNG] p.85-92, opcode lists are always big-endian */
+const OPCODE_FIX_BAD_PIXELS_LIST: u32 = 5;
+
+// DNG raw IFD tags describing levels and areas of the sensor.
+#[derive(Clone, Debug, Default)]
+pub struct DngRawTags {
+  // BlackLevel in row-major order over BlackLevelRepeatDim.
+  pub black: Option<Vec<u16>>,
+  pub black_repeat_dim: (usize, usize),
+  pub white: Option<u16>,
+  pub masked_areas: Vec<Rect>,
+  pub active_area: Option<Rect>,
+  // Relative to the active area.
+  pub default_crop: Option<Rect>,
+  // FixBadPixelsList opcodes from OpcodeList1.
+  pub bad_pixels: BadPixelMap,
+}
+
+impl DngRawTags {
+  pub fn read(stream: &mut ByteStream, ifd: &ImageFileDirectory) -> anyhow::Result<Self> {
+    let opcodes = match ifd.headers().iter().find(|it| it.tag == TAG_OPCODE_LIST1) {
+      Some(header) => match Value::read(stream, header)? {
+        Value::Blob(data) | Value::U8(data) => Some(data),
+        _ => None,
+      },
+      None => None,
+    };
+    let bad_pixels = match opcodes {
+      // Without a size there is no pixel to fix.
+      Some(data) => parse_bad_pixels(&data, ifd.image_width().unwrap_or(0), ifd.image_height().unwrap_or(0))?,
+      None => BadPixelMap::default(),
+    };
+    let mut read = |tag: u16| -> anyhow::Result<Option<Vec<f64>>> {
+      match ifd.headers().iter().find(|it| it.tag == tag) {
+        Some(header) => Ok(Value::read(stream, header)?.to_f64s()),
+        None => Ok(None),
+      }
+    };
+    let black_repeat_dim = match read(TAG_BLACK_LEVEL_REPEAT_DIM)? {
+      Some(v) if v.len() == 2 => (v[0] as usize, v[1] as usize),
+      _ => (1, 1),
+    };
+    let black = read(TAG_BLACK_LEVEL)?
+      .map(|v| v.iter().map(|it| it.round().clamp(0.0, u16::MAX as f64) as u16).collect());
+    let white = read(TAG_WHITE_LEVEL)?
+      .and_then(|v| v.first().copied())
+      .map(|it| it.clamp(0.0, u16::MAX as f64) as u16);
+    // Each area is top, left, bottom, right.
+    let masked_areas = read(TAG_MASKED_AREAS)?
+      .map(|v| v.chunks_exact(4).map(|it| Rect::from_tlbr(it[0] as u32, it[1] as u32, it[2] as u32, it[3] as u32)).collect())
+      .unwrap_or_default();
+    let active_area = read(TAG_ACTIVE_AREA)?
+      .filter(|v| v.len() == 4)
+      .map(|v| Rect::from_tlbr(v[0] as u32, v[1] as u32, v[2] as u32, v[3] as u32));
+    let default_crop = match (read(TAG_DEFAULT_CROP_ORIGIN)?, read(TAG_DEFAULT_CROP_SIZE)?) {
+      (origin, Some(size)) if size.len() == 2 => {
+        let origin = origin.filter(|it| it.len() == 2).unwrap_or_else(|| vec![0.0, 0.0]);
+        Some(Rect {
+          x: origin[0].round() as u32,
+          y: origin[1].round() as u32,
+          width: size[0].round() as u32,
+          height: size[1].round() as u32,
+        })
+      }
+      _ => None,
+    };
+    Ok(Self {
+      black,
+      black_repeat_dim,
+      white,
+      masked_areas,
+      active_area,
+      default_crop,
+      bad_pixels,
+    })
+  }
+
+  // Black levels for a CFA of `rows` x `cols`, if the repeat pattern fits it.
+  pub fn black_for_cfa(&self, rows: usize, cols: usize) -> Option<Vec<u16>> {
+    let black = self.black.as_ref()?;
+    let (repeat_rows, repeat_cols) = self.black_repeat_dim;
+    if repeat_rows == 0 || repeat_cols == 0 || !rows.is_multiple_of(repeat_rows) || !cols.is_multiple_of(repeat_cols) {
+      return None;
+    }
+    if black.len() < repeat_rows * repeat_cols {
+      return None;
+    }
+    let mut out = Vec::<u16>::with_capacity(rows * cols);
+    for y in 0..rows {
+      for x in 0..cols {
+        out.push(black[(y % repeat_rows) * repeat_cols + (x % repeat_cols)]);
+      }
+    }
+    Some(out)
+  }
+}
+
+// ColorMatrix, ForwardMatrix and CameraCalibration tags for one CalibrationIlluminant.
+#[derive(Clone, Debug, Default)]
+pub struct DngCalibration {
+  // EXIF LightSource code, 0 if unknown.
+  pub illuminant: u16,
+  // XYZ to camera, row-major with 3 columns.
+  pub color_matrix: Vec<f64>,
+  // White-balanced camera to XYZ (D50), row-major with one column per camera color.
+  pub forward_matrix: Option<Vec<f64>>,
+  // Square, one row and column per camera color.
+  pub camera_calibration: Option<Vec<f64>>,
+}
+
+// DNG IFD0 tags describing the color response of the camera.
+#[derive(Clone, Debug, Default)]
+pub struct DngColorTags {
+  pub calibrations: Vec<DngCalibration>,
+  pub analog_balance: Option<Vec<f64>>,
+  pub as_shot_neutral: Option<Vec<f64>>,
+  pub baseline_exposure: Option<f64>,
+}
+
+impl DngColorTags {
+  pub fn read(stream: &mut ByteStream, ifd: &ImageFileDirectory) -> anyhow::Result<Self> {
+    let mut read = |tag: u16| -> anyhow::Result<Option<Vec<f64>>> {
+      match ifd.headers().iter().find(|it| it.tag == tag) {
+        Some(header) => Ok(Value::read(stream, header)?.to_f64s()),
+        None => Ok(None),
+      }
+    };
+    let mut calibrations = Vec::<DngCalibration>::new();
+    let tags = [
+      (TAG_COLOR_MATRIX1, TAG_CALIBRATION_ILLUMINANT1, TAG_FORWARD_MATRIX1, TAG_CAMERA_CALIBRATION1),
+      (TAG_COLOR_MATRIX2, TAG_CALIBRATION_ILLUMINANT2, TAG_FORWARD_MATRIX2, TAG_CAMERA_CALIBRATION2),
+    ];
+    for (matrix, illuminant, forward, calibration) in tags {
+      if let Some(color_matrix) = read(matrix)? {
+        calibrations.push(DngCalibration {
+          illuminant: read(illuminant)?.and_then(|it| it.first().copied()).unwrap_or(0.0) as u16,
+          color_matrix,
+          forward_matrix: read(forward)?,
+          camera_calibration: read(calibration)?,
+        });
+      }
+    }
+    Ok(Self {
+      calibrations,
+      analog_balance: read(TAG_ANALOG_BALANCE)?,
+      as_shot_neutral: read(TAG_AS_SHOT_NEUTRAL)?,
+      baseline_exposure: read(TAG_BASELINE_EXPOSURE)?.and_then(|it| it.first().copied()),
+    })
+  }
+}
+
+// Points and rectangles of every FixBadPixelsList opcode in an opcode list. Rectangles come from
+// the file and are clipped to the width x height image before their pixels are listed.
+fn parse_bad_pixels(data: &[u8], width: u32, height: u32) -> anyhow::Result<BadPixelMap> {
+  let truncated = || anyhow::Error::msg("OpcodeList1 is truncated");
+  let be = Endianness::Big;
+  let u32_at = |offset: usize| -> anyhow::Result<u32> {
+    Ok(be.read_u32(data.get(offset..offset + 4).ok_or_else(truncated)?)?)
+  };
+  let mut map = BadPixelMap::default();
+  let count = u32_at(0)?;
+  let mut offset = 4_usize;
+  for _ in 0..count {
+    // ID, version, flags, then the size of the parameters.
+    let id = u32_at(offset)?;
+    let size = u32_at(offset + 12)? as usize;
+    let params = offset + 16;
+    offset = params + size;
+    if id != OPCODE_FIX_BAD_PIXELS_LIST {
+      continue;
+    }
+    // BayerPhase, BadPointCount, BadRectCount, then points as (row, column) and rectangles as (top, left, bottom, right).
+    let points = u32_at(params + 4)? as usize;
+    let rects = u32_at(params + 8)? as usize;
+    let mut p = params + 12;
+    for _ in 0..points {
+      map.insert(u32_at(p + 4)?, u32_at(p)?);
+      p += 8;
+    }
+    for _ in 0..rects {
+      let (top, left, bottom, right) = (u32_at(p)?, u32_at(p + 4)?, u32_at(p + 8)?, u32_at(p + 12)?);
+      if bottom < top || right < left {
+        return Err(anyhow::Error::msg(format!(
+          "FixBadPixelsList: inverted rectangle (top: {}, left: {}, bottom: {}, right: {})", top, left, bottom, right)));
+      }
+      map.insert_rect(&Rect::from_tlbr(top, left, bottom, right).clip(width as usize, height as usize));
+      p += 16;
+    }
+  }
+  Ok(map)
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  #[test]
+  fn test_parse_bad_pixels() {
+    let words: [u32; 17] = [
+      2,
+      // An unknown opcode with 4 bytes of parameters.
+      99, 1, 0, 4, 0,
+      // FixBadPixelsList with one point at row 3, column 7 and a 2x1 rectangle.
+      OPCODE_FIX_BAD_PIXELS_LIST, 1, 0, 36, 0, 1, 1, 3, 7, 10, 20,
+    ];
+    let mut data: Vec<u8> = words.iter().flat_map(|it| it.to_be_bytes()).collect();
+    data.extend([11_u32, 22].iter().flat_map(|it| it.to_be_bytes()));
+    let map = parse_bad_pixels(&data, 100, 100).unwrap();
+    assert_eq!(map.iter().collect::<Vec<_>>(), vec![(7, 3), (20, 10), (21, 10)]);
+
+    // Rectangles only cover the image, however large they claim to be.
+    let rect = |tlbr: [u32; 4]| {
+      let words = [1, OPCODE_FIX_BAD_PIXELS_LIST, 1, 0, 28, 0, 0, 1];
+      words.iter().chain(tlbr.iter()).flat_map(|it| it.to_be_bytes()).collect::<Vec<u8>>()
+    };
+    let map = parse_bad_pixels(&rect([1, 2, u32::MAX, u32::MAX]), 4, 3).unwrap();
+    assert_eq!(map.iter().collect::<Vec<_>>(), vec![(2, 1), (3, 1), (2, 2), (3, 2)]);
+    assert!(parse_bad_pixels(&rect([u32::MAX, 0, u32::MAX, u32::MAX]), 4, 3).unwrap().is_empty());
+    assert!(parse_bad_pixels(&rect([2, 0, 1, 4]), 4, 3).is_err());
+    assert!(parse_bad_pixels(&rect([0, 3, 1, 2]), 4, 3).is_err());
+  }
+}
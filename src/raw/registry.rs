@@ -0,0 +1,161 @@
+use std::sync::Arc;
+use crate::camera::{Camera, CameraDb};
+use crate::img::Scale;
+use crate::raw::{ArwDecoder, RawDecoder};
+use crate::stream::ByteStream;
+use crate::tiff::Tiff;
+
+// Bytes read from the start of the file for signature sniffing.
+const SIGNATURE_SIZE: u64 = 16;
+
+#[derive(Clone, Debug, Default)]
+pub struct DecodeOptions {
+  // Decoding threads. 0 uses every core.
+  pub threads: usize,
+  pub cameras: Arc<CameraDb>,
+  // Decoders return `RawImage::decimated` for quarter and eighth size, and may skip the dropped rows.
+  pub scale: Scale,
+}
+
+// A decoder known to `detect`. Add one here to support a new format.
+pub struct DecoderEntry {
+  pub name: &'static str,
+  // Checks the first bytes of the file.
+  pub sniff: fn(&[u8]) -> bool,
+  // Checks the parsed TIFF structure, usually Make and the raw IFD.
+  pub accepts: fn(&Tiff) -> bool,
+  pub create: for<'a> fn(&'a mut ByteStream, &'a Tiff, &DecodeOptions) -> Box<dyn RawDecoder + 'a>,
+}
+
+pub static DECODERS: &[DecoderEntry] = &[
+  DecoderEntry {
+    name: "ARW",
+    sniff: is_tiff,
+    accepts: ArwDecoder::accepts,
+    create: create_arw,
+  },
+];
+
+fn is_tiff(header: &[u8]) -> bool {
+  header.starts_with(&[0x49, 0x49, 0x2A, 0x00]) || header.starts_with(&[0x4D, 0x4D, 0x00, 0x2A])
+}
+
+fn create_arw<'a>(stream: &'a mut ByteStream, tiff: &'a Tiff, options: &DecodeOptions) -> Box<dyn RawDecoder + 'a> {
+  Box::new(ArwDecoder::new(stream, tiff)
+    .with_threads(options.threads)
+    .with_cameras(options.cameras.clone())
+    .with_scale(options.scale))
+}
+
+pub struct Detection<'a> {
+  pub decoder: Box<dyn RawDecoder + 'a>,
+  pub make: Option<String>,
+  pub model: Option<String>,
+  pub variant: Option<String>,
+  // Database entry for the camera and variant, if known.
+  pub camera: Option<Camera>,
+}
+
+impl <'a> Detection<'a> {
+  pub fn describe(&self) -> String {
+    format!(
+      "{} ({}), camera: {} {}{}",
+      self.decoder.name(),
+      self.variant.as_deref().unwrap_or("unknown variant"),
+      self.make.as_deref().unwrap_or("unknown make"),
+      self.model.as_deref().unwrap_or("unknown model"),
+      if self.camera.is_some() { "" } else { " (not in the camera database)" })
+  }
+}
+
+// Picks the first decoder in `DECODERS` accepting the file.
+pub fn detect<'a>(stream: &'a mut ByteStream, tiff: &'a Tiff, options: &DecodeOptions) -> anyhow::Result<Detection<'a>> {
+  let size = stream.size()?.min(SIGNATURE_SIZE);
+  let header = stream.fetch_vec_u8(0, size as usize)?;
+  let root = tiff.root_ifd();
+  let make = root.and_then(|it| it.make()).map(|it| it.trim().to_string());
+  let model = root.and_then(|it| it.model()).map(|it| it.trim().to_string());
+  let Some(entry) = DECODERS.iter().find(|it| (it.sniff)(&header) && (it.accepts)(tiff)) else {
+    return Err(anyhow::Error::msg(format!(
+      "No decoder supports this file (make: {}, model: {}). Known decoders: {}",
+      make.as_deref().unwrap_or("unknown"),
+      model.as_deref().unwrap_or("unknown"),
+      DECODERS.iter().map(|it| it.name).collect::<Vec<_>>().join(", "))));
+  };
+  let decoder = (entry.create)(stream, tiff, options);
+  debug_assert!(decoder.is_acceptable(), "[BUG] {} disagrees with its own `accepts`", entry.name);
+  let variant = decoder.variant();
+  let camera = options.cameras.lookup(
+    make.as_deref().unwrap_or_default(),
+    model.as_deref().unwrap_or_default(),
+    variant.as_deref());
+  if let Some(camera) = camera.as_ref().filter(|it| !it.is_supported()) {
+    return Err(anyhow::Error::msg(format!(
+      "{} {} ({}) is marked as unsupported in the camera database",
+      camera.make,
+      model.as_deref().unwrap_or("unknown model"),
+      variant.as_deref().unwrap_or("unknown variant"))));
+  }
+  Ok(Detection {
+    decoder,
+    make,
+    model,
+    variant,
+    camera,
+  })
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use crate::tiff::builder::{ascii, long, short, TiffBuilder};
+  use crate::tiff::Parser;
+
+  // An ARW2-like file: one 4x2 strip of 8 bytes.
+  fn arw(make: &str) -> ByteStream {
+    let mut builder = TiffBuilder::new();
+    let strip = builder.blob(&[0; 8]);
+    let ifd0 = builder.ifd(vec![
+      short(256, &[4]),
+      short(257, &[2]),
+      short(259, &[32767]),
+      ascii(271, make),
+      ascii(272, "TEST"),
+      long(273, &[strip]),
+      long(279, &[8]),
+    ], 0);
+    ByteStream::from_bytes(builder.build(ifd0)).unwrap()
+  }
+
+  fn options(cameras: &str) -> DecodeOptions {
+    DecodeOptions {
+      cameras: Arc::new(CameraDb::parse(cameras).unwrap()),
+      ..DecodeOptions::default()
+    }
+  }
+
+  #[test]
+  fn test_detect() {
+    let jpeg = b"\xff\xd8\xff\xe0\0\x10JFIF";
+    assert!(ByteStream::from_bytes(jpeg).is_err());
+    assert!(DECODERS.iter().all(|it| !(it.sniff)(jpeg)));
+
+    let mut stream = arw("Canon");
+    let tiff = Parser::new(&mut stream).parse().unwrap();
+    let err = detect(&mut stream, &tiff, &options("")).err().unwrap();
+    assert_eq!(err.to_string(), "No decoder supports this file (make: Canon, model: TEST). Known decoders: ARW");
+
+    let mut stream = arw("SONY");
+    let tiff = Parser::new(&mut stream).parse().unwrap();
+    let detection = detect(&mut stream, &tiff, &options("")).unwrap();
+    assert_eq!(detection.describe(), "ARW (ARW2), camera: SONY TEST (not in the camera database)");
+    drop(detection);
+    let known = "[[camera]]\nmake = \"SONY\"\nmodel = \"TEST\"\n";
+    let detection = detect(&mut stream, &tiff, &options(known)).unwrap();
+    assert_eq!(detection.describe(), "ARW (ARW2), camera: SONY TEST");
+    drop(detection);
+    let unsupported = format!("{}supported = false\n", known);
+    let err = detect(&mut stream, &tiff, &options(&unsupported)).err().unwrap();
+    assert_eq!(err.to_string(), "SONY TEST (ARW2) is marked as unsupported in the camera database");
+  }
+}
@@ -0,0 +1,188 @@
+/*
+Sony SR2 private data.
+
+IFD0's DNGPrivateData holds the offset of the SR2Private IFD, which points to the SR2SubIFD.
+The parser reads SR2Private as an IFD of its own (`Entry::SR2Private`).
+The SR2SubIFD is encrypted, and holds black levels, white levels and white balance.
+
+References:
+
+dcraw:
+- sony_decrypt() and parse_tiff_ifd(), tags 29184, 29185 and 29217
+
+exiftool:
+- https://exiftool.org/TagNames/Sony.html#SR2Private
+- https://exiftool.org/TagNames/Sony.html#SR2SubIFD
+*/
+
+use std::collections::BTreeMap;
+use byteordered::{Endian, Endianness};
+use crate::stream::ByteStream;
+use crate::tiff::{DataType, Entry, Tiff};
+
+/* SR2Private */
+const TAG_SR2_SUB_IFD_OFFSET: u16 = 0x7200;
+const TAG_SR2_SUB_IFD_LENGTH: u16 = 0x7201;
+const TAG_SR2_SUB_IFD_KEY: u16 = 0x7221;
+
+/* SR2SubIFD */
+const TAG_BLACK_LEVEL: u16 = 0x7300;
+const TAG_WB_GRBG_LEVELS: u16 = 0x7303;
+const TAG_BLACK_LEVEL2: u16 = 0x7310;
+const TAG_WB_RGGB_LEVELS: u16 = 0x7313;
+const TAG_WHITE_LEVEL: u16 = 0x787f;
+
+// Decrypted SR2SubIFD entries with integer values.
+#[derive(Clone, Debug, Default)]
+pub struct Sr2 {
+  entries: BTreeMap<u16, Vec<i64>>,
+}
+
+impl Sr2 {
+  // None if the file has no SR2 data.
+  pub fn read(stream: &mut ByteStream, tiff: &Tiff) -> anyhow::Result<Option<Sr2>> {
+    let private = tiff.root_ifd().and_then(|ifd| ifd.find(|it| match it {
+      Entry::SR2Private(dirs) => dirs.first(),
+      _ => None,
+    }));
+    let Some(private) = private else {
+      return Ok(None);
+    };
+    let value = |tag: u16| private.headers().iter().find(|it| it.tag == tag).map(|it| it.data);
+    let (Some(offset), Some(length), Some(key)) =
+      (value(TAG_SR2_SUB_IFD_OFFSET), value(TAG_SR2_SUB_IFD_LENGTH), value(TAG_SR2_SUB_IFD_KEY)) else {
+      return Ok(None);
+    };
+    let mut data = stream.fetch_vec_u8(offset as u64, length as usize)?;
+    decrypt(&mut data, key);
+    Ok(Some(Self {
+      entries: parse_ifd(&data, stream.endian(), offset)?,
+    }))
+  }
+
+  pub fn value(&self, tag: u16) -> Option<&[i64]> {
+    self.entries.get(&tag).map(|it| it.as_slice())
+  }
+
+  // In R, G, G, B order.
+  pub fn black_levels_rggb(&self) -> Option<[u16; 4]> {
+    let v = self.value(TAG_BLACK_LEVEL).or_else(|| self.value(TAG_BLACK_LEVEL2))?;
+    if v.len() < 4 {
+      return None;
+    }
+    Some([v[0] as u16, v[1] as u16, v[2] as u16, v[3] as u16])
+  }
+
+  // As-shot white balance multipliers in R, G, G, B order.
+  pub fn white_balance_rggb(&self) -> Option<[f32; 4]> {
+    if let Some(v) = self.value(TAG_WB_RGGB_LEVELS).filter(|it| it.len() >= 4) {
+      return Some([v[0], v[1], v[2], v[3]].map(|it| it as f32));
+    }
+    let v = self.value(TAG_WB_GRBG_LEVELS).filter(|it| it.len() >= 4)?;
+    Some([v[1], v[0], v[3], v[2]].map(|it| it as f32))
+  }
+
+  // Three values, one per color; they are equal in practice.
+  pub fn white_level(&self) -> Option<u16> {
+    self.value(TAG_WHITE_LEVEL)?.iter().min().map(|it| *it as u16)
+  }
+}
+
+// dcraw's sony_decrypt(): XORs 32-bit words with a pad stream seeded by the key.
+fn decrypt(data: &mut [u8], key: u32) {
+  let mut pad = [0_u32; 128];
+  let mut key = key;
+  for p in pad.iter_mut().take(4) {
+    key = key.wrapping_mul(48828125).wrapping_add(1);
+    *p = key;
+  }
+  pad[3] = (pad[3] << 1) | ((pad[0] ^ pad[2]) >> 31);
+  for p in 4..127 {
+    pad[p] = ((pad[p - 4] ^ pad[p - 2]) << 1) | ((pad[p - 3] ^ pad[p - 1]) >> 31);
+  }
+  let mut p = 127_usize;
+  for word in data.chunks_exact_mut(4) {
+    p += 1;
+    pad[(p - 1) & 127] = pad[p & 127] ^ pad[(p + 64) & 127];
+    // dcraw stores the pad big-endian and XORs it with the bytes as they are in the file.
+    let mask = pad[(p - 1) & 127].to_be_bytes();
+    for (b, m) in word.iter_mut().zip(mask.iter()) {
+      *b ^= m;
+    }
+  }
+}
+
+// `base` is the file offset of `data`; out-of-line values point into the file.
+fn parse_ifd(data: &[u8], endian: Endianness, base: u32) -> anyhow::Result<BTreeMap<u16, Vec<i64>>> {
+  let truncated = || anyhow::Error::msg("SR2SubIFD is truncated");
+  let mut entries = BTreeMap::<u16, Vec<i64>>::new();
+  let count = endian.read_u16(data.get(0..2).ok_or_else(truncated)?)?;
+  for i in 0..count as usize {
+    let entry = data.get(2 + i * 12..2 + (i + 1) * 12).ok_or_else(truncated)?;
+    let tag = endian.read_u16(&entry[0..2])?;
+    let ty = DataType::from(endian.read_u16(&entry[2..4])?);
+    let count = endian.read_u32(&entry[4..8])? as usize;
+    let size = ty.size() * count;
+    let value = if size <= 4 {
+      &entry[8..8 + size]
+    } else {
+      let offset = (endian.read_u32(&entry[8..12])? as usize).checked_sub(base as usize);
+      match offset.and_then(|it| data.get(it..it + size)) {
+        Some(value) => value,
+        // Points outside of the SR2SubIFD; nothing we need lives there.
+        None => continue,
+      }
+    };
+    let mut values = Vec::<i64>::with_capacity(count);
+    for v in value.chunks_exact(ty.size()) {
+      values.push(match ty {
+        DataType::U8 | DataType::Blob => v[0] as i64,
+        DataType::S8 => v[0] as i8 as i64,
+        DataType::U16 => endian.read_u16(v)? as i64,
+        DataType::S16 => endian.read_i16(v)? as i64,
+        DataType::U32 => endian.read_u32(v)? as i64,
+        DataType::S32 => endian.read_i32(v)? as i64,
+        _ => break,
+      });
+    }
+    entries.insert(tag, values);
+  }
+  Ok(entries)
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  #[test]
+  fn test_decrypt_roundtrip() {
+    let plain: Vec<u8> = (0..64_u8).collect();
+    let mut data = plain.clone();
+    decrypt(&mut data, 0x12345678);
+    assert_ne!(data, plain);
+    decrypt(&mut data, 0x12345678);
+    assert_eq!(data, plain);
+  }
+
+  #[test]
+  fn test_parse_ifd() {
+    // Three entries: BlackLevel and WB_GRBGLevels (4 SHORTs each, out of line at base + 38 and base + 46)
+    // and WhiteLevel (1 SHORT, inline).
+    let base = 1000_u32;
+    let mut data = vec![3, 0];
+    data.extend([0x00, 0x73, 3, 0, 4, 0, 0, 0]);
+    data.extend((base + 38).to_le_bytes());
+    data.extend([0x03, 0x73, 3, 0, 4, 0, 0, 0]);
+    data.extend((base + 46).to_le_bytes());
+    data.extend([0x7f, 0x78, 3, 0, 1, 0, 0, 0, 0xff, 0x0f, 0, 0]);
+    for v in [512_u16, 513, 514, 515, 1024, 2048, 1536, 1025] {
+      data.extend(v.to_le_bytes());
+    }
+    let sr2 = Sr2 {
+      entries: parse_ifd(&data, Endianness::Little, base).unwrap(),
+    };
+    assert_eq!(sr2.black_levels_rggb(), Some([512, 513, 514, 515]));
+    assert_eq!(sr2.white_level(), Some(4095));
+    assert_eq!(sr2.white_balance_rggb(), Some([2048.0, 1024.0, 1025.0, 1536.0]));
+  }
+}
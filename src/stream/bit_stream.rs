@@ -1,41 +1,108 @@
-use std::cmp::min;
-use crate::stream::ByteStream;
+// Bit reader over a byte slice, least significant bit first as in ARW2, with a 64-bit cache refilled
+// up to 8 bytes at a time.
+
+const MAX_READ_BITS: u8 = 32;
+
+fn check_bits(bits: u8) -> anyhow::Result<()> {
+  if bits > MAX_READ_BITS {
+    return Err(anyhow::Error::msg(format!("BitStream: can't read {} bits at once", bits)));
+  }
+  Ok(())
+}
+
+fn end_of_data() -> anyhow::Error {
+  anyhow::Error::msg("BitStream: unexpected end of data")
+}
 
 pub struct BitStream<'a> {
-  stream: &'a mut ByteStream,
-  buff: u32,
-  buff_left: u8,
+  data: &'a [u8],
+  pos: usize,
+  // Next bit is the least significant one.
+  cache: u64,
+  cache_bits: u8,
 }
 
 impl <'a> BitStream<'a> {
-  pub fn new(stream: &'a mut ByteStream) -> Self {
+  pub fn new(data: &'a [u8]) -> Self {
     Self {
-      stream,
-      buff: 0,
-      buff_left: 0,
+      data,
+      pos: 0,
+      cache: 0,
+      cache_bits: 0,
     }
   }
-  pub fn read_bits(&mut self, bits: u8) -> anyhow::Result<u32> {
-    let mut r: u32 = 0;
-    let mut loaded_bits = 0;
-    while loaded_bits < bits {
-      if self.buff_left == 0 {
-        self.buff = self.stream.read_u32()?;
-        self.buff_left = 32;
+  fn refill(&mut self) {
+    if self.pos + 8 <= self.data.len() {
+      let bytes = ((64 - self.cache_bits) / 8) as usize;
+      let mut word = [0_u8; 8];
+      word[..bytes].copy_from_slice(&self.data[self.pos..self.pos + bytes]);
+      self.cache |= u64::from_le_bytes(word) << self.cache_bits;
+      self.cache_bits += (bytes * 8) as u8;
+      self.pos += bytes;
+      return;
+    }
+    while self.cache_bits <= 56 && self.pos < self.data.len() {
+      self.cache |= (self.data[self.pos] as u64) << self.cache_bits;
+      self.cache_bits += 8;
+      self.pos += 1;
+    }
+  }
+  pub fn peek_bits(&mut self, bits: u8) -> anyhow::Result<u32> {
+    check_bits(bits)?;
+    if self.cache_bits < bits {
+      self.refill();
+      if self.cache_bits < bits {
+        return Err(end_of_data());
       }
-      r = r | (self.buff & 1) << loaded_bits;
-      self.buff >>= 1;
-      loaded_bits += 1;
-      self.buff_left -= 1;
     }
+    Ok((self.cache & ((1_u64 << bits) - 1)) as u32)
+  }
+  pub fn read_bits(&mut self, bits: u8) -> anyhow::Result<u32> {
+    let r = self.peek_bits(bits)?;
+    self.cache >>= bits;
+    self.cache_bits -= bits;
     Ok(r)
   }
 }
 
 #[cfg(test)]
 mod test {
+  use super::*;
+
+  // The bit-by-bit reader this module replaced, reading little-endian 32-bit words.
+  fn read_bits_naive(data: &[u8], widths: &[u8]) -> Vec<u32> {
+    let mut bit = 0_usize;
+    widths.iter().map(|w| {
+      let mut r = 0_u32;
+      for i in 0..*w {
+        let word = u32::from_le_bytes(data[bit / 32 * 4..bit / 32 * 4 + 4].try_into().unwrap());
+        r |= ((word >> (bit % 32)) & 1) << i;
+        bit += 1;
+      }
+      r
+    }).collect()
+  }
+
   #[test]
   fn basic() {
+    let data: Vec<u8> = (0..64_u32).map(|i| (i * 37 + 11) as u8).collect();
+    let widths: Vec<u8> = vec![
+      11, 11, 4, 4, 7, 7, 7, 32, 1, 0, 3, 7, 7, 7, 7, 7,
+      7, 7, 7, 7, 11, 11, 4, 4, 7, 7, 7, 7, 32, 32, 32, 5,
+    ];
+    let expected = read_bits_naive(&data, &widths);
+    let mut bits = BitStream::new(&data);
+    let actual: Vec<u32> = widths.iter().map(|w| bits.read_bits(*w).unwrap()).collect();
+    assert_eq!(actual, expected);
+  }
 
+  #[test]
+  fn test_lsb_end_of_data() {
+    let data = [0xA5_u8, 0x0F];
+    let mut bits = BitStream::new(&data);
+    assert_eq!(bits.peek_bits(4).unwrap(), 0x5);
+    assert_eq!(bits.read_bits(12).unwrap(), 0xFA5);
+    assert_eq!(bits.read_bits(4).unwrap(), 0x0);
+    assert!(bits.read_bits(1).is_err());
   }
 }
@@ -1,20 +1,48 @@
-use std::io::{Read, Seek, SeekFrom};
+use std::io::{Cursor, Read, Seek, SeekFrom};
 use std::path::Path;
 use byteordered::{Endian, Endianness};
 use byteordered::byteorder::ReadBytesExt;
 use crate::tiff::data_type::{UnsignedRational, SignedRational};
 
+// Bytes held in memory, e.g. a Vec<u8>, a &'static [u8] or a memory-mapped file.
+struct Bytes(Box<dyn AsRef<[u8]>>);
+
+impl AsRef<[u8]> for Bytes {
+  fn as_ref(&self) -> &[u8] {
+    self.0.as_ref().as_ref()
+  }
+}
+
 pub struct ByteStream {
   endian: Endianness,
-  file: std::fs::File,
+  source: Cursor<Bytes>,
 }
 
 impl ByteStream {
+  // Maps the file into memory, so reads and slices don't need a syscall each.
   pub fn open(path: impl AsRef<Path>) -> std::io::Result<ByteStream> {
-    let mut file = std::fs::File::open(path)?;
+    let file = std::fs::File::open(path)?;
+    // SAFETY: The file must not be truncated or modified while it is mapped.
+    // We only read files the user asked us to, and treat them as read-only inputs.
+    let mmap = unsafe { memmap2::Mmap::map(&file)? };
+    Self::from_bytes(mmap)
+  }
+
+  pub fn from_bytes(bytes: impl AsRef<[u8]> + 'static) -> std::io::Result<ByteStream> {
+    Self::new(Cursor::new(Bytes(Box::new(bytes))))
+  }
+
+  // Standard input can't seek, so it is read into memory first.
+  pub fn from_stdin() -> std::io::Result<ByteStream> {
+    let mut buff = Vec::<u8>::new();
+    std::io::stdin().lock().read_to_end(&mut buff)?;
+    Self::from_bytes(buff)
+  }
+
+  fn new(mut source: Cursor<Bytes>) -> std::io::Result<ByteStream> {
     let endian = {
       let mut header: [u8; 2] = [0, 0];
-      file.read_exact(&mut header)?;
+      source.read_exact(&mut header)?;
       if header == [0x4D, 0x4D] {
         Endianness::Big
       } else if header == [0x49, 0x49] {
@@ -23,16 +51,20 @@ impl ByteStream {
         return Err(std::io::Error::new(std::io::ErrorKind::InvalidInput, "Not a TIFF file."));
       }
     };
-    file.seek(SeekFrom::Start(0))?;
+    source.seek(SeekFrom::Start(0))?;
     Ok(Self {
       endian,
-      file,
+      source,
     })
   }
 
+  pub fn endian(&self) -> Endianness {
+    self.endian
+  }
+
   /* u8 */
   pub fn read_u8(&mut self) -> std::io::Result<u8> {
-    self.file.read_u8()
+    self.source.read_u8()
   }
   pub fn fetch_u8(&mut self, offset: u64) -> std::io::Result<u8> {
     self.warp(offset, |s| s.read_u8())
@@ -40,7 +72,7 @@ impl ByteStream {
   pub fn read_vec_u8(&mut self, n: usize) -> std::io::Result<Vec<u8>> {
     let mut buff = Vec::<u8>::new();
     buff.resize(n, 0);
-    self.file.read_exact(&mut buff)?;
+    self.source.read_exact(&mut buff)?;
     Ok(buff)
   }
   pub fn fetch_vec_u8(&mut self, offset: u64, n: usize) -> std::io::Result<Vec<u8>> {
@@ -58,7 +90,7 @@ impl ByteStream {
 
   /* i8 */
   pub fn read_i8(&mut self) -> std::io::Result<i8> {
-    self.file.read_i8()
+    self.source.read_i8()
   }
   pub fn fetch_i8(&mut self, offset: u64) -> std::io::Result<i8> {
     self.warp(offset, |s| s.read_i8())
@@ -66,7 +98,7 @@ impl ByteStream {
   pub fn read_vec_i8(&mut self, n: usize) -> std::io::Result<Vec<i8>> {
     let mut buff = Vec::<i8>::new();
     buff.resize(n, 0);
-    self.file.read_i8_into(&mut buff)?;
+    self.source.read_i8_into(&mut buff)?;
     Ok(buff)
   }
   pub fn fetch_vec_i8(&mut self, offset: u64, n: usize) -> std::io::Result<Vec<i8>> {
@@ -75,15 +107,15 @@ impl ByteStream {
 
   /* u16 */
   pub fn read_u16(&mut self) -> std::io::Result<u16> {
-    self.endian.read_u16(&mut self.file)
+    self.endian.read_u16(&mut self.source)
   }
   pub fn fetch_u16(&mut self, offset: u64) -> std::io::Result<u16> {
-    self.warp(offset, |s| s.endian.read_u16(&mut s.file))
+    self.warp(offset, |s| s.endian.read_u16(&mut s.source))
   }
   pub fn read_vec_u16(&mut self, n: usize) -> std::io::Result<Vec<u16>> {
     let mut buff: Vec<u16> = Vec::new();
     buff.resize(n, 0);
-    self.endian.read_u16_into(&mut self.file, &mut buff)?;
+    self.endian.read_u16_into(&mut self.source, &mut buff)?;
     Ok(buff)
   }
   pub fn fetch_vec_u16(&mut self, offset: u64, n: usize) -> std::io::Result<Vec<u16>> {
@@ -92,7 +124,7 @@ impl ByteStream {
 
   /* s16 */
   pub fn read_i16(&mut self) -> std::io::Result<i16> {
-    self.endian.read_i16(&mut self.file)
+    self.endian.read_i16(&mut self.source)
   }
   pub fn fetch_i16(&mut self, offset: u64) -> std::io::Result<i16> {
     self.warp(offset, |s| s.read_i16())
@@ -100,7 +132,7 @@ impl ByteStream {
   pub fn read_vec_i16(&mut self, n: usize) -> std::io::Result<Vec<i16>> {
     let mut buff: Vec<i16> = Vec::new();
     buff.resize(n, 0);
-    self.endian.read_i16_into(&mut self.file, &mut buff)?;
+    self.endian.read_i16_into(&mut self.source, &mut buff)?;
     Ok(buff)
   }
   pub fn fetch_vec_i16(&mut self, offset: u64, n: usize) -> std::io::Result<Vec<i16>> {
@@ -109,7 +141,7 @@ impl ByteStream {
 
   /* u32 */
   pub fn read_u32(&mut self) -> std::io::Result<u32> {
-    self.endian.read_u32(&mut self.file)
+    self.endian.read_u32(&mut self.source)
   }
   pub fn fetch_u32(&mut self, offset: u64) -> std::io::Result<u32> {
     self.warp(offset, |s| s.read_u32())
@@ -117,7 +149,7 @@ impl ByteStream {
   pub fn read_vec_u32(&mut self, n: usize) -> std::io::Result<Vec<u32>> {
     let mut buff: Vec<u32> = Vec::new();
     buff.resize(n, 0);
-    self.endian.read_u32_into(&mut self.file, &mut buff)?;
+    self.endian.read_u32_into(&mut self.source, &mut buff)?;
     Ok(buff)
   }
   pub fn fetch_vec_u32(&mut self, offset: u64, n: usize) -> std::io::Result<Vec<u32>> {
@@ -126,7 +158,7 @@ impl ByteStream {
 
   /* s32 */
   pub fn read_i32(&mut self) -> std::io::Result<i32> {
-    self.endian.read_i32(&mut self.file)
+    self.endian.read_i32(&mut self.source)
   }
   pub fn fetch_i32(&mut self, offset: u64) -> std::io::Result<i32> {
     self.warp(offset, |s| s.read_i32())
@@ -134,7 +166,7 @@ impl ByteStream {
   pub fn read_vec_i32(&mut self, n: usize) -> std::io::Result<Vec<i32>> {
     let mut buff: Vec<i32> = Vec::new();
     buff.resize(n, 0);
-    self.endian.read_i32_into(&mut self.file, &mut buff)?;
+    self.endian.read_i32_into(&mut self.source, &mut buff)?;
     Ok(buff)
   }
   pub fn fetch_vec_i32(&mut self, offset: u64, n: usize) -> std::io::Result<Vec<i32>> {
@@ -143,7 +175,7 @@ impl ByteStream {
 
   /* f32 */
   pub fn read_f32(&mut self) -> std::io::Result<f32> {
-    self.endian.read_f32(&mut self.file)
+    self.endian.read_f32(&mut self.source)
   }
   pub fn fetch_f32(&mut self, offset: u64) -> std::io::Result<f32> {
     self.warp(offset, |s| s.read_f32())
@@ -151,7 +183,7 @@ impl ByteStream {
   pub fn read_vec_f32(&mut self, n: usize) -> std::io::Result<Vec<f32>> {
     let mut buff: Vec<f32> = Vec::new();
     buff.resize(n, 0.0);
-    self.endian.read_f32_into(&mut self.file, &mut buff)?;
+    self.endian.read_f32_into(&mut self.source, &mut buff)?;
     Ok(buff)
   }
   pub fn fetch_vec_f32(&mut self, offset: u64, n: usize) -> std::io::Result<Vec<f32>> {
@@ -160,7 +192,7 @@ impl ByteStream {
 
   /* f64 */
   pub fn read_f64(&mut self) -> std::io::Result<f64> {
-    self.endian.read_f64(&mut self.file)
+    self.endian.read_f64(&mut self.source)
   }
   pub fn fetch_f64(&mut self, offset: u64) -> std::io::Result<f64> {
     self.warp(offset, |s| s.read_f64())
@@ -168,7 +200,7 @@ impl ByteStream {
   pub fn read_vec_f64(&mut self, n: usize) -> std::io::Result<Vec<f64>> {
     let mut buff: Vec<f64> = Vec::new();
     buff.resize(n, 0.0);
-    self.endian.read_f64_into(&mut self.file, &mut buff)?;
+    self.endian.read_f64_into(&mut self.source, &mut buff)?;
     Ok(buff)
   }
   pub fn fetch_vec_f64(&mut self, offset: u64, n: usize) -> std::io::Result<Vec<f64>> {
@@ -178,7 +210,7 @@ impl ByteStream {
   /* rational */
   pub fn read_unsigned_rational(&mut self) -> std::io::Result<UnsignedRational> {
     let mut buff: [u32; 2] = [0, 0];
-    self.endian.read_u32_into(&mut self.file, &mut buff)?;
+    self.endian.read_u32_into(&mut self.source, &mut buff)?;
     Ok(UnsignedRational {
       numerator: buff[0],
       denominator: buff[1],
@@ -202,7 +234,7 @@ impl ByteStream {
   /* SRational */
   pub fn read_signed_rational(&mut self) -> std::io::Result<SignedRational> {
     let mut buff: [i32; 2] = [0, 0];
-    self.endian.read_i32_into(&mut self.file, &mut buff)?;
+    self.endian.read_i32_into(&mut self.source, &mut buff)?;
     Ok(SignedRational {
       numerator: buff[0],
       denominator: buff[1],
@@ -223,19 +255,44 @@ impl ByteStream {
     self.warp(offset, |s| s.read_vec_signed_rational(n))
   }
 
+  /* Slice */
+  // Borrows the bytes without copying them.
+  pub fn fetch_slice(&mut self, offset: u64, n: usize) -> std::io::Result<&[u8]> {
+    let data = self.as_slice();
+    let range = usize::try_from(offset).ok()
+      .and_then(|start| Some(start..start.checked_add(n)?))
+      .filter(|range| range.end <= data.len());
+    match range {
+      Some(range) => Ok(&data[range]),
+      None => Err(std::io::Error::new(
+        std::io::ErrorKind::UnexpectedEof,
+        format!("{} bytes at {} are out of the {} bytes source", n, offset, data.len()))),
+    }
+  }
+
+  // The whole source.
+  pub fn as_slice(&self) -> &[u8] {
+    self.source.get_ref().as_ref()
+  }
+
   /* Skip */
   pub fn skip(&mut self, bytes: i64) -> std::io::Result<()> {
-    self.file.seek(SeekFrom::Current(bytes))?;
+    self.source.seek(SeekFrom::Current(bytes))?;
     Ok(())
   }
 
   pub fn seek(&mut self, offset: u64) -> std::io::Result<()> {
-    self.file.seek(SeekFrom::Start(offset))?;
+    self.source.seek(SeekFrom::Start(offset))?;
     Ok(())
   }
 
   pub fn position(&mut self) -> std::io::Result<u64> {
-    self.file.stream_position()
+    self.source.stream_position()
+  }
+
+  // Total length of the underlying source in bytes.
+  pub fn size(&mut self) -> std::io::Result<u64> {
+    self.fork(|s| s.source.seek(SeekFrom::End(0)))
   }
 
   fn warp<'s, Fn, T>(&'s mut self, offset: u64, f: Fn) -> std::io::Result<T>
@@ -298,4 +355,23 @@ mod test {
     }).expect("Failed to run test");
     assert_eq!(stream.position().expect("Failed to get pos"), 4);
   }
+
+  #[test]
+  fn test_from_bytes() {
+    let bytes: Vec<u8> = vec![0x4D, 0x4D, 0x00, 0x2A, 0x00, 0x00, 0x00, 0x08];
+    let mut stream = ByteStream::from_bytes(bytes).expect("Failed to open");
+    assert_eq!(stream.fetch_u16(2).expect("Failed to read"), 42);
+    assert_eq!(stream.fetch_u32(4).expect("Failed to read"), 8);
+    assert_eq!(stream.size().expect("Failed to get size"), 8);
+    assert!(ByteStream::from_bytes(&[0_u8, 0, 0, 0][..] as &'static [u8]).is_err());
+  }
+
+  #[test]
+  fn test_fetch_slice() {
+    let bytes: Vec<u8> = vec![0x49, 0x49, 0x2A, 0x00, 0x08, 0x00, 0x00, 0x00];
+    let mut stream = ByteStream::from_bytes(bytes.clone()).expect("Failed to open");
+    let slice = stream.fetch_slice(2, 4).expect("Failed to slice");
+    assert_eq!(slice, &bytes[2..6]);
+    assert!(stream.fetch_slice(6, 4).is_err());
+  }
 }
@@ -1,8 +1,15 @@
 pub mod parser;
 pub mod dumper;
 pub mod data_type;
+pub mod layout;
+pub mod tags;
+pub mod query;
+pub mod validator;
+#[cfg(test)]
+pub mod builder;
 
 use log::info;
+use tags::IfdKind;
 pub use crate::stream::*;
 pub use parser::*;
 pub use data_type::*;
@@ -29,6 +36,19 @@ pub enum Compression {
   Undefined(u16),
 }
 
+impl Compression {
+  pub fn code(&self) -> u16 {
+    match self {
+      Compression::NoCompression => 1,
+      Compression::OldJpeg => 6,
+      Compression::BaselineJpeg => 7,
+      Compression::AdobeDeflate => 8,
+      Compression::SonyARW => 32767,
+      Compression::Undefined(code) => *code,
+    }
+  }
+}
+
 #[derive(Clone, Debug)]
 pub enum PhotometricInterpretation {
   Grayscale,
@@ -114,6 +134,10 @@ pub enum Entry {
     green_x: UnsignedRational, green_y: UnsignedRational,
     blue_x: UnsignedRational, blue_y: UnsignedRational,
   },
+  TileWidth(u32),
+  TileLength(u32),
+  TileOffsets(Vec<u32>),
+  TileByteCounts(Vec<u32>),
   SubIFDs(Vec<ImageFileDirectory>),
   JPEGInterChangeFormat(bool),
   JPEGInterChangeFormatLength(u32),
@@ -130,15 +154,52 @@ pub enum Entry {
   },
   CFAPattern(Vec<CFAPattern>),
   ExifIFD(Vec<ImageFileDirectory>),
+  GpsIFD(Vec<ImageFileDirectory>),
+  InteropIFD(Vec<ImageFileDirectory>),
   DNGVersion(Vec<u8>),
   DNGPrivateData(Vec<u8>),
+  // DNGPrivateData of Sony cameras, pointing to the SR2Private IFD.
+  SR2Private(Vec<ImageFileDirectory>),
+  // MakerNote of Sony cameras, an IFD after an optional header.
+  SonyMakerNote(Vec<ImageFileDirectory>),
   // Unknown by this parser.
   Unknown(u16, DataType, u32, u32)
 }
 
+// Raw 12-byte IFD entry as found in the file.
+#[derive(Clone, Debug)]
+pub struct EntryHeader {
+  // Position of the entry itself.
+  pub offset: u64,
+  pub tag: u16,
+  pub ty: DataType,
+  pub count: u32,
+  // Value, or offset to the value when it does not fit into 4 bytes.
+  pub data: u32,
+}
+
+impl EntryHeader {
+  pub fn value_size(&self) -> u64 {
+    self.ty.size() as u64 * self.count as u64
+  }
+  pub fn is_value_inline(&self) -> bool {
+    self.value_size() <= 4
+  }
+  pub fn value_offset(&self) -> u64 {
+    if self.is_value_inline() {
+      self.offset + 8
+    } else {
+      self.data as u64
+    }
+  }
+}
+
 #[derive(Clone, Debug)]
 pub struct ImageFileDirectory {
+  kind: IfdKind,
+  offset: u64,
   entries: Vec<Entry>,
+  headers: Vec<EntryHeader>,
 }
 
 #[derive(Clone, Debug)]
@@ -161,6 +222,11 @@ impl Tiff {
         for (i, v) in vs.iter().enumerate() {
           self.inspect_dir(i as i32, v, indent + 4);
         }
+      } else if let &Entry::Unknown(tag, ..) = ent {
+        match tags::lookup(dir.kind, tag) {
+          Some(it) => info!("{:indent$}- {}: {:?} // {}", " ", it.name, ent, it.description, indent = indent + 2),
+          None => info!("{:indent$}- {:?}", " ", ent, indent = indent + 2),
+        }
       } else {
         info!("{:indent$}- {:?}", " ", ent, indent = indent + 2);
       }
@@ -183,6 +249,42 @@ impl Tiff {
       }
     }
   }
+  // Visits every IFD, including SubIFDs, Exif, GPS, Interop and Sony IFDs, with a path such as `ifd0/subifd[1]`.
+  pub fn walk_ifds<'a>(&'a self, mut f: impl FnMut(&str, &'a ImageFileDirectory)) {
+    fn walk<'a>(path: &str, dir: &'a ImageFileDirectory, f: &mut impl FnMut(&str, &'a ImageFileDirectory)) {
+      f(path, dir);
+      for ent in &dir.entries {
+        match ent {
+          Entry::SubIFDs(dirs) => {
+            for (i, d) in dirs.iter().enumerate() {
+              walk(&format!("{}/subifd[{}]", path, i), d, f);
+            }
+          }
+          Entry::ExifIFD(dirs) | Entry::GpsIFD(dirs) | Entry::InteropIFD(dirs) |
+          Entry::SR2Private(dirs) | Entry::SonyMakerNote(dirs) => {
+            let name = match ent {
+              Entry::ExifIFD(_) => "exif",
+              Entry::GpsIFD(_) => "gps",
+              Entry::InteropIFD(_) => "interop",
+              Entry::SR2Private(_) => "sr2private",
+              _ => "makernote",
+            };
+            for (i, d) in dirs.iter().enumerate() {
+              if i == 0 {
+                walk(&format!("{}/{}", path, name), d, f);
+              } else {
+                walk(&format!("{}/{}[{}]", path, name, i), d, f);
+              }
+            }
+          }
+          _ => {}
+        }
+      }
+    }
+    for (i, dir) in self.directories.iter().enumerate() {
+      walk(&format!("ifd{}", i), dir, &mut f);
+    }
+  }
   pub fn image_file_directories(&self) -> &Vec<ImageFileDirectory> {
     &self.directories
   }
@@ -197,10 +299,28 @@ impl Tiff {
 }
 
 impl ImageFileDirectory {
+  pub fn kind(&self) -> IfdKind {
+    self.kind
+  }
+
+  pub fn offset(&self) -> u64 {
+    self.offset
+  }
+
+  // Size of the directory itself: entry count, entries and the next IFD offset.
+  pub fn size(&self) -> u64 {
+    2 + 12 * self.headers.len() as u64 + 4
+  }
+
   pub fn entries(&self) -> &Vec<Entry> {
     &self.entries
   }
 
+  // Raw headers, in the same order as `entries()`.
+  pub fn headers(&self) -> &Vec<EntryHeader> {
+    &self.headers
+  }
+
   pub fn find<'a, 'b, F, R>(&'a self, f: F) -> Option<R>
   where
     F: Fn(&'b Entry) -> Option<R>,
@@ -224,6 +344,13 @@ impl ImageFileDirectory {
     })
   }
 
+  pub fn model(&self) -> Option<&str> {
+    self.find(|it: &Entry| match it {
+      Entry::Model(str) => Some(str.as_str()),
+      _ => None,
+    })
+  }
+
   pub fn compression(&self) -> Option<Compression> {
     self.find(|it: &Entry| match it {
       Entry::Compression(compression) => {
@@ -281,6 +408,51 @@ impl ImageFileDirectory {
     })
   }
 
+  pub fn rows_per_strip(&self) -> Option<u32> {
+    self.find(|it: &Entry| match it {
+      Entry::RowsPerStrip(v) => {
+        Some(*v)
+      }
+      _ => None,
+    })
+  }
+
+  pub fn tile_offsets(&self) -> Option<&Vec<u32>> {
+    self.find(|it: &Entry| match it {
+      Entry::TileOffsets(v) => {
+        Some(v)
+      }
+      _ => None,
+    })
+  }
+
+  pub fn tile_byte_counts(&self) -> Option<&Vec<u32>> {
+    self.find(|it: &Entry| match it {
+      Entry::TileByteCounts(v) => {
+        Some(v)
+      }
+      _ => None,
+    })
+  }
+
+  pub fn tile_width(&self) -> Option<u32> {
+    self.find(|it: &Entry| match it {
+      Entry::TileWidth(v) => {
+        Some(*v)
+      }
+      _ => None,
+    })
+  }
+
+  pub fn tile_length(&self) -> Option<u32> {
+    self.find(|it: &Entry| match it {
+      Entry::TileLength(v) => {
+        Some(*v)
+      }
+      _ => None,
+    })
+  }
+
   pub fn image_width(&self) -> Option<u32> {
     self.find(|it: &Entry| match it {
       Entry::ImageWidth(v) => {
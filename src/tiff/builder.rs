@@ -0,0 +1,85 @@
+// Little-endian TIFF files assembled in memory, for tests.
+//
+// IFDs are written as they are added, so children come before the entries pointing to them.
+
+use super::DataType;
+
+pub struct RawEntry {
+  pub tag: u16,
+  pub ty: u16,
+  pub count: u32,
+  // Values, written inline when they fit in 4 bytes.
+  pub bytes: Vec<u8>,
+}
+
+fn type_code(ty: DataType) -> u16 {
+  (1..=12).find(|it| DataType::from(*it) == ty).unwrap_or(0)
+}
+
+pub fn entry(tag: u16, ty: DataType, count: u32, bytes: Vec<u8>) -> RawEntry {
+  RawEntry { tag, ty: type_code(ty), count, bytes }
+}
+
+pub fn ascii(tag: u16, text: &str) -> RawEntry {
+  let mut bytes = text.as_bytes().to_vec();
+  bytes.push(0);
+  entry(tag, DataType::Ascii, bytes.len() as u32, bytes)
+}
+
+// An entry whose `count` values were already written at `offset`.
+pub fn pointer(tag: u16, ty: DataType, count: u32, offset: u32) -> RawEntry {
+  RawEntry { tag, ty: type_code(ty), count, bytes: offset.to_le_bytes().to_vec() }
+}
+
+pub fn short(tag: u16, values: &[u16]) -> RawEntry {
+  entry(tag, DataType::U16, values.len() as u32, values.iter().flat_map(|it| it.to_le_bytes()).collect())
+}
+
+pub fn long(tag: u16, values: &[u32]) -> RawEntry {
+  entry(tag, DataType::U32, values.len() as u32, values.iter().flat_map(|it| it.to_le_bytes()).collect())
+}
+
+pub struct TiffBuilder {
+  data: Vec<u8>,
+}
+
+impl TiffBuilder {
+  pub fn new() -> Self {
+    Self { data: b"II\x2a\x00\0\0\0\0".to_vec() }
+  }
+
+  // Appends `bytes` at a word boundary and returns their offset.
+  pub fn blob(&mut self, bytes: &[u8]) -> u32 {
+    self.data.resize(self.data.len().next_multiple_of(2), 0);
+    let offset = self.data.len() as u32;
+    self.data.extend(bytes);
+    offset
+  }
+
+  // Writes an IFD of `entries`, given in tag order, followed by `next`, and returns its offset.
+  pub fn ifd(&mut self, entries: Vec<RawEntry>, next: u32) -> u32 {
+    let values: Vec<[u8; 4]> = entries.iter().map(|it| {
+      if it.bytes.len() <= 4 {
+        let mut inline = [0_u8; 4];
+        inline[..it.bytes.len()].copy_from_slice(&it.bytes);
+        inline
+      } else {
+        self.blob(&it.bytes).to_le_bytes()
+      }
+    }).collect();
+    let mut ifd = (entries.len() as u16).to_le_bytes().to_vec();
+    for (it, value) in entries.iter().zip(values) {
+      ifd.extend(it.tag.to_le_bytes());
+      ifd.extend(it.ty.to_le_bytes());
+      ifd.extend(it.count.to_le_bytes());
+      ifd.extend(value);
+    }
+    ifd.extend(next.to_le_bytes());
+    self.blob(&ifd)
+  }
+
+  pub fn build(mut self, first_ifd: u32) -> Vec<u8> {
+    self.data[4..8].copy_from_slice(&first_ifd.to_le_bytes());
+    self.data
+  }
+}
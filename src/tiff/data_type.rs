@@ -18,7 +18,7 @@ pub enum DataType {
 }
 
 impl DataType {
-  fn size(&self) -> usize {
+  pub fn size(&self) -> usize {
     match *self {
       DataType::U8 => 1,
       DataType::Ascii => 1,
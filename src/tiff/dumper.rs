@@ -1,67 +1,228 @@
 use std::fs::File;
 use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
 
 use crate::stream::ByteStream;
-use crate::tiff::{Entry, ImageFileDirectory, Tiff};
+use crate::tiff::{tags, ImageFileDirectory, Tiff};
+
+pub const MANIFEST_FILE_NAME: &str = "manifest.json";
+
+const TAG_STRIP_OFFSETS: u16 = 273;
+const TAG_STRIP_BYTE_COUNTS: u16 = 279;
+const TAG_TILE_OFFSETS: u16 = 324;
+const TAG_TILE_BYTE_COUNTS: u16 = 325;
+
+#[derive(Clone, Copy, Debug, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BlockKind {
+  Strip,
+  Tile,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct DumpedBlock {
+  pub file: String,
+  pub ifd: String,
+  pub kind: BlockKind,
+  pub index: usize,
+  pub offset: u32,
+  pub length: u32,
+  // Names of the tags holding the offset and length.
+  pub offset_tag: String,
+  pub length_tag: String,
+  pub compression: Option<String>,
+  pub width: Option<u32>,
+  pub height: Option<u32>,
+}
+
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct Manifest {
+  pub blocks: Vec<DumpedBlock>,
+}
 
 pub struct Dumper <'a> {
   stream: &'a mut ByteStream,
   image: &'a Tiff,
+  output_dir: PathBuf,
 }
 
 impl <'a> Dumper <'a> {
-  pub fn new(stream: &'a mut ByteStream, image: &'a Tiff) -> Self {
+  pub fn new(stream: &'a mut ByteStream, image: &'a Tiff, output_dir: impl AsRef<Path>) -> Self {
     Self {
       stream,
       image,
+      output_dir: output_dir.as_ref().to_path_buf(),
     }
   }
-  pub fn dump(&mut self) -> anyhow::Result<()> {
-    self.dump_directories("", &self.image.directories)
+  // Writes every strip and tile of every IFD into the output directory, then the manifest describing
+  // them. IFDs are named by their `Tiff::walk_ifds` path, as other commands do.
+  pub fn dump(&mut self) -> anyhow::Result<Manifest> {
+    std::fs::create_dir_all(&self.output_dir)?;
+    let mut manifest = Manifest::default();
+    let mut dirs = Vec::<(String, &ImageFileDirectory)>::new();
+    self.image.walk_ifds(|path, dir| dirs.push((path.to_string(), dir)));
+    for (path, dir) in dirs {
+      self.dump_directory(&mut manifest, &path, dir)?;
+    }
+    let f = File::create(self.output_dir.join(MANIFEST_FILE_NAME))?;
+    serde_json::to_writer_pretty(f, &manifest)?;
+    Ok(manifest)
   }
-  fn dump_directories(&mut self, depth: &str, dirs: &Vec<ImageFileDirectory>)
-    -> anyhow::Result<()>
-  {
-    for (idx, dir) in dirs.iter().enumerate() {
-      let next_depth = if depth == "" {
-        format!("{}", idx)
-      } else {
-        format!("{}-{}", depth, idx)
-      };
-      let mut offsets: Option<Vec<u32>> = None;
-      let mut bytes: Option<Vec<u32>> = None;
-      let mut strip_idx = 0 as usize;
-      for e in dir.entries.iter() {
-        match e {
-          Entry::StripOffsets(vs) => {
-            offsets = Some(vs.clone());
-          }
-          Entry::StripByteCounts(vs) => {
-            bytes = Some(vs.clone());
-          }
-          Entry::SubIFDs(dirs) => {
-            self.dump_directories(&next_depth, dirs)?;
-          }
-          _ => {}
-        }
+  fn dump_directory(&mut self, manifest: &mut Manifest, path: &str, dir: &ImageFileDirectory) -> anyhow::Result<()> {
+    match (dir.strip_byte_offsets(), dir.strip_byte_counts()) {
+      (None, None) => {}
+      (Some(offsets), Some(counts)) => {
+        self.dump_blocks(manifest, path, dir, BlockKind::Strip, offsets, counts)?;
       }
-      if offsets.is_none() && bytes.is_none() {
-        continue;
+      _ => {
+        return Err(anyhow::Error::msg(format!("{}: Both StripOffsets and StripByteCounts must be set.", path)));
       }
-      if let (Some(offsets), Some(bytes)) = (offsets, bytes) {
-        if offsets.len() != bytes.len() {
-          return Err(anyhow::Error::msg("StripOffsets.len != StripByteCounts.len"));
-        }
-        for (_idx, (offset, bytes)) in offsets.iter().zip(bytes.iter()).enumerate() {
-          self.stream.seek(*offset as u64)?;
-          let data = self.stream.read_vec_u8(*bytes as usize)?;
-          let mut f = File::create(format!("{}_{}.dump", next_depth, strip_idx))?;
-          f.write_all(&data)?;
-        }
-      } else {
-        return Err(anyhow::Error::msg("Both StripOffsets and StripByteCounts must be set."));
+    }
+    match (dir.tile_offsets(), dir.tile_byte_counts()) {
+      (None, None) => {}
+      (Some(offsets), Some(counts)) => {
+        self.dump_blocks(manifest, path, dir, BlockKind::Tile, offsets, counts)?;
       }
+      _ => {
+        return Err(anyhow::Error::msg(format!("{}: Both TileOffsets and TileByteCounts must be set.", path)));
+      }
+    }
+    Ok(())
+  }
+  fn dump_blocks(
+    &mut self,
+    manifest: &mut Manifest,
+    ifd_path: &str,
+    dir: &ImageFileDirectory,
+    kind: BlockKind,
+    offsets: &[u32],
+    counts: &[u32],
+  ) -> anyhow::Result<()> {
+    let (offset_tag, length_tag) = match kind {
+      BlockKind::Strip => (tag_name(dir, TAG_STRIP_OFFSETS), tag_name(dir, TAG_STRIP_BYTE_COUNTS)),
+      BlockKind::Tile => (tag_name(dir, TAG_TILE_OFFSETS), tag_name(dir, TAG_TILE_BYTE_COUNTS)),
+    };
+    if offsets.len() != counts.len() {
+      return Err(anyhow::Error::msg(format!(
+        "{} and {} differ in length: {} and {}",
+        offset_tag,
+        length_tag,
+        offsets.len(),
+        counts.len())));
+    }
+    let compression = dir.compression().map(|it| format!("{:?}", it));
+    // Paths nest with slashes, which can't be in file names.
+    let prefix = ifd_path.replace('/', "-");
+    for (idx, (offset, length)) in offsets.iter().zip(counts.iter()).enumerate() {
+      let (width, height) = block_dimensions(dir, kind, idx);
+      let file = match kind {
+        BlockKind::Strip => format!("{}_strip{}.dump", prefix, idx),
+        BlockKind::Tile => format!("{}_tile{}.dump", prefix, idx),
+      };
+      let data = self.stream.fetch_slice(*offset as u64, *length as usize)?;
+      let mut f = File::create(self.output_dir.join(&file))?;
+      f.write_all(&data)?;
+      manifest.blocks.push(DumpedBlock {
+        file,
+        ifd: ifd_path.to_string(),
+        kind,
+        index: idx,
+        offset: *offset,
+        length: *length,
+        offset_tag: offset_tag.clone(),
+        length_tag: length_tag.clone(),
+        compression: compression.clone(),
+        width,
+        height,
+      });
     }
     Ok(())
   }
 }
+
+// Registry name of `tag` in `dir`, or its ID.
+fn tag_name(dir: &ImageFileDirectory, tag: u16) -> String {
+  tags::lookup(dir.kind(), tag).map(|it| it.name.to_string()).unwrap_or_else(|| tag.to_string())
+}
+
+// Dimensions of the idx-th block in pixels.
+// The last strip only holds the remaining rows of the image.
+fn block_dimensions(dir: &ImageFileDirectory, kind: BlockKind, idx: usize) -> (Option<u32>, Option<u32>) {
+  match kind {
+    BlockKind::Strip => {
+      let width = dir.image_width();
+      let height = dir.image_height().map(|height| {
+        let rows = dir.rows_per_strip().unwrap_or(height).max(1);
+        let start = rows.saturating_mul(idx as u32);
+        std::cmp::min(rows, height.saturating_sub(start))
+      });
+      (width, height)
+    }
+    BlockKind::Tile => (dir.tile_width(), dir.tile_length()),
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use crate::tiff::builder::{long, short, TiffBuilder};
+  use crate::tiff::Parser;
+
+  #[test]
+  fn test_dump() {
+    let mut builder = TiffBuilder::new();
+    let strips = [
+      builder.blob(b"first"), builder.blob(b"second"), builder.blob(b"other"), builder.blob(b"sub"), builder.blob(b"exif"),
+    ];
+    // Every IFD has a strip 0, and the second one is uncompressed.
+    let ifd1 = builder.ifd(vec![
+      short(256, &[4]),
+      short(257, &[1]),
+      short(259, &[1]),
+      long(273, &[strips[2]]),
+      long(279, &[5]),
+    ], 0);
+    let sub_ifd = builder.ifd(vec![long(273, &[strips[3]]), long(279, &[3])], 0);
+    let exif = builder.ifd(vec![long(273, &[strips[4]]), long(279, &[4])], 0);
+    let ifd0 = builder.ifd(vec![
+      short(256, &[4]),
+      short(257, &[3]),
+      short(259, &[32767]),
+      long(273, &[strips[0], strips[1]]),
+      short(278, &[2]),
+      long(279, &[5, 6]),
+      long(330, &[sub_ifd]),
+      long(34665, &[exif]),
+    ], ifd1);
+    let mut stream = ByteStream::from_bytes(builder.build(ifd0)).unwrap();
+    let tiff = Parser::new(&mut stream).parse().unwrap();
+
+    let dir = std::env::temp_dir().join(format!("ag-test-dump-{}", std::process::id()));
+    Dumper::new(&mut stream, &tiff, &dir).dump().unwrap();
+    let read = |name: &str| std::fs::read(dir.join(name)).unwrap();
+    assert_eq!(read("ifd0_strip0.dump"), b"first");
+    assert_eq!(read("ifd0_strip1.dump"), b"second");
+    assert_eq!(read("ifd0-subifd[0]_strip0.dump"), b"sub");
+    assert_eq!(read("ifd0-exif_strip0.dump"), b"exif");
+    assert_eq!(read("ifd1_strip0.dump"), b"other");
+
+    let manifest: serde_json::Value = serde_json::from_slice(&read(MANIFEST_FILE_NAME)).unwrap();
+    let blocks = manifest["blocks"].as_array().unwrap();
+    // IFDs as `walk_ifds` names them.
+    let ifds: Vec<&str> = blocks.iter().map(|it| it["ifd"].as_str().unwrap()).collect();
+    assert_eq!(ifds, vec!["ifd0", "ifd0", "ifd0/subifd[0]", "ifd0/exif", "ifd1"]);
+    assert_eq!(blocks[1]["file"], "ifd0_strip1.dump");
+    assert_eq!(blocks[1]["kind"], "strip");
+    assert_eq!(blocks[1]["offset"], strips[1]);
+    assert_eq!(blocks[1]["length"], 6);
+    assert_eq!(blocks[1]["compression"], "SonyARW");
+    assert_eq!(blocks[1]["offset_tag"], "StripOffsets");
+    assert_eq!(blocks[1]["length_tag"], "StripByteCounts");
+    // The last strip holds the remaining row.
+    assert_eq!(blocks[1]["height"], 1);
+    assert_eq!(blocks[4]["compression"], "NoCompression");
+    std::fs::remove_dir_all(&dir).unwrap();
+  }
+}
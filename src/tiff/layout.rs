@@ -0,0 +1,246 @@
+use crate::stream::ByteStream;
+use crate::tiff::{tags, Entry, ImageFileDirectory, Tiff};
+
+// TIFF header: byte order, magic number and the offset to the first IFD.
+const HEADER_SIZE: u64 = 8;
+const TAG_MAKER_NOTE: u16 = 37500;
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum RegionKind {
+  Header,
+  Ifd,
+  TagValue,
+  Strip,
+  Tile,
+  MakerNote,
+  Preview,
+}
+
+#[derive(Clone, Debug)]
+pub struct Region {
+  pub start: u64,
+  pub end: u64,
+  pub kind: RegionKind,
+  pub label: String,
+}
+
+impl Region {
+  pub fn size(&self) -> u64 {
+    self.end - self.start
+  }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Issue {
+  // Indices into `Layout::regions`.
+  Overlap(usize, usize),
+  PastEof(usize),
+  Gap {
+    start: u64,
+    end: u64,
+  },
+}
+
+#[derive(Clone, Debug)]
+pub struct Layout {
+  pub file_size: u64,
+  // Sorted by start offset.
+  pub regions: Vec<Region>,
+  pub issues: Vec<Issue>,
+}
+
+impl Layout {
+  pub fn analyze(stream: &mut ByteStream, tiff: &Tiff) -> std::io::Result<Self> {
+    let file_size = stream.size()?;
+    Ok(Self::new(tiff, file_size))
+  }
+
+  pub fn new(tiff: &Tiff, file_size: u64) -> Self {
+    let mut regions = vec![Region {
+      start: 0,
+      end: HEADER_SIZE,
+      kind: RegionKind::Header,
+      label: "header".to_string(),
+    }];
+    tiff.walk_ifds(|path, dir| collect_regions(&mut regions, path, dir));
+    Self::from_regions(regions, file_size)
+  }
+
+  pub fn from_regions(mut regions: Vec<Region>, file_size: u64) -> Self {
+    regions.sort_by_key(|it| (it.start, it.end));
+    let issues = find_issues(&regions, file_size);
+    Self {
+      file_size,
+      regions,
+      issues,
+    }
+  }
+}
+
+fn collect_regions(regions: &mut Vec<Region>, path: &str, dir: &ImageFileDirectory) {
+  regions.push(Region {
+    start: dir.offset(),
+    end: dir.offset() + dir.size(),
+    kind: RegionKind::Ifd,
+    label: path.to_string(),
+  });
+  for (header, entry) in dir.headers().iter().zip(dir.entries().iter()) {
+    if header.is_value_inline() {
+      continue;
+    }
+    // A single child IFD offset is inline; the IFD itself is reported on its own.
+    if let Entry::SubIFDs(_) | Entry::ExifIFD(_) | Entry::GpsIFD(_) | Entry::InteropIFD(_) = entry {
+      if header.count == 1 {
+        continue;
+      }
+    }
+    // A parsed MakerNote is its IFD and values, which are reported on their own, after its header.
+    if let Entry::SonyMakerNote(dirs) = entry {
+      if let Some(ifd) = dirs.first().filter(|it| it.offset() > header.value_offset()) {
+        regions.push(Region {
+          start: header.value_offset(),
+          end: ifd.offset(),
+          kind: RegionKind::MakerNote,
+          label: format!("{}/MakerNote header", path),
+        });
+      }
+      continue;
+    }
+    let kind = if header.tag == TAG_MAKER_NOTE {
+      RegionKind::MakerNote
+    } else {
+      RegionKind::TagValue
+    };
+    regions.push(Region {
+      start: header.value_offset(),
+      end: header.value_offset() + header.value_size(),
+      kind,
+      label: match tags::lookup(dir.kind(), header.tag) {
+        Some(info) => format!("{}/{}", path, info.name),
+        None => format!("{}/{}", path, header.tag),
+      },
+    });
+  }
+  let blocks = [
+    (RegionKind::Strip, "strip", dir.strip_byte_offsets(), dir.strip_byte_counts()),
+    (RegionKind::Tile, "tile", dir.tile_offsets(), dir.tile_byte_counts()),
+  ];
+  for (kind, name, offsets, counts) in blocks {
+    if let (Some(offsets), Some(counts)) = (offsets, counts) {
+      for (i, (offset, count)) in offsets.iter().zip(counts.iter()).enumerate() {
+        regions.push(Region {
+          start: *offset as u64,
+          end: *offset as u64 + *count as u64,
+          kind,
+          label: format!("{} {}[{}]", path, name, i),
+        });
+      }
+    }
+  }
+  let preview_offset = dir.headers().iter().find(|it| it.tag == 513).map(|it| it.data);
+  let preview_length = dir.find(|it| match it {
+    Entry::JPEGInterChangeFormatLength(v) => Some(*v),
+    _ => None,
+  });
+  if let (Some(offset), Some(length)) = (preview_offset, preview_length) {
+    regions.push(Region {
+      start: offset as u64,
+      end: offset as u64 + length as u64,
+      kind: RegionKind::Preview,
+      label: format!("{} preview", path),
+    });
+  }
+}
+
+// `regions` must be sorted by start offset.
+fn find_issues(regions: &[Region], file_size: u64) -> Vec<Issue> {
+  let mut issues = Vec::<Issue>::new();
+  for (i, region) in regions.iter().enumerate() {
+    if region.end > file_size {
+      issues.push(Issue::PastEof(i));
+    }
+    for (j, other) in regions.iter().enumerate().skip(i + 1) {
+      if other.start >= region.end {
+        break;
+      }
+      if region.size() > 0 && other.size() > 0 {
+        issues.push(Issue::Overlap(i, j));
+      }
+    }
+  }
+  let mut covered_until = 0_u64;
+  for region in regions {
+    let start = region.start.min(file_size);
+    // A single padding byte keeping the next region word-aligned is not a gap.
+    let is_padding = start == covered_until + 1 && start % 2 == 0;
+    if start > covered_until && !is_padding {
+      issues.push(Issue::Gap {
+        start: covered_until,
+        end: start,
+      });
+    }
+    covered_until = covered_until.max(region.end.min(file_size));
+  }
+  if covered_until < file_size {
+    issues.push(Issue::Gap {
+      start: covered_until,
+      end: file_size,
+    });
+  }
+  issues
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use crate::tiff::builder::{ascii, long, pointer, TiffBuilder};
+  use crate::tiff::{DataType, Parser};
+
+  fn region(start: u64, end: u64) -> Region {
+    Region {
+      start,
+      end,
+      kind: RegionKind::TagValue,
+      label: String::new(),
+    }
+  }
+
+  #[test]
+  fn test_no_issues() {
+    let layout = Layout::from_regions(vec![region(8, 19), region(0, 8), region(20, 30)], 30);
+    assert!(layout.issues.is_empty(), "{:?}", layout.issues);
+  }
+
+  #[test]
+  fn test_overlap_and_eof() {
+    let layout = Layout::from_regions(vec![region(0, 10), region(5, 20), region(20, 40)], 30);
+    assert_eq!(layout.issues, vec![Issue::Overlap(0, 1), Issue::PastEof(2)]);
+  }
+
+  #[test]
+  fn test_gaps() {
+    let layout = Layout::from_regions(vec![region(0, 8), region(16, 20)], 32);
+    assert_eq!(layout.issues, vec![
+      Issue::Gap { start: 8, end: 16 },
+      Issue::Gap { start: 20, end: 32 },
+    ]);
+  }
+
+  #[test]
+  fn test_maker_note_header() {
+    let mut builder = TiffBuilder::new();
+    let header = builder.blob(b"SONY DSC \0\0\0");
+    let maker_note = builder.ifd(vec![long(0x0102, &[2])], 0);
+    let exif = builder.ifd(vec![pointer(37500, DataType::Blob, 12 + 2 + 12 + 4, header)], 0);
+    let ifd0 = builder.ifd(vec![ascii(271, "SONY"), long(34665, &[exif])], 0);
+    let data = builder.build(ifd0);
+    let size = data.len() as u64;
+    let mut stream = ByteStream::from_bytes(data).unwrap();
+    let tiff = Parser::new(&mut stream).parse().unwrap();
+    let layout = Layout::new(&tiff, size);
+    let region = layout.regions.iter().find(|it| it.kind == RegionKind::MakerNote).unwrap();
+    assert_eq!((region.start, region.end), (header as u64, maker_note as u64));
+    assert_eq!(region.label, "ifd0/exif/MakerNote header");
+    assert!(layout.issues.is_empty(), "{:?}", layout.issues);
+  }
+}
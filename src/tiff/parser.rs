@@ -1,15 +1,22 @@
-use log::warn;
+use log::{debug, warn};
 use crate::tiff::Entry::YCbCrCoefficients;
 use super::*;
+use super::tags::{self, IfdKind};
+
+// Headers some Sony MakerNotes start with, before their IFD.
+const SONY_MAKER_NOTE_HEADERS: [&[u8; 12]; 3] = [b"SONY DSC \0\0\0", b"SONY CAM \0\0\0", b"SONY MOBILE\0"];
 
 pub struct Parser <'a> {
   stream: &'a mut ByteStream,
+  // Set by IFD0's Make; Sony's private IFDs are only parsed for Sony cameras.
+  sony: bool,
 }
 
 impl <'a> Parser <'a> {
   pub fn new(stream: &'a mut ByteStream) -> Self {
     Self{
       stream,
+      sony: false,
     }
   }
 
@@ -21,31 +28,42 @@ impl <'a> Parser <'a> {
     }
     let offset = self.stream.read_u32()?;
     self.stream.seek(offset as u64)?;
-    let directories = self.parse_image_file_directories()?;
+    let directories = self.parse_image_file_directories(IfdKind::Image)?;
     Ok(Tiff{
       directories,
     })
   }
 
-  fn parse_image_file_directories(&mut self) -> anyhow::Result<Vec<ImageFileDirectory>> {
+  fn parse_image_file_directories(&mut self, kind: IfdKind) -> anyhow::Result<Vec<ImageFileDirectory>> {
     let mut ifd:Vec<ImageFileDirectory> = Vec::new();
     let mut pos = self.stream.position()?;
     while pos != 0 {
+      if ifd.iter().any(|it| it.offset == pos) {
+        warn!("IFD at {} links back to itself", pos);
+        break;
+      }
       self.stream.seek(pos)?;
       let mut entries = Vec::<Entry>::new();
+      let mut headers = Vec::<EntryHeader>::new();
       let num_entries = self.stream.read_u16()?;
       for _ in 0..num_entries {
-        entries.push(self.parse_entry()?);
+        let (header, entry) = self.parse_entry(kind)?;
+        headers.push(header);
+        entries.push(entry);
       }
       ifd.push(ImageFileDirectory {
-        entries
+        kind,
+        offset: pos,
+        entries,
+        headers,
       });
       pos = self.stream.read_u32()? as u64;
     }
     Ok(ifd)
   }
 
-  fn parse_entry(&mut self) -> anyhow::Result<Entry> {
+  fn parse_entry(&mut self, kind: IfdKind) -> anyhow::Result<(EntryHeader, Entry)> {
+    let offset = self.stream.position()?;
     let tag = self.stream.read_u16()?;
     let mut ctx = {
       let ty = DataType::from(self.stream.read_u16()?);
@@ -55,17 +73,27 @@ impl <'a> Parser <'a> {
       let data = self.stream.read_u32()?;
       EntryContext {
         stream: &mut self.stream,
+        sony: self.sony,
         ty,
         count,
         data_offset,
         data,
       }
     };
+    let header = EntryHeader {
+      offset,
+      tag,
+      ty: ctx.ty,
+      count: ctx.count,
+      data: ctx.data,
+    };
     /* ************************************************************************
      * Analyze via tag
      * See p.17 for correspondence between tag name and value.
      *************************************************************************/
     let entry = match tag {
+      // Tag IDs of Sony IFDs collide with the ones below.
+      _ if matches!(kind, IfdKind::SonyMakerNote | IfdKind::SonySr2) => ctx.unhandled(kind, tag),
       254 => {
         // p.20
         ctx.check_type([DataType::U32])?;
@@ -221,11 +249,39 @@ impl <'a> Parser <'a> {
           blue_y: v[5].clone(),
         }
       }
+      322 => { // [TIFF] p.67
+        ctx.check_type([DataType::U16, DataType::U32])?;
+        Entry::TileWidth(ctx.data)
+      }
+      323 => { // [TIFF] p.67
+        ctx.check_type([DataType::U16, DataType::U32])?;
+        Entry::TileLength(ctx.data)
+      }
+      324 => { // [TIFF] p.68
+        ctx.check_type([DataType::U16, DataType::U32])?;
+        match ctx.ty {
+          DataType::U16 =>
+            Entry::TileOffsets(ctx.read_u16s()?.iter().map(|it| *it as u32).collect()),
+          DataType::U32 =>
+            Entry::TileOffsets(ctx.read_u32s()?),
+          _ => panic!("Unreachable!"),
+        }
+      }
+      325 => { // [TIFF] p.68
+        ctx.check_type([DataType::U16, DataType::U32])?;
+        match ctx.ty {
+          DataType::U16 =>
+            Entry::TileByteCounts(ctx.read_u16s()?.iter().map(|it| *it as u32).collect()),
+          DataType::U32 =>
+            Entry::TileByteCounts(ctx.read_u32s()?),
+          _ => panic!("Unreachable!"),
+        }
+      }
       330 => { // [TIFF/EP] p.21
         ctx.check_type([DataType::U32])?;
         if ctx.count == 1 {
           let r = ctx.fork(ctx.data, |parser| {
-            parser.parse_image_file_directories()
+            parser.parse_image_file_directories(IfdKind::Image)
           })?;
           Entry::SubIFDs(r)
         } else {
@@ -290,27 +346,57 @@ impl <'a> Parser <'a> {
       }
       34665 => { // https://www.awaresystems.be/imaging/tiff/tifftags/exififd.html
         ctx.check_type([DataType::U32])?;
-        Entry::ExifIFD(ctx.fork(ctx.data, |parser| parser.parse_image_file_directories())?)
+        Entry::ExifIFD(ctx.fork(ctx.data, |parser| parser.parse_image_file_directories(IfdKind::Exif))?)
+      }
+      34853 => { // [TIFF/EP] p.37
+        ctx.check_type([DataType::U32])?;
+        Entry::GpsIFD(ctx.fork(ctx.data, |parser| parser.parse_image_file_directories(IfdKind::Gps))?)
+      }
+      40965 => { // [Exif] 4.6.3
+        ctx.check_type([DataType::U32])?;
+        Entry::InteropIFD(ctx.fork(ctx.data, |parser| parser.parse_image_file_directories(IfdKind::Interop))?)
       }
       50706 => { // [DNG] p.22
         ctx.check_type([DataType::U8])?;
         Entry::DNGVersion(ctx.read_u8s()?)
       }
+      37500 if kind == IfdKind::Exif && ctx.sony && ctx.count > 12 => {
+        match ctx.read_sony_maker_note() {
+          Ok(dirs) => Entry::SonyMakerNote(dirs),
+          Err(err) => {
+            warn!("MakerNote is not a Sony IFD: {}", err);
+            ctx.unhandled(kind, tag)
+          }
+        }
+      }
       50740 => { // [DNG] p.39
         ctx.check_type([DataType::U8])?;
-        Entry::DNGPrivateData(ctx.read_binary()?)
-      }
-      _ => {
-        warn!("Unknown Tag: {}", tag);
-        Entry::Unknown(tag, ctx.ty, ctx.count, ctx.data)
+        let data = ctx.read_binary()?;
+        if !ctx.sony || data.len() != 4 {
+          Entry::DNGPrivateData(data)
+        } else {
+          // https://exiftool.org/TagNames/Sony.html#SR2Private
+          match ctx.fork(ctx.data, |parser| parser.parse_image_file_directories(IfdKind::SonySr2)) {
+            Ok(dirs) => Entry::SR2Private(dirs),
+            Err(err) => {
+              warn!("DNGPrivateData does not point to an SR2Private IFD: {}", err);
+              Entry::DNGPrivateData(data)
+            }
+          }
+        }
       }
+      _ => ctx.unhandled(kind, tag),
     };
-    Ok(entry)
+    if let Entry::Make(make) = &entry {
+      self.sony = make.trim().to_ascii_uppercase().starts_with("SONY");
+    }
+    Ok((header, entry))
   }
 }
 
 struct EntryContext<'s> {
   stream: &'s mut ByteStream,
+  sony: bool,
   ty: DataType,
   count: u32,
   data_offset: u64,
@@ -327,6 +413,24 @@ impl <'s> EntryContext<'s> {
     let msg = format!("Type Mismatch: {:?} not in {:?}", self.ty, types);
     Err(anyhow::Error::msg(msg))
   }
+  // Kept as is, with warnings when the registry expects something else.
+  fn unhandled(&self, kind: IfdKind, tag: u16) -> Entry {
+    match tags::lookup(kind, tag) {
+      Some(info) => {
+        debug!("Unhandled Tag: {} ({})", info.name, tag);
+        if !info.accepts(self.ty) {
+          warn!("{} ({}): unexpected type {:?}, expected {:?}", info.name, tag, self.ty, info.types);
+        }
+        if let tags::Count::Fixed(n) = info.count {
+          if n != self.count {
+            warn!("{} ({}): unexpected count {}, expected {}", info.name, tag, self.count, n);
+          }
+        }
+      }
+      None => warn!("Unknown Tag: {}", tag),
+    }
+    Entry::Unknown(tag, self.ty, self.count, self.data)
+  }
   fn read_ascii(&mut self) -> std::io::Result<String> {
     if self.count > 4 {
       self.stream.fetch_ascii(self.data as u64, self.count as usize)
@@ -365,13 +469,77 @@ impl <'s> EntryContext<'s> {
       self.stream.fetch_vec_u32(self.data_offset, self.count as usize)
     }
   }
+  // https://exiftool.org/TagNames/Sony.html
+  fn read_sony_maker_note(&mut self) -> anyhow::Result<Vec<ImageFileDirectory>> {
+    let head = self.stream.fetch_vec_u8(self.data as u64, 12)?;
+    let skip = if SONY_MAKER_NOTE_HEADERS.iter().any(|it| head == it[..]) { 12 } else { 0 };
+    self.fork(self.data.saturating_add(skip), |parser| parser.parse_image_file_directories(IfdKind::SonyMakerNote))
+  }
   fn fork<Fn, R>(&mut self, offset: u32, f: Fn) -> anyhow::Result<R>
     where Fn: FnOnce(&mut Parser) -> anyhow::Result<R> {
     let current = self.stream.position()?;
     self.stream.seek(offset as u64)?;
-    let mut parser = Parser::new(&mut self.stream);
+    let mut parser = Parser {
+      stream: self.stream,
+      sony: self.sony,
+    };
     let r = f(&mut parser);
     self.stream.seek(current)?;
     r
   }
 }
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use crate::tiff::builder::{ascii, entry, long, pointer, TiffBuilder};
+
+  fn parse(data: Vec<u8>) -> Tiff {
+    let mut stream = ByteStream::from_bytes(data).unwrap();
+    Parser::new(&mut stream).parse().unwrap()
+  }
+
+  #[test]
+  fn test_sony_ifds() {
+    let build = |make: &str| {
+      let mut builder = TiffBuilder::new();
+      let sr2_private = builder.ifd(vec![long(0x7200, &[100]), long(0x7201, &[8]), long(0x7221, &[42])], 0);
+      // Quality and WhiteBalance, which are BitsPerSample and SamplesPerPixel in standard IFDs.
+      let header = builder.blob(b"SONY DSC \0\0\0");
+      let maker_note = builder.ifd(vec![long(0x0102, &[2]), long(0x0115, &[0])], 0);
+      assert_eq!(maker_note, header + 12);
+      let exif = builder.ifd(vec![pointer(37500, DataType::Blob, 12 + 2 + 2 * 12 + 4, header)], 0);
+      let ifd0 = builder.ifd(vec![
+        ascii(271, make),
+        long(34665, &[exif]),
+        entry(50740, DataType::U8, 4, sr2_private.to_le_bytes().to_vec()),
+      ], 0);
+      parse(builder.build(ifd0))
+    };
+
+    let tiff = build("SONY");
+    let mut kinds = Vec::<(String, IfdKind)>::new();
+    tiff.walk_ifds(|path, dir| kinds.push((path.to_string(), dir.kind())));
+    assert_eq!(kinds, vec![
+      ("ifd0".to_string(), IfdKind::Image),
+      ("ifd0/exif".to_string(), IfdKind::Exif),
+      ("ifd0/exif/makernote".to_string(), IfdKind::SonyMakerNote),
+      ("ifd0/sr2private".to_string(), IfdKind::SonySr2),
+    ]);
+    let mut names = Vec::<&str>::new();
+    tiff.walk_ifds(|_, dir| {
+      names.extend(dir.headers().iter().filter_map(|it| tags::lookup(dir.kind(), it.tag)).map(|it| it.name))
+    });
+    assert_eq!(names, vec![
+      "Make", "ExifIFD", "DNGPrivateData", "MakerNote", "Quality", "WhiteBalance",
+      "SR2SubIFDOffset", "SR2SubIFDLength", "SR2SubIFDKey",
+    ]);
+
+    // Other makers keep them as data.
+    let tiff = build("Other");
+    let mut paths = Vec::<String>::new();
+    tiff.walk_ifds(|path, _| paths.push(path.to_string()));
+    assert_eq!(paths, vec!["ifd0", "ifd0/exif"]);
+    assert!(matches!(tiff.root_ifd().unwrap().entries()[2], Entry::DNGPrivateData(_)));
+  }
+}
@@ -0,0 +1,346 @@
+// Path queries over IFDs.
+//
+//   ifd0/subifd[0]/ImageWidth   ImageWidth of the first SubIFD of IFD0
+//   exif/ExposureTime           ExposureTime of any Exif IFD directly under a top-level IFD
+//   **/CFAPattern               CFAPattern wherever it is
+//   ifd1/0x0201                 Tags may also be given by decimal or hex ID.
+//
+// IFD segments are `ifdN`, `subifd[N]`, `exif[N]`, `gps[N]`, `interop[N]`, and for Sony cameras
+// `makernote[N]` and `sr2private[N]`; the index may be omitted to match all of them.
+// `*` matches exactly one IFD and `**` matches any number of them, including none.
+// A path that does not start with `ifd`, `*` or `**` is looked up under every top-level IFD.
+
+use std::fmt::{Display, Formatter};
+use crate::stream::ByteStream;
+use crate::tiff::{DataType, EntryHeader, SignedRational, Tiff, UnsignedRational};
+use crate::tiff::tags::{self, IfdKind};
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+enum Segment {
+  Ifd {
+    name: String,
+    index: Option<usize>,
+  },
+  AnyOne,
+  AnyMany,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+enum TagSelector {
+  Id(u16),
+  // Resolved against the registry for each IFD kind, since names are only unique within one.
+  Name(String),
+  All,
+}
+
+impl TagSelector {
+  fn matches(&self, kind: IfdKind, tag: u16) -> bool {
+    match self {
+      TagSelector::Id(id) => *id == tag,
+      TagSelector::Name(name) => tags::lookup(kind, tag).is_some_and(|it| it.name.eq_ignore_ascii_case(name)),
+      TagSelector::All => true,
+    }
+  }
+}
+
+#[derive(Clone, Debug)]
+pub struct Query {
+  segments: Vec<Segment>,
+  tag: TagSelector,
+}
+
+#[derive(Clone, Debug)]
+pub struct Match<'a> {
+  // Path of the IFD holding the entry, e.g. `ifd0/exif`.
+  pub ifd_path: String,
+  pub ifd_kind: IfdKind,
+  pub header: &'a EntryHeader,
+}
+
+impl <'a> Match<'a> {
+  pub fn tag_name(&self) -> String {
+    match tags::lookup(self.ifd_kind, self.header.tag) {
+      Some(info) => info.name.to_string(),
+      None => format!("{}", self.header.tag),
+    }
+  }
+  pub fn value(&self, stream: &mut ByteStream) -> std::io::Result<Value> {
+    Value::read(stream, self.header)
+  }
+}
+
+impl Query {
+  pub fn parse(expr: &str) -> anyhow::Result<Self> {
+    let mut parts: Vec<&str> = expr.trim_matches('/').split('/').collect();
+    let tag = match parts.pop() {
+      None | Some("") => return Err(anyhow::Error::msg("Empty query")),
+      Some(tag) => parse_tag(tag)?,
+    };
+    let mut segments = parts.iter().map(|it| parse_segment(it)).collect::<anyhow::Result<Vec<_>>>()?;
+    let is_rooted = match segments.first() {
+      Some(Segment::Ifd { name, .. }) => name == "ifd",
+      Some(_) => true,
+      None => false,
+    };
+    if !is_rooted {
+      segments.insert(0, Segment::AnyOne);
+    }
+    Ok(Self {
+      segments,
+      tag,
+    })
+  }
+
+  pub fn select<'a>(&self, tiff: &'a Tiff) -> Vec<Match<'a>> {
+    let mut matches = Vec::<Match<'a>>::new();
+    tiff.walk_ifds(|path, dir| {
+      let components: Vec<(String, usize)> = path.split('/').map(parse_component).collect();
+      if !match_segments(&self.segments, &components) {
+        return;
+      }
+      for header in dir.headers() {
+        if self.tag.matches(dir.kind(), header.tag) {
+          matches.push(Match {
+            ifd_path: path.to_string(),
+            ifd_kind: dir.kind(),
+            header,
+          });
+        }
+      }
+    });
+    matches
+  }
+}
+
+fn parse_tag(tag: &str) -> anyhow::Result<TagSelector> {
+  if tag == "*" {
+    return Ok(TagSelector::All);
+  }
+  let id = if let Some(hex) = tag.strip_prefix("0x").or_else(|| tag.strip_prefix("0X")) {
+    u16::from_str_radix(hex, 16).ok()
+  } else if tag.chars().all(|c| c.is_ascii_digit()) {
+    tag.parse::<u16>().ok()
+  } else if tags::tag_id(tag).is_some() {
+    return Ok(TagSelector::Name(tag.to_string()));
+  } else {
+    None
+  };
+  id.map(TagSelector::Id).ok_or_else(|| anyhow::Error::msg(format!("Unknown tag: {}", tag)))
+}
+
+fn parse_segment(segment: &str) -> anyhow::Result<Segment> {
+  match segment {
+    "*" => return Ok(Segment::AnyOne),
+    "**" => return Ok(Segment::AnyMany),
+    _ => {}
+  }
+  let lower = segment.to_ascii_lowercase();
+  let (name, index) = if let Some(body) = lower.strip_suffix(']') {
+    let Some((name, index)) = body.split_once('[') else {
+      return Err(anyhow::Error::msg(format!("Invalid segment: {}", segment)));
+    };
+    (name.to_string(), Some(index.parse::<usize>()?))
+  } else if let Some(index) = lower.strip_prefix("ifd").filter(|it| !it.is_empty()) {
+    ("ifd".to_string(), Some(index.parse::<usize>()?))
+  } else {
+    (lower, None)
+  };
+  match name.as_str() {
+    "ifd" | "subifd" | "exif" | "gps" | "interop" | "makernote" | "sr2private" => Ok(Segment::Ifd { name, index }),
+    _ => Err(anyhow::Error::msg(format!("Unknown IFD: {}", segment))),
+  }
+}
+
+// Components of paths made by `Tiff::walk_ifds`. `exif` is the same as `exif[0]`.
+fn parse_component(component: &str) -> (String, usize) {
+  if let Some(index) = component.strip_prefix("ifd").and_then(|it| it.parse::<usize>().ok()) {
+    return ("ifd".to_string(), index);
+  }
+  if let Some((name, index)) = component.strip_suffix(']').and_then(|it| it.split_once('[')) {
+    return (name.to_string(), index.parse::<usize>().unwrap_or(0));
+  }
+  (component.to_string(), 0)
+}
+
+fn match_segments(segments: &[Segment], components: &[(String, usize)]) -> bool {
+  match (segments.first(), components.first()) {
+    (None, None) => true,
+    (Some(Segment::AnyMany), _) =>
+      match_segments(&segments[1..], components) ||
+        (!components.is_empty() && match_segments(segments, &components[1..])),
+    (Some(Segment::AnyOne), Some(_)) => match_segments(&segments[1..], &components[1..]),
+    (Some(Segment::Ifd { name, index }), Some((c_name, c_index))) =>
+      name == c_name &&
+        index.is_none_or(|it| it == *c_index) &&
+        match_segments(&segments[1..], &components[1..]),
+    _ => false,
+  }
+}
+
+#[derive(Clone, Debug)]
+pub enum Value {
+  U8(Vec<u8>),
+  Ascii(String),
+  U16(Vec<u16>),
+  U32(Vec<u32>),
+  Rational(Vec<UnsignedRational>),
+  S8(Vec<i8>),
+  Blob(Vec<u8>),
+  S16(Vec<i16>),
+  S32(Vec<i32>),
+  SRational(Vec<SignedRational>),
+  F32(Vec<f32>),
+  F64(Vec<f64>),
+}
+
+impl Value {
+  pub fn read(stream: &mut ByteStream, header: &EntryHeader) -> std::io::Result<Self> {
+    let offset = header.value_offset();
+    // The count comes from the file; check it before allocating for it.
+    let size = stream.size()?;
+    let end = (header.count as u64).checked_mul(header.ty.size() as u64).and_then(|it| it.checked_add(offset));
+    if end.is_none_or(|it| it > size) {
+      return Err(std::io::Error::new(std::io::ErrorKind::InvalidData, format!(
+        "Tag {}: {} values of {:?} at {} are out of the {} bytes file", header.tag, header.count, header.ty, offset, size)));
+    }
+    let n = header.count as usize;
+    let value = match header.ty {
+      DataType::U8 => Value::U8(stream.fetch_vec_u8(offset, n)?),
+      DataType::Ascii => {
+        let bytes = stream.fetch_vec_u8(offset, n)?;
+        let end = bytes.iter().position(|it| *it == 0).unwrap_or(bytes.len());
+        Value::Ascii(String::from_utf8_lossy(&bytes[..end]).to_string())
+      }
+      DataType::U16 => Value::U16(stream.fetch_vec_u16(offset, n)?),
+      DataType::U32 => Value::U32(stream.fetch_vec_u32(offset, n)?),
+      DataType::Rational => Value::Rational(stream.fetch_unsigned_rationals(offset, n)?),
+      DataType::S8 => Value::S8(stream.fetch_vec_i8(offset, n)?),
+      DataType::Blob | DataType::Unknown(_) => Value::Blob(stream.fetch_vec_u8(offset, n)?),
+      DataType::S16 => Value::S16(stream.fetch_vec_i16(offset, n)?),
+      DataType::S32 => Value::S32(stream.fetch_vec_i32(offset, n)?),
+      DataType::SRational => Value::SRational(stream.fetch_signed_rationals(offset, n)?),
+      DataType::F32 => Value::F32(stream.fetch_vec_f32(offset, n)?),
+      DataType::F64 => Value::F64(stream.fetch_vec_f64(offset, n)?),
+    };
+    Ok(value)
+  }
+}
+
+const MAX_VALUES: usize = 32;
+
+impl Value {
+  // Integer values widened to u64. None for non-integer types.
+  pub fn to_u64s(&self) -> Option<Vec<u64>> {
+    match self {
+      Value::U8(vs) | Value::Blob(vs) => Some(vs.iter().map(|it| *it as u64).collect()),
+      Value::U16(vs) => Some(vs.iter().map(|it| *it as u64).collect()),
+      Value::U32(vs) => Some(vs.iter().map(|it| *it as u64).collect()),
+      _ => None,
+    }
+  }
+
+  // Every numeric type, rationals divided out.
+  pub fn to_f64s(&self) -> Option<Vec<f64>> {
+    match self {
+      Value::U8(vs) | Value::Blob(vs) => Some(vs.iter().map(|it| *it as f64).collect()),
+      Value::U16(vs) => Some(vs.iter().map(|it| *it as f64).collect()),
+      Value::U32(vs) => Some(vs.iter().map(|it| *it as f64).collect()),
+      Value::Rational(vs) => Some(vs.iter().map(|it| it.numerator as f64 / it.denominator as f64).collect()),
+      Value::S8(vs) => Some(vs.iter().map(|it| *it as f64).collect()),
+      Value::S16(vs) => Some(vs.iter().map(|it| *it as f64).collect()),
+      Value::S32(vs) => Some(vs.iter().map(|it| *it as f64).collect()),
+      Value::SRational(vs) => Some(vs.iter().map(|it| it.numerator as f64 / it.denominator as f64).collect()),
+      Value::F32(vs) => Some(vs.iter().map(|it| *it as f64).collect()),
+      Value::F64(vs) => Some(vs.clone()),
+      Value::Ascii(_) => None,
+    }
+  }
+}
+
+fn write_list<T>(f: &mut Formatter<'_>, vs: &[T], g: impl Fn(&mut Formatter<'_>, &T) -> std::fmt::Result) -> std::fmt::Result {
+  for (i, v) in vs.iter().take(MAX_VALUES).enumerate() {
+    if i > 0 {
+      write!(f, ", ")?;
+    }
+    g(f, v)?;
+  }
+  if vs.len() > MAX_VALUES {
+    write!(f, ", ... ({} values)", vs.len())?;
+  }
+  Ok(())
+}
+
+impl Display for Value {
+  fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    match self {
+      Value::U8(vs) => write_list(f, vs, |f, v| write!(f, "{}", v)),
+      Value::Ascii(s) => write!(f, "{:?}", s),
+      Value::U16(vs) => write_list(f, vs, |f, v| write!(f, "{}", v)),
+      Value::U32(vs) => write_list(f, vs, |f, v| write!(f, "{}", v)),
+      Value::Rational(vs) => write_list(f, vs, |f, v| write!(f, "{}/{}", v.numerator, v.denominator)),
+      Value::S8(vs) => write_list(f, vs, |f, v| write!(f, "{}", v)),
+      Value::Blob(vs) => {
+        for v in vs.iter().take(MAX_VALUES) {
+          write!(f, "{:02x}", v)?;
+        }
+        if vs.len() > MAX_VALUES {
+          write!(f, "... ({} bytes)", vs.len())?;
+        }
+        Ok(())
+      }
+      Value::S16(vs) => write_list(f, vs, |f, v| write!(f, "{}", v)),
+      Value::S32(vs) => write_list(f, vs, |f, v| write!(f, "{}", v)),
+      Value::SRational(vs) => write_list(f, vs, |f, v| write!(f, "{}/{}", v.numerator, v.denominator)),
+      Value::F32(vs) => write_list(f, vs, |f, v| write!(f, "{}", v)),
+      Value::F64(vs) => write_list(f, vs, |f, v| write!(f, "{}", v)),
+    }
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  fn matches(query: &str, path: &str) -> bool {
+    let query = Query::parse(query).expect("Failed to parse");
+    let components: Vec<(String, usize)> = path.split('/').map(parse_component).collect();
+    match_segments(&query.segments, &components)
+  }
+
+  #[test]
+  fn test_parse_tag() {
+    assert!(Query::parse("ifd0/ImageWidth").unwrap().tag.matches(IfdKind::Image, 256));
+    assert!(Query::parse("ifd0/imagewidth").unwrap().tag.matches(IfdKind::Image, 256));
+    assert!(Query::parse("gps/GPSLatitudeRef").unwrap().tag.matches(IfdKind::Gps, 1));
+    assert!(!Query::parse("interop/GPSLatitudeRef").unwrap().tag.matches(IfdKind::Interop, 1));
+    assert_eq!(Query::parse("ifd0/256").unwrap().tag, TagSelector::Id(256));
+    assert_eq!(Query::parse("ifd0/0x100").unwrap().tag, TagSelector::Id(256));
+    assert!(Query::parse("ifd0/NoSuchTag").is_err());
+  }
+
+  #[test]
+  fn test_match() {
+    assert!(matches("ifd0/subifd[0]/ImageWidth", "ifd0/subifd[0]"));
+    assert!(!matches("ifd0/subifd[0]/ImageWidth", "ifd0/subifd[1]"));
+    assert!(matches("ifd0/subifd/ImageWidth", "ifd0/subifd[1]"));
+    assert!(matches("exif/ExposureTime", "ifd0/exif"));
+    assert!(!matches("exif/ExposureTime", "ifd0/subifd[0]/exif"));
+    assert!(matches("**/CFAPattern", "ifd0"));
+    assert!(matches("**/CFAPattern", "ifd0/subifd[0]/exif"));
+    assert!(matches("ImageWidth", "ifd1"));
+    assert!(!matches("ImageWidth", "ifd1/exif"));
+  }
+
+  #[test]
+  fn test_read_bounds() {
+    let mut stream = ByteStream::from_bytes(b"II\x2a\x00\x08\x00\x00\x00abcdefgh").unwrap();
+    let mut header = EntryHeader { offset: 0, tag: 270, ty: DataType::Ascii, count: 8, data: 8 };
+    assert!(matches!(Value::read(&mut stream, &header).unwrap(), Value::Ascii(it) if it == "abcdefgh"));
+    // Past the end of the file, and far past it.
+    header.count = 9;
+    assert!(Value::read(&mut stream, &header).is_err());
+    header.ty = DataType::F64;
+    header.count = u32::MAX;
+    assert!(Value::read(&mut stream, &header).is_err());
+  }
+}
@@ -0,0 +1,643 @@
+/*
+Registry of known tags.
+
+References:
+- [TIFF] TIFF Revision 6.0 (doc/TIFF6.pdf), Section 8 and Appendix A
+- [TIFF/EP] ISO 12234-2 (doc/N4378.pdf), Section 5
+- [Exif] CIPA DC-008 Exif 2.32, Section 4.6
+- [DNG] DNG Specification 1.7.0.0 (doc/DNG_Spec_1_7_0_0.pdf), Chapter 4
+- Sony: https://exiftool.org/TagNames/Sony.html
+*/
+
+use crate::tiff::DataType;
+
+// Which kind of IFD a tag belongs to.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum IfdKind {
+  // IFD0, SubIFDs and further IFDs in the chain.
+  Image,
+  Exif,
+  Gps,
+  Interop,
+  // Sony's MakerNote in the Exif IFD.
+  SonyMakerNote,
+  // Sony SR2Private, pointed by DNGPrivateData, and the SR2SubIFD it points to.
+  SonySr2,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Count {
+  Any,
+  Fixed(u32),
+  // One value per sample, i.e. SamplesPerPixel.
+  PerSample,
+}
+
+#[derive(Debug)]
+pub struct TagInfo {
+  pub id: u16,
+  pub name: &'static str,
+  pub ifd: IfdKind,
+  // Empty when any type is accepted.
+  pub types: &'static [DataType],
+  pub count: Count,
+  pub description: &'static str,
+}
+
+impl TagInfo {
+  pub fn accepts(&self, ty: DataType) -> bool {
+    self.types.is_empty() || self.types.contains(&ty)
+  }
+}
+
+const fn t(
+  id: u16,
+  name: &'static str,
+  ifd: IfdKind,
+  types: &'static [DataType],
+  count: Count,
+  description: &'static str,
+) -> TagInfo {
+  TagInfo {
+    id,
+    name,
+    ifd,
+    types,
+    count,
+    description,
+  }
+}
+
+const ANY: &[DataType] = &[];
+const BYTE: &[DataType] = &[DataType::U8];
+const ASCII: &[DataType] = &[DataType::Ascii];
+const SHORT: &[DataType] = &[DataType::U16];
+const LONG: &[DataType] = &[DataType::U32];
+const SHORT_LONG: &[DataType] = &[DataType::U16, DataType::U32];
+const SHORT_LONG_RATIONAL: &[DataType] = &[DataType::U16, DataType::U32, DataType::Rational];
+const SHORT_RATIONAL: &[DataType] = &[DataType::U16, DataType::Rational];
+const RATIONAL: &[DataType] = &[DataType::Rational];
+const SRATIONAL: &[DataType] = &[DataType::SRational];
+const RATIONAL_SRATIONAL: &[DataType] = &[DataType::Rational, DataType::SRational];
+const SSHORT: &[DataType] = &[DataType::S16];
+const UNDEFINED: &[DataType] = &[DataType::Blob];
+const BYTE_UNDEFINED: &[DataType] = &[DataType::U8, DataType::Blob];
+const ASCII_BYTE: &[DataType] = &[DataType::Ascii, DataType::U8];
+const FLOAT: &[DataType] = &[DataType::F32];
+const DOUBLE: &[DataType] = &[DataType::F64];
+
+use IfdKind::{Image, Exif, Gps, Interop, SonyMakerNote, SonySr2};
+use Count::{Any, Fixed, PerSample};
+
+pub static TAGS: &[TagInfo] = &[
+  /* [TIFF] */
+  t(254, "NewSubFileType", Image, LONG, Fixed(1), "Kind of data in this subfile"),
+  t(255, "SubfileType", Image, SHORT, Fixed(1), "Kind of data in this subfile (deprecated)"),
+  t(256, "ImageWidth", Image, SHORT_LONG, Fixed(1), "Number of columns in the image"),
+  t(257, "ImageLength", Image, SHORT_LONG, Fixed(1), "Number of rows in the image"),
+  t(258, "BitsPerSample", Image, SHORT, PerSample, "Number of bits per component"),
+  t(259, "Compression", Image, SHORT, Fixed(1), "Compression scheme used on the image data"),
+  t(262, "PhotometricInterpretation", Image, SHORT, Fixed(1), "Color space of the image data"),
+  t(263, "Threshholding", Image, SHORT, Fixed(1), "Technique used to convert from gray to black and white"),
+  t(264, "CellWidth", Image, SHORT, Fixed(1), "Width of the dithering or halftoning matrix"),
+  t(265, "CellLength", Image, SHORT, Fixed(1), "Length of the dithering or halftoning matrix"),
+  t(266, "FillOrder", Image, SHORT, Fixed(1), "Logical order of bits within a byte"),
+  t(269, "DocumentName", Image, ASCII, Any, "Name of the document from which this image was scanned"),
+  t(270, "ImageDescription", Image, ASCII, Any, "Description of the image subject"),
+  t(271, "Make", Image, ASCII, Any, "Manufacturer of the scanner, camera or other input device"),
+  t(272, "Model", Image, ASCII, Any, "Model name or number of the input device"),
+  t(273, "StripOffsets", Image, SHORT_LONG, Any, "Byte offset of each strip"),
+  t(274, "Orientation", Image, SHORT, Fixed(1), "Orientation of the image with respect to rows and columns"),
+  t(277, "SamplesPerPixel", Image, SHORT, Fixed(1), "Number of components per pixel"),
+  t(278, "RowsPerStrip", Image, SHORT_LONG, Fixed(1), "Number of rows per strip"),
+  t(279, "StripByteCounts", Image, SHORT_LONG, Any, "Number of bytes in each strip after compression"),
+  t(280, "MinSampleValue", Image, SHORT, PerSample, "Minimum component value used"),
+  t(281, "MaxSampleValue", Image, SHORT, PerSample, "Maximum component value used"),
+  t(282, "XResolution", Image, RATIONAL, Fixed(1), "Pixels per ResolutionUnit in the ImageWidth direction"),
+  t(283, "YResolution", Image, RATIONAL, Fixed(1), "Pixels per ResolutionUnit in the ImageLength direction"),
+  t(284, "PlanarConfiguration", Image, SHORT, Fixed(1), "How the components of each pixel are stored"),
+  t(285, "PageName", Image, ASCII, Any, "Name of the page from which this image was scanned"),
+  t(286, "XPosition", Image, RATIONAL, Fixed(1), "X position of the image"),
+  t(287, "YPosition", Image, RATIONAL, Fixed(1), "Y position of the image"),
+  t(288, "FreeOffsets", Image, LONG, Any, "Byte offsets of unused strings of bytes"),
+  t(289, "FreeByteCounts", Image, LONG, Any, "Byte counts of unused strings of bytes"),
+  t(290, "GrayResponseUnit", Image, SHORT, Fixed(1), "Precision of the GrayResponseCurve"),
+  t(291, "GrayResponseCurve", Image, SHORT, Any, "Optical density of each possible pixel value"),
+  t(292, "T4Options", Image, LONG, Fixed(1), "Options for CCITT Group 3 compression"),
+  t(293, "T6Options", Image, LONG, Fixed(1), "Options for CCITT Group 4 compression"),
+  t(296, "ResolutionUnit", Image, SHORT, Fixed(1), "Unit of XResolution and YResolution"),
+  t(297, "PageNumber", Image, SHORT, Fixed(2), "Page number of the scanned page"),
+  t(301, "TransferFunction", Image, SHORT, Any, "Transfer function for the image"),
+  t(305, "Software", Image, ASCII, Any, "Name and version of the software that created the image"),
+  t(306, "DateTime", Image, ASCII, Fixed(20), "Date and time of image creation"),
+  t(315, "Artist", Image, ASCII, Any, "Person who created the image"),
+  t(316, "HostComputer", Image, ASCII, Any, "Computer and/or operating system in use at the time of image creation"),
+  t(317, "Predictor", Image, SHORT, Fixed(1), "Prediction scheme used before compression"),
+  t(318, "WhitePoint", Image, RATIONAL, Fixed(2), "Chromaticity of the white point of the image"),
+  t(319, "PrimaryChromaticities", Image, RATIONAL, Fixed(6), "Chromaticities of the primaries of the image"),
+  t(320, "ColorMap", Image, SHORT, Any, "Color map for palette color images"),
+  t(321, "HalftoneHints", Image, SHORT, Fixed(2), "Range of gray levels to retain tonal detail"),
+  t(322, "TileWidth", Image, SHORT_LONG, Fixed(1), "Tile width in pixels"),
+  t(323, "TileLength", Image, SHORT_LONG, Fixed(1), "Tile length in pixels"),
+  t(324, "TileOffsets", Image, SHORT_LONG, Any, "Byte offset of each tile"),
+  t(325, "TileByteCounts", Image, SHORT_LONG, Any, "Number of bytes in each tile after compression"),
+  t(330, "SubIFDs", Image, LONG, Any, "Offsets to child IFDs"),
+  t(332, "InkSet", Image, SHORT, Fixed(1), "Set of inks used in a separated image"),
+  t(333, "InkNames", Image, ASCII, Any, "Names of each ink used in a separated image"),
+  t(334, "NumberOfInks", Image, SHORT, Fixed(1), "Number of inks"),
+  t(336, "DotRange", Image, ANY, Any, "Component values corresponding to 0% and 100% dots"),
+  t(337, "TargetPrinter", Image, ASCII, Any, "Intended printing environment"),
+  t(338, "ExtraSamples", Image, SHORT, Any, "Description of extra components"),
+  t(339, "SampleFormat", Image, SHORT, PerSample, "How to interpret each data sample in a pixel"),
+  t(340, "SMinSampleValue", Image, ANY, PerSample, "Minimum sample value"),
+  t(341, "SMaxSampleValue", Image, ANY, PerSample, "Maximum sample value"),
+  t(342, "TransferRange", Image, SHORT, Fixed(6), "Expands the range of the TransferFunction"),
+  t(347, "JPEGTables", Image, UNDEFINED, Any, "JPEG quantization and Huffman tables"),
+  t(512, "JPEGProc", Image, SHORT, Fixed(1), "Old-style JPEG compression process"),
+  t(513, "JPEGInterchangeFormat", Image, LONG, Fixed(1), "Offset to the JPEG SOI marker"),
+  t(514, "JPEGInterchangeFormatLength", Image, LONG, Fixed(1), "Number of bytes of JPEG compressed data"),
+  t(515, "JPEGRestartInterval", Image, SHORT, Fixed(1), "Length of the restart interval"),
+  t(517, "JPEGLosslessPredictors", Image, SHORT, PerSample, "Lossless predictor-selection values"),
+  t(518, "JPEGPointTransforms", Image, SHORT, PerSample, "Point transform values"),
+  t(519, "JPEGQTables", Image, LONG, PerSample, "Offsets to the quantization tables"),
+  t(520, "JPEGDCTables", Image, LONG, PerSample, "Offsets to the DC Huffman tables"),
+  t(521, "JPEGACTables", Image, LONG, PerSample, "Offsets to the AC Huffman tables"),
+  t(529, "YCbCrCoefficients", Image, RATIONAL, Fixed(3), "Transformation from RGB to YCbCr"),
+  t(530, "YCbCrSubSampling", Image, SHORT, Fixed(2), "Subsampling factors for the chrominance components"),
+  t(531, "YCbCrPositioning", Image, SHORT, Fixed(1), "Positioning of subsampled chrominance components"),
+  t(532, "ReferenceBlackWhite", Image, RATIONAL, Fixed(6), "Reference black and white point values"),
+  t(700, "XMP", Image, BYTE_UNDEFINED, Any, "XMP metadata packet"),
+  t(33432, "Copyright", Image, ASCII, Any, "Copyright notice"),
+  t(33723, "IPTC", Image, ANY, Any, "IPTC-NAA metadata record"),
+  t(34377, "Photoshop", Image, BYTE_UNDEFINED, Any, "Photoshop image resource blocks"),
+  t(34675, "InterColorProfile", Image, UNDEFINED, Any, "Embedded ICC profile"),
+  t(37724, "ImageSourceData", Image, UNDEFINED, Any, "Photoshop layer and mask data"),
+  t(50341, "PrintIM", Image, UNDEFINED, Any, "Epson print image matching data"),
+  /* [TIFF/EP] */
+  t(33421, "CFARepeatPatternDim", Image, SHORT, Fixed(2), "Number of rows and columns of the repeating CFA pattern"),
+  t(33422, "CFAPattern", Image, BYTE, Any, "Color filter array geometric pattern"),
+  t(33423, "BatteryLevel", Image, ANY, Any, "Battery level at the time of capture"),
+  t(34853, "GPSInfo", Image, LONG, Fixed(1), "Offset to the GPS IFD"),
+  t(34857, "Interlace", Image, SHORT, Fixed(1), "Field number of a multifield image"),
+  t(34858, "TimeZoneOffset", Image, SSHORT, Any, "Time zone offset from GMT in hours"),
+  t(34859, "SelfTimerMode", Image, SHORT, Fixed(1), "Self timer delay in seconds"),
+  t(37393, "ImageNumber", Image, SHORT_LONG, Fixed(1), "Number assigned to the image"),
+  t(37394, "SecurityClassification", Image, ASCII, Any, "Security classification of the image"),
+  t(37395, "ImageHistory", Image, ASCII, Any, "Record of modifications to the original image"),
+  t(37398, "TIFFEPStandardID", Image, BYTE, Fixed(4), "TIFF/EP standard version"),
+  // Also Exif tags, which TIFF/EP records in IFD0. Some allow a second value for a range.
+  t(33434, "ExposureTime", Image, RATIONAL, Fixed(1), "Exposure time in seconds"),
+  t(33437, "FNumber", Image, RATIONAL, Fixed(1), "F number"),
+  t(34850, "ExposureProgram", Image, SHORT, Fixed(1), "Program used to set exposure"),
+  t(34852, "SpectralSensitivity", Image, ASCII, Any, "Spectral sensitivity of each channel"),
+  t(34855, "ISOSpeedRatings", Image, SHORT, Any, "Sensitivity"),
+  t(34856, "OECF", Image, UNDEFINED, Any, "Opto-electronic conversion function"),
+  t(36867, "DateTimeOriginal", Image, ASCII, Fixed(20), "Date and time when the original image was generated"),
+  t(37122, "CompressedBitsPerPixel", Image, RATIONAL, Fixed(1), "Image compression mode"),
+  t(37377, "ShutterSpeedValue", Image, SRATIONAL, Fixed(1), "Shutter speed in APEX"),
+  t(37378, "ApertureValue", Image, RATIONAL, Fixed(1), "Lens aperture in APEX"),
+  t(37379, "BrightnessValue", Image, SRATIONAL, Any, "Brightness in APEX"),
+  t(37380, "ExposureBiasValue", Image, SRATIONAL, Fixed(1), "Exposure bias in APEX"),
+  t(37381, "MaxApertureValue", Image, RATIONAL, Fixed(1), "Smallest F number of the lens in APEX"),
+  t(37382, "SubjectDistance", Image, RATIONAL_SRATIONAL, Any, "Distance to the subject in meters"),
+  t(37383, "MeteringMode", Image, SHORT, Fixed(1), "Metering mode"),
+  t(37384, "LightSource", Image, SHORT, Fixed(1), "Kind of light source"),
+  t(37385, "Flash", Image, SHORT, Fixed(1), "Status of flash when the image was shot"),
+  t(37386, "FocalLength", Image, RATIONAL, Any, "Actual focal length of the lens in mm"),
+  t(37396, "SubjectLocation", Image, SHORT, Any, "Location of the main subject"),
+  /* [Exif] */
+  t(33434, "ExposureTime", Exif, RATIONAL, Fixed(1), "Exposure time in seconds"),
+  t(33437, "FNumber", Exif, RATIONAL, Fixed(1), "F number"),
+  t(34665, "ExifIFD", Image, LONG, Fixed(1), "Offset to the Exif IFD"),
+  t(34850, "ExposureProgram", Exif, SHORT, Fixed(1), "Program used to set exposure"),
+  t(34852, "SpectralSensitivity", Exif, ASCII, Any, "Spectral sensitivity of each channel"),
+  t(34855, "ISOSpeedRatings", Exif, SHORT, Any, "Sensitivity (PhotographicSensitivity in Exif 2.3)"),
+  t(34856, "OECF", Exif, UNDEFINED, Any, "Opto-electronic conversion function"),
+  t(34864, "SensitivityType", Exif, SHORT, Fixed(1), "Which sensitivity parameter is recorded"),
+  t(34865, "StandardOutputSensitivity", Exif, LONG, Fixed(1), "Standard output sensitivity"),
+  t(34866, "RecommendedExposureIndex", Exif, LONG, Fixed(1), "Recommended exposure index"),
+  t(34867, "ISOSpeed", Exif, LONG, Fixed(1), "ISO speed"),
+  t(34868, "ISOSpeedLatitudeyyy", Exif, LONG, Fixed(1), "ISO speed latitude yyy"),
+  t(34869, "ISOSpeedLatitudezzz", Exif, LONG, Fixed(1), "ISO speed latitude zzz"),
+  t(36864, "ExifVersion", Exif, UNDEFINED, Fixed(4), "Exif version"),
+  t(36867, "DateTimeOriginal", Exif, ASCII, Fixed(20), "Date and time when the original image was generated"),
+  t(36868, "DateTimeDigitized", Exif, ASCII, Fixed(20), "Date and time when the image was stored as digital data"),
+  t(36880, "OffsetTime", Exif, ASCII, Fixed(7), "Time offset of DateTime"),
+  t(36881, "OffsetTimeOriginal", Exif, ASCII, Fixed(7), "Time offset of DateTimeOriginal"),
+  t(36882, "OffsetTimeDigitized", Exif, ASCII, Fixed(7), "Time offset of DateTimeDigitized"),
+  t(37121, "ComponentsConfiguration", Exif, UNDEFINED, Fixed(4), "Meaning of each component"),
+  t(37122, "CompressedBitsPerPixel", Exif, RATIONAL, Fixed(1), "Image compression mode"),
+  t(37377, "ShutterSpeedValue", Exif, SRATIONAL, Fixed(1), "Shutter speed in APEX"),
+  t(37378, "ApertureValue", Exif, RATIONAL, Fixed(1), "Lens aperture in APEX"),
+  t(37379, "BrightnessValue", Exif, SRATIONAL, Fixed(1), "Brightness in APEX"),
+  t(37380, "ExposureBiasValue", Exif, SRATIONAL, Fixed(1), "Exposure bias in APEX"),
+  t(37381, "MaxApertureValue", Exif, RATIONAL, Fixed(1), "Smallest F number of the lens in APEX"),
+  t(37382, "SubjectDistance", Exif, RATIONAL, Fixed(1), "Distance to the subject in meters"),
+  t(37383, "MeteringMode", Exif, SHORT, Fixed(1), "Metering mode"),
+  t(37384, "LightSource", Exif, SHORT, Fixed(1), "Kind of light source"),
+  t(37385, "Flash", Exif, SHORT, Fixed(1), "Status of flash when the image was shot"),
+  t(37386, "FocalLength", Exif, RATIONAL, Fixed(1), "Actual focal length of the lens in mm"),
+  t(37387, "FlashEnergy", Image, RATIONAL, Any, "Strobe energy in BCPS (TIFF/EP)"),
+  t(37388, "SpatialFrequencyResponse", Image, ANY, Any, "Spatial frequency table (TIFF/EP)"),
+  t(37389, "Noise", Image, UNDEFINED, Any, "Noise measurement values (TIFF/EP)"),
+  t(37390, "FocalPlaneXResolution", Image, RATIONAL, Fixed(1), "Pixels per unit in the focal plane X direction (TIFF/EP)"),
+  t(37391, "FocalPlaneYResolution", Image, RATIONAL, Fixed(1), "Pixels per unit in the focal plane Y direction (TIFF/EP)"),
+  t(37392, "FocalPlaneResolutionUnit", Image, SHORT, Fixed(1), "Unit of the focal plane resolution (TIFF/EP)"),
+  t(37396, "SubjectArea", Exif, SHORT, Any, "Location and area of the main subject"),
+  t(37397, "ExposureIndex", Image, RATIONAL, Any, "Exposure index (TIFF/EP)"),
+  t(37399, "SensingMethod", Image, SHORT, Fixed(1), "Image sensor type (TIFF/EP)"),
+  t(37500, "MakerNote", Exif, UNDEFINED, Any, "Manufacturer specific information"),
+  t(37510, "UserComment", Exif, UNDEFINED, Any, "User comments"),
+  t(37520, "SubSecTime", Exif, ASCII, Any, "Fractions of seconds of DateTime"),
+  t(37521, "SubSecTimeOriginal", Exif, ASCII, Any, "Fractions of seconds of DateTimeOriginal"),
+  t(37522, "SubSecTimeDigitized", Exif, ASCII, Any, "Fractions of seconds of DateTimeDigitized"),
+  t(37888, "Temperature", Exif, SRATIONAL, Fixed(1), "Ambient temperature in degrees Celsius"),
+  t(37889, "Humidity", Exif, RATIONAL, Fixed(1), "Ambient relative humidity in percent"),
+  t(37890, "Pressure", Exif, RATIONAL, Fixed(1), "Ambient air pressure in hPa"),
+  t(37891, "WaterDepth", Exif, SRATIONAL, Fixed(1), "Water depth in meters"),
+  t(37892, "Acceleration", Exif, RATIONAL, Fixed(1), "Acceleration in mGal"),
+  t(37893, "CameraElevationAngle", Exif, SRATIONAL, Fixed(1), "Elevation angle of the camera in degrees"),
+  t(40960, "FlashpixVersion", Exif, UNDEFINED, Fixed(4), "Supported Flashpix version"),
+  t(40961, "ColorSpace", Exif, SHORT, Fixed(1), "Color space information"),
+  t(40962, "PixelXDimension", Exif, SHORT_LONG, Fixed(1), "Valid image width"),
+  t(40963, "PixelYDimension", Exif, SHORT_LONG, Fixed(1), "Valid image height"),
+  t(40964, "RelatedSoundFile", Exif, ASCII, Fixed(13), "Name of an audio file related to the image"),
+  t(40965, "InteroperabilityIFD", Exif, LONG, Fixed(1), "Offset to the Interoperability IFD"),
+  t(41483, "FlashEnergy", Exif, RATIONAL, Fixed(1), "Strobe energy in BCPS"),
+  t(41484, "SpatialFrequencyResponse", Exif, UNDEFINED, Any, "Spatial frequency table"),
+  t(41486, "FocalPlaneXResolution", Exif, RATIONAL, Fixed(1), "Pixels per unit in the focal plane X direction"),
+  t(41487, "FocalPlaneYResolution", Exif, RATIONAL, Fixed(1), "Pixels per unit in the focal plane Y direction"),
+  t(41488, "FocalPlaneResolutionUnit", Exif, SHORT, Fixed(1), "Unit of the focal plane resolution"),
+  t(41492, "SubjectLocation", Exif, SHORT, Fixed(2), "Location of the main subject"),
+  t(41493, "ExposureIndex", Exif, RATIONAL, Fixed(1), "Exposure index"),
+  t(41495, "SensingMethod", Exif, SHORT, Fixed(1), "Image sensor type"),
+  t(41728, "FileSource", Exif, UNDEFINED, Fixed(1), "Image source"),
+  t(41729, "SceneType", Exif, UNDEFINED, Fixed(1), "Type of scene"),
+  t(41730, "CFAPattern", Exif, UNDEFINED, Any, "Color filter array geometric pattern, with its dimensions"),
+  t(41985, "CustomRendered", Exif, SHORT, Fixed(1), "Use of special processing on image data"),
+  t(41986, "ExposureMode", Exif, SHORT, Fixed(1), "Exposure mode"),
+  t(41987, "WhiteBalance", Exif, SHORT, Fixed(1), "White balance mode"),
+  t(41988, "DigitalZoomRatio", Exif, RATIONAL, Fixed(1), "Digital zoom ratio"),
+  t(41989, "FocalLengthIn35mmFilm", Exif, SHORT, Fixed(1), "Equivalent focal length for 35mm film"),
+  t(41990, "SceneCaptureType", Exif, SHORT, Fixed(1), "Type of scene that was shot"),
+  t(41991, "GainControl", Exif, SHORT, Fixed(1), "Degree of overall image gain adjustment"),
+  t(41992, "Contrast", Exif, SHORT, Fixed(1), "Contrast processing applied by the camera"),
+  t(41993, "Saturation", Exif, SHORT, Fixed(1), "Saturation processing applied by the camera"),
+  t(41994, "Sharpness", Exif, SHORT, Fixed(1), "Sharpness processing applied by the camera"),
+  t(41995, "DeviceSettingDescription", Exif, UNDEFINED, Any, "Picture-taking conditions of a particular camera model"),
+  t(41996, "SubjectDistanceRange", Exif, SHORT, Fixed(1), "Distance to the subject"),
+  t(42016, "ImageUniqueID", Exif, ASCII, Fixed(33), "Unique identifier of the image"),
+  t(42032, "CameraOwnerName", Exif, ASCII, Any, "Owner of the camera"),
+  t(42033, "BodySerialNumber", Exif, ASCII, Any, "Serial number of the camera body"),
+  t(42034, "LensSpecification", Exif, RATIONAL, Fixed(4), "Minimum/maximum focal length and F numbers of the lens"),
+  t(42035, "LensMake", Exif, ASCII, Any, "Lens manufacturer"),
+  t(42036, "LensModel", Exif, ASCII, Any, "Lens model name"),
+  t(42037, "LensSerialNumber", Exif, ASCII, Any, "Serial number of the lens"),
+  t(42080, "CompositeImage", Exif, SHORT, Fixed(1), "Whether the image is a composite image"),
+  t(42081, "SourceImageNumberOfCompositeImage", Exif, SHORT, Fixed(2), "Number of source images of the composite image"),
+  t(42082, "SourceExposureTimesOfCompositeImage", Exif, UNDEFINED, Any, "Exposure times of the source images"),
+  t(42240, "Gamma", Exif, RATIONAL, Fixed(1), "Transfer function gamma"),
+  /* GPS [Exif] 4.6.6 */
+  t(0, "GPSVersionID", Gps, BYTE, Fixed(4), "Version of the GPS IFD"),
+  t(1, "GPSLatitudeRef", Gps, ASCII, Fixed(2), "North or south latitude"),
+  t(2, "GPSLatitude", Gps, RATIONAL, Fixed(3), "Latitude in degrees, minutes and seconds"),
+  t(3, "GPSLongitudeRef", Gps, ASCII, Fixed(2), "East or west longitude"),
+  t(4, "GPSLongitude", Gps, RATIONAL, Fixed(3), "Longitude in degrees, minutes and seconds"),
+  t(5, "GPSAltitudeRef", Gps, BYTE, Fixed(1), "Altitude reference"),
+  t(6, "GPSAltitude", Gps, RATIONAL, Fixed(1), "Altitude in meters"),
+  t(7, "GPSTimeStamp", Gps, RATIONAL, Fixed(3), "Time as UTC"),
+  t(8, "GPSSatellites", Gps, ASCII, Any, "Satellites used for measurement"),
+  t(9, "GPSStatus", Gps, ASCII, Fixed(2), "Status of the GPS receiver"),
+  t(10, "GPSMeasureMode", Gps, ASCII, Fixed(2), "GPS measurement mode"),
+  t(11, "GPSDOP", Gps, RATIONAL, Fixed(1), "Measurement precision"),
+  t(12, "GPSSpeedRef", Gps, ASCII, Fixed(2), "Unit of GPSSpeed"),
+  t(13, "GPSSpeed", Gps, RATIONAL, Fixed(1), "Speed of the GPS receiver"),
+  t(14, "GPSTrackRef", Gps, ASCII, Fixed(2), "Reference for the direction of movement"),
+  t(15, "GPSTrack", Gps, RATIONAL, Fixed(1), "Direction of movement"),
+  t(16, "GPSImgDirectionRef", Gps, ASCII, Fixed(2), "Reference for the direction of the image"),
+  t(17, "GPSImgDirection", Gps, RATIONAL, Fixed(1), "Direction of the image"),
+  t(18, "GPSMapDatum", Gps, ASCII, Any, "Geodetic survey data used"),
+  t(19, "GPSDestLatitudeRef", Gps, ASCII, Fixed(2), "Reference for the latitude of the destination"),
+  t(20, "GPSDestLatitude", Gps, RATIONAL, Fixed(3), "Latitude of the destination"),
+  t(21, "GPSDestLongitudeRef", Gps, ASCII, Fixed(2), "Reference for the longitude of the destination"),
+  t(22, "GPSDestLongitude", Gps, RATIONAL, Fixed(3), "Longitude of the destination"),
+  t(23, "GPSDestBearingRef", Gps, ASCII, Fixed(2), "Reference for the bearing to the destination"),
+  t(24, "GPSDestBearing", Gps, RATIONAL, Fixed(1), "Bearing to the destination"),
+  t(25, "GPSDestDistanceRef", Gps, ASCII, Fixed(2), "Unit of GPSDestDistance"),
+  t(26, "GPSDestDistance", Gps, RATIONAL, Fixed(1), "Distance to the destination"),
+  t(27, "GPSProcessingMethod", Gps, UNDEFINED, Any, "Name of the method used for location finding"),
+  t(28, "GPSAreaInformation", Gps, UNDEFINED, Any, "Name of the GPS area"),
+  t(29, "GPSDateStamp", Gps, ASCII, Fixed(11), "Date as UTC"),
+  t(30, "GPSDifferential", Gps, SHORT, Fixed(1), "Whether differential correction is applied"),
+  t(31, "GPSHPositioningError", Gps, RATIONAL, Fixed(1), "Horizontal positioning error in meters"),
+  /* Interoperability [Exif] 4.6.7 */
+  t(1, "InteroperabilityIndex", Interop, ASCII, Fixed(4), "Interoperability rule"),
+  t(2, "InteroperabilityVersion", Interop, UNDEFINED, Fixed(4), "Interoperability version"),
+  t(4096, "RelatedImageFileFormat", Interop, ASCII, Any, "File format of the related image"),
+  t(4097, "RelatedImageWidth", Interop, SHORT_LONG, Fixed(1), "Width of the related image"),
+  t(4098, "RelatedImageLength", Interop, SHORT_LONG, Fixed(1), "Height of the related image"),
+  /* [DNG] */
+  t(50706, "DNGVersion", Image, BYTE, Fixed(4), "DNG specification version"),
+  t(50707, "DNGBackwardVersion", Image, BYTE, Fixed(4), "Oldest DNG version this file is compatible with"),
+  t(50708, "UniqueCameraModel", Image, ASCII, Any, "Unique, non-localized camera model name"),
+  t(50709, "LocalizedCameraModel", Image, ASCII_BYTE, Any, "Localized camera model name"),
+  t(50710, "CFAPlaneColor", Image, BYTE, Any, "Mapping between CFAPattern values and plane numbers"),
+  t(50711, "CFALayout", Image, SHORT, Fixed(1), "Spatial layout of the CFA"),
+  t(50712, "LinearizationTable", Image, SHORT, Any, "Lookup table mapping stored values to linear values"),
+  t(50713, "BlackLevelRepeatDim", Image, SHORT, Fixed(2), "Repeat pattern size of the BlackLevel tag"),
+  t(50714, "BlackLevel", Image, SHORT_LONG_RATIONAL, Any, "Zero light encoding level"),
+  t(50715, "BlackLevelDeltaH", Image, SRATIONAL, Any, "Per-column black level deltas"),
+  t(50716, "BlackLevelDeltaV", Image, SRATIONAL, Any, "Per-row black level deltas"),
+  t(50717, "WhiteLevel", Image, SHORT_LONG, Any, "Fully saturated encoding level"),
+  t(50718, "DefaultScale", Image, RATIONAL, Fixed(2), "Default scale factors for each direction"),
+  t(50719, "DefaultCropOrigin", Image, SHORT_LONG_RATIONAL, Fixed(2), "Origin of the final image area"),
+  t(50720, "DefaultCropSize", Image, SHORT_LONG_RATIONAL, Fixed(2), "Size of the final image area"),
+  t(50721, "ColorMatrix1", Image, SRATIONAL, Any, "XYZ to camera color space matrix for illuminant 1"),
+  t(50722, "ColorMatrix2", Image, SRATIONAL, Any, "XYZ to camera color space matrix for illuminant 2"),
+  t(50723, "CameraCalibration1", Image, SRATIONAL, Any, "Individual camera calibration for illuminant 1"),
+  t(50724, "CameraCalibration2", Image, SRATIONAL, Any, "Individual camera calibration for illuminant 2"),
+  t(50725, "ReductionMatrix1", Image, SRATIONAL, Any, "Dimensionality reduction matrix for illuminant 1"),
+  t(50726, "ReductionMatrix2", Image, SRATIONAL, Any, "Dimensionality reduction matrix for illuminant 2"),
+  t(50727, "AnalogBalance", Image, RATIONAL, Any, "Gain applied to the stored raw values"),
+  t(50728, "AsShotNeutral", Image, SHORT_RATIONAL, Any, "Selected white balance as camera neutral coordinates"),
+  t(50729, "AsShotWhiteXY", Image, RATIONAL, Fixed(2), "Selected white balance as x-y chromaticity"),
+  t(50730, "BaselineExposure", Image, SRATIONAL, Fixed(1), "Baseline exposure compensation in EV"),
+  t(50731, "BaselineNoise", Image, RATIONAL, Fixed(1), "Relative noise level of the camera model"),
+  t(50732, "BaselineSharpness", Image, RATIONAL, Fixed(1), "Relative amount of sharpening required"),
+  t(50733, "BayerGreenSplit", Image, LONG, Fixed(1), "Tracking difference between the two green channels"),
+  t(50734, "LinearResponseLimit", Image, RATIONAL, Fixed(1), "Fraction of the encoding range above which the response may be non-linear"),
+  t(50735, "CameraSerialNumber", Image, ASCII, Any, "Serial number of the camera"),
+  t(50736, "LensInfo", Image, RATIONAL, Fixed(4), "Lens focal length and F number range"),
+  t(50737, "ChromaBlurRadius", Image, RATIONAL, Fixed(1), "Chroma blur radius for demosaic"),
+  t(50738, "AntiAliasStrength", Image, RATIONAL, Fixed(1), "Relative strength of the anti-alias filter"),
+  t(50739, "ShadowScale", Image, RATIONAL, Fixed(1), "Used by Adobe Camera Raw"),
+  t(50740, "DNGPrivateData", Image, BYTE, Any, "Private data (SR2Private on Sony)"),
+  t(50741, "MakerNoteSafety", Image, SHORT, Fixed(1), "Whether the MakerNote is safe to preserve"),
+  t(50778, "CalibrationIlluminant1", Image, SHORT, Fixed(1), "Illuminant of the first set of color calibration tags"),
+  t(50779, "CalibrationIlluminant2", Image, SHORT, Fixed(1), "Illuminant of the second set of color calibration tags"),
+  t(50780, "BestQualityScale", Image, RATIONAL, Fixed(1), "Amount to scale the default scale for best quality"),
+  t(50781, "RawDataUniqueID", Image, BYTE, Fixed(16), "Unique identifier of the raw image data"),
+  t(50827, "OriginalRawFileName", Image, ASCII_BYTE, Any, "File name of the original raw file"),
+  t(50828, "OriginalRawFileData", Image, UNDEFINED, Any, "Contents of the original raw file"),
+  t(50829, "ActiveArea", Image, SHORT_LONG, Fixed(4), "Rectangle of the non-masked sensor area"),
+  t(50830, "MaskedAreas", Image, SHORT_LONG, Any, "Rectangles of fully masked sensor areas"),
+  t(50831, "AsShotICCProfile", Image, UNDEFINED, Any, "ICC profile for the as shot rendering"),
+  t(50832, "AsShotPreProfileMatrix", Image, SRATIONAL, Any, "Matrix applied before AsShotICCProfile"),
+  t(50833, "CurrentICCProfile", Image, UNDEFINED, Any, "ICC profile for the current rendering"),
+  t(50834, "CurrentPreProfileMatrix", Image, SRATIONAL, Any, "Matrix applied before CurrentICCProfile"),
+  t(50879, "ColorimetricReference", Image, SHORT, Fixed(1), "Colorimetric reference of the camera color space"),
+  t(50931, "CameraCalibrationSignature", Image, ASCII_BYTE, Any, "Identifies the camera calibration"),
+  t(50932, "ProfileCalibrationSignature", Image, ASCII_BYTE, Any, "Camera calibration this profile is for"),
+  t(50933, "ExtraCameraProfiles", Image, LONG, Any, "Offsets to extra camera profiles"),
+  t(50934, "AsShotProfileName", Image, ASCII_BYTE, Any, "Camera profile selected at capture"),
+  t(50935, "NoiseReductionApplied", Image, RATIONAL, Fixed(1), "Amount of noise reduction applied"),
+  t(50936, "ProfileName", Image, ASCII_BYTE, Any, "Name of the camera profile"),
+  t(50937, "ProfileHueSatMapDims", Image, LONG, Fixed(3), "Dimensions of the hue/saturation/value mapping tables"),
+  t(50938, "ProfileHueSatMapData1", Image, FLOAT, Any, "Hue/saturation/value mapping for illuminant 1"),
+  t(50939, "ProfileHueSatMapData2", Image, FLOAT, Any, "Hue/saturation/value mapping for illuminant 2"),
+  t(50940, "ProfileToneCurve", Image, FLOAT, Any, "Default tone curve"),
+  t(50941, "ProfileEmbedPolicy", Image, LONG, Fixed(1), "Usage rules for the camera profile"),
+  t(50942, "ProfileCopyright", Image, ASCII_BYTE, Any, "Copyright of the camera profile"),
+  t(50964, "ForwardMatrix1", Image, SRATIONAL, Any, "White balanced camera to XYZ D50 matrix for illuminant 1"),
+  t(50965, "ForwardMatrix2", Image, SRATIONAL, Any, "White balanced camera to XYZ D50 matrix for illuminant 2"),
+  t(50966, "PreviewApplicationName", Image, ASCII_BYTE, Any, "Application that created the preview"),
+  t(50967, "PreviewApplicationVersion", Image, ASCII_BYTE, Any, "Version of the application that created the preview"),
+  t(50968, "PreviewSettingsName", Image, ASCII_BYTE, Any, "Name of the conversion settings of the preview"),
+  t(50969, "PreviewSettingsDigest", Image, BYTE, Fixed(16), "Digest of the conversion settings of the preview"),
+  t(50970, "PreviewColorSpace", Image, LONG, Fixed(1), "Color space of the preview"),
+  t(50971, "PreviewDateTime", Image, ASCII, Any, "Date and time the preview was rendered"),
+  t(50972, "RawImageDigest", Image, BYTE_UNDEFINED, Fixed(16), "MD5 digest of the raw image data"),
+  t(50973, "OriginalRawFileDigest", Image, BYTE_UNDEFINED, Fixed(16), "MD5 digest of OriginalRawFileData"),
+  t(50974, "SubTileBlockSize", Image, SHORT_LONG, Fixed(2), "Size of sub-tile blocks"),
+  t(50975, "RowInterleaveFactor", Image, SHORT_LONG, Fixed(1), "Number of interleaved fields"),
+  t(50981, "ProfileLookTableDims", Image, LONG, Fixed(3), "Dimensions of the look table"),
+  t(50982, "ProfileLookTableData", Image, FLOAT, Any, "Default look table"),
+  t(51008, "OpcodeList1", Image, UNDEFINED, Any, "Opcodes applied to the raw image as read"),
+  t(51009, "OpcodeList2", Image, UNDEFINED, Any, "Opcodes applied after mapping to linear values"),
+  t(51022, "OpcodeList3", Image, UNDEFINED, Any, "Opcodes applied after demosaicing"),
+  t(51041, "NoiseProfile", Image, DOUBLE, Any, "Noise model parameters"),
+  t(51089, "OriginalDefaultFinalSize", Image, SHORT_LONG, Fixed(2), "Default final size of the original image"),
+  t(51090, "OriginalBestQualityFinalSize", Image, SHORT_LONG, Fixed(2), "Best quality final size of the original image"),
+  t(51091, "OriginalDefaultCropSize", Image, SHORT_LONG_RATIONAL, Fixed(2), "Default crop size of the original image"),
+  t(51107, "ProfileHueSatMapEncoding", Image, LONG, Fixed(1), "Encoding of the hue/saturation/value mapping"),
+  t(51108, "ProfileLookTableEncoding", Image, LONG, Fixed(1), "Encoding of the look table"),
+  t(51109, "BaselineExposureOffset", Image, SRATIONAL, Fixed(1), "Baseline exposure adjustment of the profile"),
+  t(51110, "DefaultBlackRender", Image, LONG, Fixed(1), "Preferred black rendering"),
+  t(51111, "NewRawImageDigest", Image, BYTE, Fixed(16), "MD5 digest of the raw image data, including opcodes"),
+  t(51112, "RawToPreviewGain", Image, DOUBLE, Fixed(1), "Gain between the raw data and the preview"),
+  t(51125, "DefaultUserCrop", Image, RATIONAL, Fixed(4), "Default user crop rectangle"),
+  t(51177, "DepthFormat", Image, SHORT, Fixed(1), "Encoding of depth data"),
+  t(51178, "DepthNear", Image, RATIONAL, Fixed(1), "Distance to the nearest depth value"),
+  t(51179, "DepthFar", Image, RATIONAL, Fixed(1), "Distance to the farthest depth value"),
+  t(51180, "DepthUnits", Image, SHORT, Fixed(1), "Unit of DepthNear and DepthFar"),
+  t(51181, "DepthMeasureType", Image, SHORT, Fixed(1), "How depth is measured"),
+  t(51182, "EnhanceParams", Image, ASCII, Any, "Parameters of an enhanced image"),
+  t(52525, "ProfileGainTableMap", Image, UNDEFINED, Any, "Spatially varying gain table map"),
+  t(52526, "SemanticName", Image, ASCII, Any, "Kind of semantic mask"),
+  t(52528, "SemanticInstanceID", Image, ASCII, Any, "Instance of the semantic mask"),
+  t(52529, "CalibrationIlluminant3", Image, SHORT, Fixed(1), "Illuminant of the third set of color calibration tags"),
+  t(52530, "CameraCalibration3", Image, SRATIONAL, Any, "Individual camera calibration for illuminant 3"),
+  t(52531, "ColorMatrix3", Image, SRATIONAL, Any, "XYZ to camera color space matrix for illuminant 3"),
+  t(52532, "ForwardMatrix3", Image, SRATIONAL, Any, "White balanced camera to XYZ D50 matrix for illuminant 3"),
+  t(52533, "IlluminantData1", Image, UNDEFINED, Any, "Spectral data of illuminant 1"),
+  t(52534, "IlluminantData2", Image, UNDEFINED, Any, "Spectral data of illuminant 2"),
+  t(52535, "IlluminantData3", Image, UNDEFINED, Any, "Spectral data of illuminant 3"),
+  t(52536, "MaskSubArea", Image, LONG, Fixed(4), "Area of the mask relative to the main image"),
+  t(52537, "ProfileHueSatMapData3", Image, FLOAT, Any, "Hue/saturation/value mapping for illuminant 3"),
+  t(52538, "ReductionMatrix3", Image, SRATIONAL, Any, "Dimensionality reduction matrix for illuminant 3"),
+  t(52543, "RGBTables", Image, UNDEFINED, Any, "Color lookup tables"),
+  t(52544, "ProfileGainTableMap2", Image, UNDEFINED, Any, "Spatially varying gain table map, version 2"),
+  t(52547, "ColumnInterleaveFactor", Image, SHORT_LONG, Fixed(1), "Number of interleaved columns"),
+  t(52548, "ImageSequenceInfo", Image, UNDEFINED, Any, "Information about the image sequence"),
+  t(52550, "ImageStats", Image, UNDEFINED, Any, "Image statistics"),
+  t(52551, "ProfileDynamicRange", Image, UNDEFINED, Any, "Dynamic range of the profile"),
+  t(52552, "ProfileGroupName", Image, ASCII, Any, "Group name of the profile"),
+  t(52553, "JXLDistance", Image, FLOAT, Fixed(1), "JPEG XL distance parameter"),
+  t(52554, "JXLEffort", Image, LONG, Fixed(1), "JPEG XL effort parameter"),
+  t(52555, "JXLDecodeSpeed", Image, LONG, Fixed(1), "JPEG XL decode speed parameter"),
+  /* Sony raw IFD */
+  t(28672, "SonyRawFileType", Image, SHORT, Fixed(1), "Sony raw compression type"),
+  t(28688, "SonyToneCurve", Image, SHORT, Fixed(4), "Knee points of the Sony tone curve"),
+  t(28721, "VignettingCorrection", Image, SSHORT, Fixed(1), "Whether vignetting correction is applied"),
+  t(28722, "VignettingCorrParams", Image, SSHORT, Any, "Vignetting correction parameters"),
+  t(28724, "ChromaticAberrationCorrection", Image, SSHORT, Fixed(1), "Whether chromatic aberration correction is applied"),
+  t(28725, "ChromaticAberrationCorrParams", Image, SSHORT, Any, "Chromatic aberration correction parameters"),
+  t(28726, "DistortionCorrection", Image, SSHORT, Fixed(1), "Whether distortion correction is applied"),
+  t(28727, "DistortionCorrParams", Image, SSHORT, Any, "Distortion correction parameters"),
+  t(29895, "SonyCropTopLeft", Image, LONG, Fixed(2), "Top left corner of the Sony crop"),
+  t(29896, "SonyCropSize", Image, LONG, Fixed(2), "Size of the Sony crop"),
+  /* Sony MakerNote */
+  t(0x0010, "CameraInfo", SonyMakerNote, UNDEFINED, Any, "Camera information"),
+  t(0x0020, "FocusInfo", SonyMakerNote, UNDEFINED, Any, "Focus information"),
+  t(0x0102, "Quality", SonyMakerNote, LONG, Fixed(1), "Image quality"),
+  t(0x0104, "FlashExposureComp", SonyMakerNote, SRATIONAL, Fixed(1), "Flash exposure compensation"),
+  t(0x0105, "Teleconverter", SonyMakerNote, LONG, Fixed(1), "Attached teleconverter"),
+  t(0x0112, "WhiteBalanceFineTune", SonyMakerNote, LONG, Fixed(1), "White balance fine tune"),
+  t(0x0114, "CameraSettings", SonyMakerNote, ANY, Any, "Camera settings"),
+  t(0x0115, "WhiteBalance", SonyMakerNote, LONG, Fixed(1), "White balance setting"),
+  t(0x0116, "ExtraInfo", SonyMakerNote, UNDEFINED, Any, "Extra information"),
+  t(0x0e00, "PrintIM", SonyMakerNote, UNDEFINED, Any, "Print image matching data"),
+  t(0x1000, "MultiBurstMode", SonyMakerNote, UNDEFINED, Fixed(1), "Multi burst mode"),
+  t(0x1001, "MultiBurstImageWidth", SonyMakerNote, SHORT, Fixed(1), "Multi burst image width"),
+  t(0x1002, "MultiBurstImageHeight", SonyMakerNote, SHORT, Fixed(1), "Multi burst image height"),
+  t(0x1003, "Panorama", SonyMakerNote, UNDEFINED, Any, "Panorama information"),
+  t(0x2001, "PreviewImage", SonyMakerNote, UNDEFINED, Any, "Embedded preview image"),
+  t(0x2002, "Rating", SonyMakerNote, LONG, Fixed(1), "Rating"),
+  t(0x2004, "Contrast", SonyMakerNote, ANY, Fixed(1), "Contrast setting"),
+  t(0x2005, "Saturation", SonyMakerNote, ANY, Fixed(1), "Saturation setting"),
+  t(0x2006, "Sharpness", SonyMakerNote, ANY, Fixed(1), "Sharpness setting"),
+  t(0x2007, "Brightness", SonyMakerNote, ANY, Fixed(1), "Brightness setting"),
+  t(0x2008, "LongExposureNoiseReduction", SonyMakerNote, LONG, Fixed(1), "Long exposure noise reduction"),
+  t(0x2009, "HighISONoiseReduction", SonyMakerNote, SHORT, Fixed(1), "High ISO noise reduction"),
+  t(0x200a, "HDR", SonyMakerNote, LONG, Fixed(1), "HDR setting"),
+  t(0x200b, "MultiFrameNoiseReduction", SonyMakerNote, LONG, Fixed(1), "Multi frame noise reduction"),
+  t(0x200e, "PictureEffect", SonyMakerNote, SHORT, Fixed(1), "Picture effect"),
+  t(0x200f, "SoftSkinEffect", SonyMakerNote, LONG, Fixed(1), "Soft skin effect"),
+  t(0x2011, "VignettingCorrection", SonyMakerNote, LONG, Fixed(1), "Vignetting correction setting"),
+  t(0x2012, "LateralChromaticAberration", SonyMakerNote, LONG, Fixed(1), "Lateral chromatic aberration correction setting"),
+  t(0x2013, "DistortionCorrectionSetting", SonyMakerNote, LONG, Fixed(1), "Distortion correction setting"),
+  t(0x2014, "WBShiftAB_GM", SonyMakerNote, ANY, Fixed(2), "White balance shift in amber-blue and green-magenta"),
+  t(0x2016, "AutoPortraitFramed", SonyMakerNote, SHORT, Fixed(1), "Whether auto portrait framing was used"),
+  t(0x2017, "FlashAction", SonyMakerNote, LONG, Fixed(1), "Flash action"),
+  t(0x201a, "ElectronicFrontCurtainShutter", SonyMakerNote, LONG, Fixed(1), "Electronic front curtain shutter"),
+  t(0x201b, "FocusMode", SonyMakerNote, BYTE, Fixed(1), "Focus mode"),
+  t(0x201c, "AFAreaModeSetting", SonyMakerNote, BYTE, Fixed(1), "AF area mode setting"),
+  t(0x201d, "FlexibleSpotPosition", SonyMakerNote, SHORT, Fixed(2), "Flexible spot position"),
+  t(0x201e, "AFPointSelected", SonyMakerNote, BYTE, Fixed(1), "Selected AF point"),
+  t(0x2020, "AFPointsUsed", SonyMakerNote, BYTE, Any, "AF points used"),
+  t(0x2021, "AFTracking", SonyMakerNote, BYTE, Fixed(1), "AF tracking"),
+  t(0x2022, "FocalPlaneAFPointsUsed", SonyMakerNote, BYTE, Any, "Focal plane AF points used"),
+  t(0x2023, "MultiFrameNREffect", SonyMakerNote, LONG, Fixed(1), "Multi frame noise reduction effect"),
+  t(0x2026, "WBShiftAB_GM_Precise", SonyMakerNote, SRATIONAL, Fixed(2), "Precise white balance shift"),
+  t(0x2027, "FocusLocation", SonyMakerNote, SHORT, Fixed(4), "Focus location"),
+  t(0x2028, "VariableLowPassFilter", SonyMakerNote, SHORT, Fixed(2), "Variable low pass filter"),
+  t(0x2029, "RAWFileType", SonyMakerNote, SHORT, Fixed(1), "Raw file type"),
+  t(0x202b, "PrioritySetInAWB", SonyMakerNote, BYTE, Fixed(1), "Priority set in auto white balance"),
+  t(0x202c, "MeteringMode2", SonyMakerNote, SHORT, Fixed(1), "Metering mode"),
+  t(0x202d, "ExposureStandardAdjustment", SonyMakerNote, SRATIONAL, Fixed(1), "Exposure standard adjustment"),
+  t(0x202e, "Quality", SonyMakerNote, SHORT, Fixed(2), "Image quality"),
+  t(0x202f, "PixelShiftInfo", SonyMakerNote, UNDEFINED, Any, "Pixel shift information"),
+  t(0x2031, "SerialNumber", SonyMakerNote, ASCII, Any, "Camera serial number"),
+  t(0x2032, "Shadows", SonyMakerNote, ANY, Fixed(1), "Shadows setting"),
+  t(0x2033, "Highlights", SonyMakerNote, ANY, Fixed(1), "Highlights setting"),
+  t(0x2034, "Fade", SonyMakerNote, ANY, Fixed(1), "Fade setting"),
+  t(0x2035, "SharpnessRange", SonyMakerNote, ANY, Fixed(1), "Sharpness range setting"),
+  t(0x2036, "Clarity", SonyMakerNote, ANY, Fixed(1), "Clarity setting"),
+  t(0x2037, "FocusFrameSize", SonyMakerNote, UNDEFINED, Any, "Focus frame size"),
+  t(0x2039, "JPEG-HEIFSwitch", SonyMakerNote, SHORT, Fixed(1), "JPEG or HEIF"),
+  t(0x3000, "ShotInfo", SonyMakerNote, UNDEFINED, Any, "Shot information"),
+  t(0x9050, "Tag9050", SonyMakerNote, UNDEFINED, Any, "Enciphered shot information"),
+  t(0x9400, "Tag9400", SonyMakerNote, UNDEFINED, Any, "Enciphered shot information"),
+  t(0x940e, "AFInfo", SonyMakerNote, UNDEFINED, Any, "Enciphered AF information"),
+  t(0xb000, "FileFormat", SonyMakerNote, BYTE, Fixed(4), "File format"),
+  t(0xb001, "SonyModelID", SonyMakerNote, SHORT, Fixed(1), "Sony model ID"),
+  t(0xb020, "CreativeStyle", SonyMakerNote, ASCII, Any, "Creative style"),
+  t(0xb021, "ColorTemperature", SonyMakerNote, LONG, Fixed(1), "Color temperature"),
+  t(0xb022, "ColorCompensationFilter", SonyMakerNote, LONG, Fixed(1), "Color compensation filter"),
+  t(0xb023, "SceneMode", SonyMakerNote, LONG, Fixed(1), "Scene mode"),
+  t(0xb024, "ZoneMatching", SonyMakerNote, LONG, Fixed(1), "Zone matching"),
+  t(0xb025, "DynamicRangeOptimizer", SonyMakerNote, LONG, Fixed(1), "Dynamic range optimizer"),
+  t(0xb026, "ImageStabilization", SonyMakerNote, LONG, Fixed(1), "Image stabilization"),
+  t(0xb027, "LensType", SonyMakerNote, LONG, Fixed(1), "Lens type"),
+  t(0xb028, "MinoltaMakerNote", SonyMakerNote, LONG, Fixed(1), "Offset to the Minolta maker note"),
+  t(0xb029, "ColorMode", SonyMakerNote, LONG, Fixed(1), "Color mode"),
+  t(0xb02a, "LensSpec", SonyMakerNote, BYTE, Fixed(8), "Lens specification"),
+  t(0xb02b, "FullImageSize", SonyMakerNote, LONG, Fixed(2), "Full image size"),
+  t(0xb02c, "PreviewImageSize", SonyMakerNote, LONG, Fixed(2), "Preview image size"),
+  t(0xb040, "Macro", SonyMakerNote, SHORT, Fixed(1), "Macro"),
+  t(0xb041, "ExposureMode", SonyMakerNote, SHORT, Fixed(1), "Exposure mode"),
+  t(0xb042, "FocusMode", SonyMakerNote, SHORT, Fixed(1), "Focus mode"),
+  t(0xb043, "AFAreaMode", SonyMakerNote, SHORT, Fixed(1), "AF area mode"),
+  t(0xb044, "AFIlluminator", SonyMakerNote, SHORT, Fixed(1), "AF illuminator"),
+  t(0xb047, "JPEGQuality", SonyMakerNote, SHORT, Fixed(1), "JPEG quality"),
+  t(0xb048, "FlashLevel", SonyMakerNote, SSHORT, Fixed(1), "Flash level"),
+  t(0xb049, "ReleaseMode", SonyMakerNote, SHORT, Fixed(1), "Release mode"),
+  t(0xb04a, "SequenceNumber", SonyMakerNote, SHORT, Fixed(1), "Sequence number"),
+  t(0xb04b, "Anti-Blur", SonyMakerNote, SHORT, Fixed(1), "Anti-blur"),
+  t(0xb04e, "FocusMode2", SonyMakerNote, SHORT, Fixed(1), "Focus mode"),
+  t(0xb04f, "DynamicRangeOptimizer2", SonyMakerNote, SHORT, Fixed(1), "Dynamic range optimizer"),
+  t(0xb050, "HighISONoiseReduction2", SonyMakerNote, SHORT, Fixed(1), "High ISO noise reduction"),
+  t(0xb052, "IntelligentAuto", SonyMakerNote, SHORT, Fixed(1), "Intelligent auto"),
+  t(0xb054, "WhiteBalance2", SonyMakerNote, SHORT, Fixed(1), "White balance"),
+  /* Sony SR2Private and SR2SubIFD */
+  t(0x7200, "SR2SubIFDOffset", SonySr2, LONG, Fixed(1), "Offset to the enciphered SR2SubIFD"),
+  t(0x7201, "SR2SubIFDLength", SonySr2, LONG, Fixed(1), "Length of the enciphered SR2SubIFD"),
+  t(0x7221, "SR2SubIFDKey", SonySr2, LONG, Fixed(1), "Key of the SR2SubIFD cipher"),
+  t(0x7240, "IDC_IFD", SonySr2, LONG, Fixed(1), "Offset to the IDC IFD"),
+  t(0x7241, "IDC2_IFD", SonySr2, LONG, Fixed(1), "Offset to the second IDC IFD"),
+  t(0x7250, "MRWInfo", SonySr2, UNDEFINED, Any, "Minolta raw information"),
+  t(0x7300, "BlackLevel", SonySr2, SHORT, Fixed(4), "Black level of each CFA channel"),
+  t(0x7302, "WB_GRBGLevelsAuto", SonySr2, SSHORT, Fixed(4), "Auto white balance multipliers, GRBG"),
+  t(0x7303, "WB_GRBGLevels", SonySr2, SSHORT, Fixed(4), "As shot white balance multipliers, GRBG"),
+  t(0x7310, "BlackLevel2", SonySr2, SHORT, Fixed(4), "Black level of each CFA channel, RGGB"),
+  t(0x7312, "WB_RGGBLevelsAuto", SonySr2, SSHORT, Fixed(4), "Auto white balance multipliers, RGGB"),
+  t(0x7313, "WB_RGGBLevels", SonySr2, SSHORT, Fixed(4), "As shot white balance multipliers, RGGB"),
+  t(0x7480, "WB_RGBLevelsDaylight", SonySr2, SSHORT, Fixed(4), "Daylight white balance multipliers"),
+  t(0x7481, "WB_RGBLevelsCloudy", SonySr2, SSHORT, Fixed(4), "Cloudy white balance multipliers"),
+  t(0x7482, "WB_RGBLevelsTungsten", SonySr2, SSHORT, Fixed(4), "Tungsten white balance multipliers"),
+  t(0x7483, "WB_RGBLevelsFlash", SonySr2, SSHORT, Fixed(4), "Flash white balance multipliers"),
+  t(0x7484, "WB_RGBLevels4500K", SonySr2, SSHORT, Fixed(4), "4500K white balance multipliers"),
+  t(0x7486, "WB_RGBLevelsFluorescent", SonySr2, SSHORT, Fixed(4), "Fluorescent white balance multipliers"),
+  t(0x74a0, "MaxApertureAtMaxFocal", SonySr2, RATIONAL, Fixed(1), "Maximum aperture at the longest focal length"),
+  t(0x74a1, "MaxApertureAtMinFocal", SonySr2, RATIONAL, Fixed(1), "Maximum aperture at the shortest focal length"),
+  t(0x74a2, "MaxFocalLength", SonySr2, RATIONAL, Fixed(1), "Longest focal length"),
+  t(0x74a3, "MinFocalLength", SonySr2, RATIONAL, Fixed(1), "Shortest focal length"),
+  t(0x74c0, "SR2DataIFD", SonySr2, LONG, Fixed(1), "Offset to the SR2 data IFD"),
+  t(0x7800, "ColorMatrix", SonySr2, SSHORT, Fixed(9), "Camera color matrix"),
+  t(0x7820, "WB_RGBLevelsDaylight", SonySr2, SSHORT, Fixed(3), "Daylight white balance multipliers"),
+  t(0x7821, "WB_RGBLevelsCloudy", SonySr2, SSHORT, Fixed(3), "Cloudy white balance multipliers"),
+  t(0x7822, "WB_RGBLevelsTungsten", SonySr2, SSHORT, Fixed(3), "Tungsten white balance multipliers"),
+  t(0x7823, "WB_RGBLevelsFlash", SonySr2, SSHORT, Fixed(3), "Flash white balance multipliers"),
+  t(0x7824, "WB_RGBLevels4500K", SonySr2, SSHORT, Fixed(3), "4500K white balance multipliers"),
+  t(0x7825, "WB_RGBLevelsShade", SonySr2, SSHORT, Fixed(3), "Shade white balance multipliers"),
+  t(0x7826, "WB_RGBLevelsFluorescent", SonySr2, SSHORT, Fixed(3), "Fluorescent white balance multipliers"),
+  t(0x787f, "WhiteLevel", SonySr2, SHORT, Fixed(3), "White level of each color"),
+  t(0x797d, "VignettingCorrParams", SonySr2, SSHORT, Any, "Vignetting correction parameters"),
+  t(0x7980, "ChromaticAberrationCorrParams", SonySr2, SSHORT, Any, "Chromatic aberration correction parameters"),
+  t(0x7982, "DistortionCorrParams", SonySr2, SSHORT, Any, "Distortion correction parameters"),
+];
+
+// Looks up `tag` among the tags of `ifd` only; IDs are reused across kinds of IFDs.
+pub fn lookup(ifd: IfdKind, tag: u16) -> Option<&'static TagInfo> {
+  TAGS.iter().find(|it| it.ifd == ifd && it.id == tag)
+}
+
+// Every definition of `tag`, whichever IFD it belongs to.
+pub fn lookup_all(tag: u16) -> impl Iterator<Item = &'static TagInfo> {
+  TAGS.iter().filter(move |it| it.id == tag)
+}
+
+// Case-insensitive.
+pub fn tag_id(name: &str) -> Option<u16> {
+  TAGS.iter().find(|it| it.name.eq_ignore_ascii_case(name)).map(|it| it.id)
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  #[test]
+  fn test_no_duplicates() {
+    for (i, a) in TAGS.iter().enumerate() {
+      for b in TAGS.iter().skip(i + 1) {
+        assert!(a.ifd != b.ifd || a.id != b.id, "Duplicated: {} and {}", a.name, b.name);
+      }
+    }
+  }
+
+  #[test]
+  fn test_lookup() {
+    assert_eq!(lookup(IfdKind::Image, 256).map(|it| it.name), Some("ImageWidth"));
+    assert_eq!(lookup(IfdKind::Gps, 1).map(|it| it.name), Some("GPSLatitudeRef"));
+    assert_eq!(lookup(IfdKind::Interop, 1).map(|it| it.name), Some("InteroperabilityIndex"));
+    // Only the IFD's own tags.
+    assert!(lookup(IfdKind::Exif, 256).is_none());
+    assert!(lookup(IfdKind::Gps, 33434).is_none());
+    // TIFF/EP records some Exif tags in IFD0.
+    assert_eq!(lookup(IfdKind::Image, 33434).map(|it| it.name), Some("ExposureTime"));
+    assert_eq!(lookup(IfdKind::Image, 37396).map(|it| it.name), Some("SubjectLocation"));
+    assert_eq!(lookup(IfdKind::SonyMakerNote, 0x0102).map(|it| it.name), Some("Quality"));
+    assert_eq!(lookup(IfdKind::SonySr2, 0x7221).map(|it| it.name), Some("SR2SubIFDKey"));
+    assert_eq!(tag_id("cfapattern"), Some(33422));
+  }
+}
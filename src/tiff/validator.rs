@@ -0,0 +1,692 @@
+/*
+Conformance checks against the specifications in doc/.
+
+- [TIFF] TIFF6.pdf
+- [TIFF/EP] N4378.pdf
+- [DNG] DNG_Spec_1_7_0_0.pdf
+*/
+
+use std::fmt::{Display, Formatter};
+use crate::stream::ByteStream;
+use crate::tiff::{Entry, EntryHeader, ImageFileDirectory, PhotometricInterpretation, Tiff};
+use crate::tiff::layout::{Issue, Layout};
+use crate::tiff::query::Value;
+use crate::tiff::tags::{self, Count, IfdKind};
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Ord, PartialOrd)]
+pub enum Severity {
+  Info,
+  Warning,
+  Error,
+}
+
+impl Severity {
+  // The least severe findings that fail validation.
+  pub fn threshold(strict: bool) -> Self {
+    if strict { Severity::Warning } else { Severity::Error }
+  }
+}
+
+impl Display for Severity {
+  fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    match self {
+      Severity::Info => write!(f, "info"),
+      Severity::Warning => write!(f, "warning"),
+      Severity::Error => write!(f, "error"),
+    }
+  }
+}
+
+#[derive(Clone, Debug)]
+pub struct Finding {
+  pub severity: Severity,
+  // IFD path, optionally followed by a tag name, e.g. `ifd0/subifd[0]/StripOffsets`.
+  pub location: String,
+  pub message: String,
+}
+
+impl Display for Finding {
+  fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    write!(f, "{}: {}: {}", self.severity, self.location, self.message)
+  }
+}
+
+// Which specification the file is checked against.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Profile {
+  Tiff,
+  TiffEp,
+  Dng,
+}
+
+impl Profile {
+  // DNG if DNGVersion is present, TIFF/EP if TIFF/EPStandardID is present, baseline TIFF otherwise.
+  pub fn detect(tiff: &Tiff) -> Self {
+    let Some(ifd0) = tiff.root_ifd() else {
+      return Profile::Tiff;
+    };
+    if ifd0.headers().iter().any(|it| it.tag == TAG_DNG_VERSION) {
+      Profile::Dng
+    } else if ifd0.headers().iter().any(|it| it.tag == TAG_TIFF_EP_STANDARD_ID) {
+      Profile::TiffEp
+    } else {
+      Profile::Tiff
+    }
+  }
+}
+
+const TAG_NEW_SUB_FILE_TYPE: u16 = 254;
+const TAG_IMAGE_WIDTH: u16 = 256;
+const TAG_IMAGE_LENGTH: u16 = 257;
+const TAG_BITS_PER_SAMPLE: u16 = 258;
+const TAG_COMPRESSION: u16 = 259;
+const TAG_PHOTOMETRIC_INTERPRETATION: u16 = 262;
+const TAG_ORIENTATION: u16 = 274;
+const TAG_SAMPLES_PER_PIXEL: u16 = 277;
+const TAG_X_RESOLUTION: u16 = 282;
+const TAG_Y_RESOLUTION: u16 = 283;
+const TAG_PLANAR_CONFIGURATION: u16 = 284;
+const TAG_RESOLUTION_UNIT: u16 = 296;
+const TAG_DATE_TIME: u16 = 306;
+const TAG_CFA_REPEAT_PATTERN_DIM: u16 = 33421;
+const TAG_CFA_PATTERN: u16 = 33422;
+const TAG_TIFF_EP_STANDARD_ID: u16 = 37398;
+const TAG_EXIF_VERSION: u16 = 36864;
+const TAG_GPS_VERSION_ID: u16 = 0;
+const TAG_DNG_VERSION: u16 = 50706;
+const TAG_DNG_BACKWARD_VERSION: u16 = 50707;
+const TAG_UNIQUE_CAMERA_MODEL: u16 = 50708;
+const TAG_COLOR_MATRIX1: u16 = 50721;
+
+const PHOTOMETRIC_LINEAR_RAW: u16 = 34892;
+
+pub struct Validator<'a> {
+  stream: &'a mut ByteStream,
+  tiff: &'a Tiff,
+  profile: Profile,
+  findings: Vec<Finding>,
+}
+
+impl <'a> Validator<'a> {
+  pub fn new(stream: &'a mut ByteStream, tiff: &'a Tiff) -> Self {
+    Self {
+      stream,
+      tiff,
+      profile: Profile::detect(tiff),
+      findings: Vec::new(),
+    }
+  }
+
+  pub fn with_profile(mut self, profile: Profile) -> Self {
+    self.profile = profile;
+    self
+  }
+
+  pub fn profile(&self) -> Profile {
+    self.profile
+  }
+
+  // Findings sorted from the most severe.
+  pub fn validate(mut self) -> anyhow::Result<Vec<Finding>> {
+    let tiff = self.tiff;
+    let mut dirs = Vec::<(String, &ImageFileDirectory)>::new();
+    tiff.walk_ifds(|path, dir| dirs.push((path.to_string(), dir)));
+    for (path, dir) in &dirs {
+      self.findings.extend(check_order(path, dir));
+      self.findings.extend(check_alignment(path, dir));
+      self.findings.extend(check_required(self.profile, path, dir));
+      self.findings.extend(check_blocks(path, dir));
+      self.findings.extend(check_cfa(path, dir));
+      self.check_types(path, dir)?;
+      self.check_values(path, dir)?;
+    }
+    if self.profile == Profile::Dng {
+      self.check_dng_versions(&dirs)?;
+    }
+    self.check_layout()?;
+    self.findings.sort_by_key(|it| std::cmp::Reverse(it.severity));
+    Ok(self.findings)
+  }
+
+  fn read(&mut self, header: &EntryHeader) -> anyhow::Result<Value> {
+    Ok(Value::read(self.stream, header)?)
+  }
+
+  fn read_u64s(&mut self, dir: &ImageFileDirectory, tag: u16) -> anyhow::Result<Option<Vec<u64>>> {
+    let Some(header) = find_header(dir, tag) else {
+      return Ok(None);
+    };
+    Ok(self.read(header)?.to_u64s())
+  }
+
+  fn push(&mut self, severity: Severity, location: String, message: String) {
+    self.findings.push(Finding {
+      severity,
+      location,
+      message,
+    });
+  }
+
+  // Types and counts against the tag registry, for the IFD's own tags only. A tag of another kind of
+  // IFD is unknown here, as its ID may mean something else in this IFD, but only worth a note.
+  fn check_types(&mut self, path: &str, dir: &ImageFileDirectory) -> anyhow::Result<()> {
+    let samples_per_pixel = self.read_u64s(dir, TAG_SAMPLES_PER_PIXEL)?
+      .and_then(|it| it.first().copied())
+      .unwrap_or(1);
+    for header in dir.headers() {
+      let Some(info) = tags::lookup(dir.kind(), header.tag) else {
+        let elsewhere: Vec<String> = tags::lookup_all(header.tag)
+          .map(|it| format!("{} in {:?} IFDs", it.name, it.ifd))
+          .collect();
+        if elsewhere.is_empty() {
+          self.push(Severity::Info, format!("{}/{}", path, header.tag), "Unknown tag".to_string());
+        } else {
+          self.push(
+            Severity::Info,
+            format!("{}/{}", path, header.tag),
+            format!("Unknown tag in a {:?} IFD, known as {}", dir.kind(), elsewhere.join(", ")));
+        }
+        continue;
+      };
+      let location = format!("{}/{}", path, info.name);
+      if !info.accepts(header.ty) {
+        self.push(
+          Severity::Error,
+          location.clone(),
+          format!("Type {:?} is not allowed, expected one of {:?}", header.ty, info.types));
+      }
+      let expected = match info.count {
+        Count::Any => None,
+        Count::Fixed(n) => Some(n as u64),
+        Count::PerSample => Some(samples_per_pixel),
+      };
+      if let Some(expected) = expected {
+        if header.count as u64 != expected {
+          self.push(
+            Severity::Error,
+            location,
+            format!("Count is {}, expected {}", header.count, expected));
+        }
+      }
+    }
+    Ok(())
+  }
+
+  fn check_values(&mut self, path: &str, dir: &ImageFileDirectory) -> anyhow::Result<()> {
+    if dir.kind() != IfdKind::Image {
+      return Ok(());
+    }
+    let ranges: [(u16, &str, u64, u64); 7] = [
+      (TAG_IMAGE_WIDTH, "ImageWidth", 1, u32::MAX as u64),
+      (TAG_IMAGE_LENGTH, "ImageLength", 1, u32::MAX as u64),
+      (TAG_BITS_PER_SAMPLE, "BitsPerSample", 1, 32),
+      (TAG_SAMPLES_PER_PIXEL, "SamplesPerPixel", 1, u16::MAX as u64),
+      (TAG_ORIENTATION, "Orientation", 1, 8),
+      (TAG_PLANAR_CONFIGURATION, "PlanarConfiguration", 1, 2),
+      (TAG_RESOLUTION_UNIT, "ResolutionUnit", 1, 3),
+    ];
+    for (tag, name, min, max) in ranges {
+      let Some(values) = self.read_u64s(dir, tag)? else {
+        continue;
+      };
+      for v in values.iter().filter(|it| **it < min || **it > max) {
+        self.push(
+          Severity::Error,
+          format!("{}/{}", path, name),
+          format!("Value {} is out of range [{}, {}]", v, min, max));
+      }
+    }
+    for (tag, name) in [(TAG_X_RESOLUTION, "XResolution"), (TAG_Y_RESOLUTION, "YResolution")] {
+      let Some(header) = find_header(dir, tag) else {
+        continue;
+      };
+      if let Value::Rational(vs) = self.read(header)? {
+        if vs.iter().any(|it| it.denominator == 0) {
+          self.push(Severity::Error, format!("{}/{}", path, name), "Denominator is zero".to_string());
+        }
+      }
+    }
+    if let Some(compression) = dir.compression() {
+      let code = compression.code();
+      // [TIFF] p.30, [TIFF/EP] p.30, [DNG] p.20
+      if ![1, 2, 3, 4, 5, 6, 7, 8, 32773, 32946, 34892, 52546, 32767].contains(&code) {
+        self.push(
+          Severity::Warning,
+          format!("{}/Compression", path),
+          format!("Unknown compression scheme {}", code));
+      }
+    }
+    if let Some(Entry::PhotometricInterpretation(PhotometricInterpretation::Undefined(code))) =
+      dir.find(|it| match it {
+        Entry::PhotometricInterpretation(_) => Some(it),
+        _ => None,
+      }) {
+      if ![0, 3, 4, 5, 8, 9, 10, PHOTOMETRIC_LINEAR_RAW].contains(code) {
+        self.push(
+          Severity::Warning,
+          format!("{}/PhotometricInterpretation", path),
+          format!("Unknown photometric interpretation {}", code));
+      }
+    }
+    Ok(())
+  }
+
+  // [DNG] Compatibility Issues: compression schemes added after DNG 1.0 need DNGBackwardVersion raised.
+  fn check_dng_versions(&mut self, dirs: &[(String, &ImageFileDirectory)]) -> anyhow::Result<()> {
+    let Some(ifd0) = self.tiff.root_ifd() else {
+      return Ok(());
+    };
+    // A missing DNGVersion is already reported by check_required.
+    let version = self.read_u64s(ifd0, TAG_DNG_VERSION)?.unwrap_or_default();
+    let backward = self.read_u64s(ifd0, TAG_DNG_BACKWARD_VERSION)?
+      .unwrap_or_else(|| vec![1, 0, 0, 0]);
+    if !version.is_empty() && backward > version {
+      self.push(
+        Severity::Error,
+        "ifd0/DNGBackwardVersion".to_string(),
+        format!("{:?} is newer than DNGVersion {:?}", backward, version));
+    }
+    for (path, dir) in dirs {
+      let Some(compression) = dir.compression() else {
+        continue;
+      };
+      let required: Option<[u64; 4]> = match compression.code() {
+        8 | 34892 => Some([1, 4, 0, 0]),
+        52546 => Some([1, 7, 0, 0]),
+        _ => None,
+      };
+      if let Some(required) = required {
+        if backward.as_slice() < required.as_slice() {
+          self.push(
+            Severity::Error,
+            format!("{}/Compression", path),
+            format!(
+              "Compression {} requires DNGBackwardVersion >= {:?}, but it is {:?}",
+              compression.code(), required, backward));
+        }
+      }
+    }
+    Ok(())
+  }
+
+  fn check_layout(&mut self) -> anyhow::Result<()> {
+    let layout = Layout::analyze(self.stream, self.tiff)?;
+    for issue in &layout.issues {
+      match issue {
+        Issue::PastEof(i) => {
+          let r = &layout.regions[*i];
+          self.push(
+            Severity::Error,
+            r.label.clone(),
+            format!("[{}, {}) extends past the end of file ({} bytes)", r.start, r.end, layout.file_size));
+        }
+        Issue::Overlap(a, b) => {
+          let (a, b) = (&layout.regions[*a], &layout.regions[*b]);
+          self.push(
+            Severity::Warning,
+            a.label.clone(),
+            format!("[{}, {}) overlaps {} [{}, {})", a.start, a.end, b.label, b.start, b.end));
+        }
+        Issue::Gap { .. } => {}
+      }
+    }
+    Ok(())
+  }
+}
+
+fn find_header(dir: &ImageFileDirectory, tag: u16) -> Option<&EntryHeader> {
+  dir.headers().iter().find(|it| it.tag == tag)
+}
+
+fn has_tag(dir: &ImageFileDirectory, tag: u16) -> bool {
+  find_header(dir, tag).is_some()
+}
+
+fn tag_name(dir: &ImageFileDirectory, tag: u16) -> String {
+  match tags::lookup(dir.kind(), tag) {
+    Some(info) => info.name.to_string(),
+    None => format!("{}", tag),
+  }
+}
+
+fn finding(severity: Severity, location: String, message: String) -> Finding {
+  Finding {
+    severity,
+    location,
+    message,
+  }
+}
+
+// [TIFF] p.15: The entries in an IFD must be sorted in ascending order by Tag.
+fn check_order(path: &str, dir: &ImageFileDirectory) -> Vec<Finding> {
+  let mut findings = Vec::<Finding>::new();
+  for pair in dir.headers().windows(2) {
+    let (prev, next) = (&pair[0], &pair[1]);
+    if prev.tag == next.tag {
+      findings.push(finding(
+        Severity::Error,
+        format!("{}/{}", path, tag_name(dir, next.tag)),
+        "Duplicated tag".to_string()));
+    } else if prev.tag > next.tag {
+      findings.push(finding(
+        Severity::Error,
+        format!("{}/{}", path, tag_name(dir, next.tag)),
+        format!("Tag {} follows {}; tags must be in ascending order", next.tag, prev.tag)));
+    }
+  }
+  findings
+}
+
+// [TIFF] p.13-15: IFDs and values must begin on a word boundary.
+fn check_alignment(path: &str, dir: &ImageFileDirectory) -> Vec<Finding> {
+  let mut findings = Vec::<Finding>::new();
+  if !dir.offset().is_multiple_of(2) {
+    findings.push(finding(
+      Severity::Error,
+      path.to_string(),
+      format!("IFD offset {} is not word-aligned", dir.offset())));
+  }
+  for header in dir.headers().iter().filter(|it| !it.is_value_inline()) {
+    if !header.value_offset().is_multiple_of(2) {
+      findings.push(finding(
+        Severity::Warning,
+        format!("{}/{}", path, tag_name(dir, header.tag)),
+        format!("Value offset {} is not word-aligned", header.value_offset())));
+    }
+  }
+  findings
+}
+
+fn check_required(profile: Profile, path: &str, dir: &ImageFileDirectory) -> Vec<Finding> {
+  let mut required = Vec::<(u16, Severity)>::new();
+  match dir.kind() {
+    IfdKind::Image => {
+      let has_image = has_tag(dir, TAG_IMAGE_WIDTH) ||
+        dir.strip_byte_offsets().is_some() ||
+        dir.tile_offsets().is_some();
+      if has_image {
+        // [TIFF] Section 8: Baseline Field Reference Guide
+        required.extend([
+          (TAG_IMAGE_WIDTH, Severity::Error),
+          (TAG_IMAGE_LENGTH, Severity::Error),
+          (TAG_PHOTOMETRIC_INTERPRETATION, Severity::Error),
+          (TAG_X_RESOLUTION, Severity::Warning),
+          (TAG_Y_RESOLUTION, Severity::Warning),
+        ]);
+        if profile != Profile::Tiff {
+          // [TIFF/EP] Table 2: mandatory in every image IFD
+          required.extend([
+            (TAG_NEW_SUB_FILE_TYPE, Severity::Error),
+            (TAG_BITS_PER_SAMPLE, Severity::Error),
+            (TAG_COMPRESSION, Severity::Error),
+            (TAG_SAMPLES_PER_PIXEL, Severity::Error),
+            (TAG_PLANAR_CONFIGURATION, Severity::Error),
+            (TAG_RESOLUTION_UNIT, Severity::Error),
+          ]);
+        }
+        if let Some(Entry::PhotometricInterpretation(PhotometricInterpretation::ColorFilterArray)) =
+          dir.find(|it| match it {
+            Entry::PhotometricInterpretation(_) => Some(it),
+            _ => None,
+          }) {
+          required.extend([
+            (TAG_CFA_REPEAT_PATTERN_DIM, Severity::Error),
+            (TAG_CFA_PATTERN, Severity::Error),
+          ]);
+        }
+      }
+      // Only IFD0 carries file-wide tags.
+      if path == "ifd0" {
+        match profile {
+          Profile::Tiff => {}
+          Profile::TiffEp => required.extend([
+            (TAG_DATE_TIME, Severity::Error),
+            (TAG_TIFF_EP_STANDARD_ID, Severity::Error),
+          ]),
+          // [DNG] Chapter 4: DNGVersion and UniqueCameraModel are required, ColorMatrix1 for color cameras.
+          Profile::Dng => required.extend([
+            (TAG_DNG_VERSION, Severity::Error),
+            (TAG_UNIQUE_CAMERA_MODEL, Severity::Error),
+            (TAG_COLOR_MATRIX1, Severity::Error),
+          ]),
+        }
+      }
+    }
+    IfdKind::Exif => required.push((TAG_EXIF_VERSION, Severity::Warning)),
+    IfdKind::Gps => required.push((TAG_GPS_VERSION_ID, Severity::Warning)),
+    _ => {}
+  }
+  let mut findings = Vec::<Finding>::new();
+  for (tag, severity) in required {
+    if !has_tag(dir, tag) {
+      findings.push(finding(severity, path.to_string(), format!("Missing required tag {}", tag_name(dir, tag))));
+    }
+  }
+  let has_strips = dir.strip_byte_offsets().is_some();
+  let has_tiles = dir.tile_offsets().is_some();
+  if has_tag(dir, TAG_IMAGE_WIDTH) && dir.kind() == IfdKind::Image && !has_strips && !has_tiles {
+    findings.push(finding(
+      Severity::Error,
+      path.to_string(),
+      "Neither StripOffsets nor TileOffsets is present".to_string()));
+  }
+  if has_strips && has_tiles {
+    findings.push(finding(
+      Severity::Error,
+      path.to_string(),
+      "Both strips and tiles are present".to_string()));
+  }
+  findings
+}
+
+// [TIFF] p.39, p.68: one byte count per offset, and one strip/tile per RowsPerStrip rows or per tile.
+fn check_blocks(path: &str, dir: &ImageFileDirectory) -> Vec<Finding> {
+  let mut findings = Vec::<Finding>::new();
+  let planes = match dir.find(|it| match it {
+    Entry::PlanarConfiguration(crate::tiff::PlanarConfiguration::Planar) => Some(()),
+    _ => None,
+  }) {
+    Some(_) => dir.find(|it| match it {
+      Entry::SamplesPerPixel(n) => Some(*n as u64),
+      _ => None,
+    }).unwrap_or(1),
+    None => 1,
+  };
+  if let Some(offsets) = dir.strip_byte_offsets() {
+    match dir.strip_byte_counts() {
+      None => findings.push(finding(
+        Severity::Error,
+        path.to_string(),
+        "StripOffsets without StripByteCounts".to_string())),
+      Some(counts) if counts.len() != offsets.len() => findings.push(finding(
+        Severity::Error,
+        format!("{}/StripByteCounts", path),
+        format!("{} byte counts for {} strips", counts.len(), offsets.len()))),
+      _ => {}
+    }
+    if let Some(height) = dir.image_height() {
+      let rows = dir.rows_per_strip().unwrap_or(u32::MAX).max(1) as u64;
+      let expected = (height as u64).div_ceil(rows) * planes;
+      if offsets.len() as u64 != expected {
+        findings.push(finding(
+          Severity::Error,
+          format!("{}/StripOffsets", path),
+          format!("{} strips, expected {} from ImageLength and RowsPerStrip", offsets.len(), expected)));
+      }
+    }
+  }
+  if let Some(offsets) = dir.tile_offsets() {
+    match dir.tile_byte_counts() {
+      None => findings.push(finding(
+        Severity::Error,
+        path.to_string(),
+        "TileOffsets without TileByteCounts".to_string())),
+      Some(counts) if counts.len() != offsets.len() => findings.push(finding(
+        Severity::Error,
+        format!("{}/TileByteCounts", path),
+        format!("{} byte counts for {} tiles", counts.len(), offsets.len()))),
+      _ => {}
+    }
+    if let (Some(w), Some(h), Some(tw), Some(th)) =
+      (dir.image_width(), dir.image_height(), dir.tile_width(), dir.tile_length()) {
+      if tw == 0 || th == 0 || tw % 16 != 0 || th % 16 != 0 {
+        findings.push(finding(
+          Severity::Error,
+          format!("{}/TileWidth", path),
+          format!("Tile size {}x{} must be a non-zero multiple of 16", tw, th)));
+      } else {
+        let expected = (w as u64).div_ceil(tw as u64) * (h as u64).div_ceil(th as u64) * planes;
+        if offsets.len() as u64 != expected {
+          findings.push(finding(
+            Severity::Error,
+            format!("{}/TileOffsets", path),
+            format!("{} tiles, expected {}", offsets.len(), expected)));
+        }
+      }
+    }
+  }
+  findings
+}
+
+// [TIFF/EP] p.26: CFAPattern holds CFARepeatPatternDim rows * cols values from 0 (red) to 6 (white).
+fn check_cfa(path: &str, dir: &ImageFileDirectory) -> Vec<Finding> {
+  let mut findings = Vec::<Finding>::new();
+  let dim = dir.cfa_pattern_dim();
+  let pattern = dir.cfa_pattern();
+  match (dim, pattern) {
+    (Some(dim), Some(pattern)) => {
+      if dim.width * dim.height != pattern.len() {
+        findings.push(finding(
+          Severity::Error,
+          format!("{}/CFAPattern", path),
+          format!(
+            "{} values, but CFARepeatPatternDim is {}x{}",
+            pattern.len(), dim.height, dim.width)));
+      }
+      for v in pattern {
+        if let crate::tiff::CFAPattern::Unknown(n) = v {
+          if *n > 6 {
+            findings.push(finding(
+              Severity::Error,
+              format!("{}/CFAPattern", path),
+              format!("Color {} is out of range [0, 6]", n)));
+          }
+        }
+      }
+    }
+    (Some(_), None) => findings.push(finding(
+      Severity::Error,
+      path.to_string(),
+      "CFARepeatPatternDim without CFAPattern".to_string())),
+    (None, Some(_)) => findings.push(finding(
+      Severity::Error,
+      path.to_string(),
+      "CFAPattern without CFARepeatPatternDim".to_string())),
+    (None, None) => {}
+  }
+  findings
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use crate::tiff::{CFAPattern, DataType, Parser};
+  use crate::tiff::builder::{entry, long, pointer, short, TiffBuilder};
+
+  fn dir(entries: Vec<(u16, Entry)>) -> ImageFileDirectory {
+    let headers = entries.iter().enumerate().map(|(i, (tag, _))| EntryHeader {
+      offset: 10 + 12 * i as u64,
+      tag: *tag,
+      ty: DataType::U16,
+      count: 1,
+      data: 0,
+    }).collect();
+    ImageFileDirectory {
+      kind: IfdKind::Image,
+      offset: 8,
+      entries: entries.into_iter().map(|(_, e)| e).collect(),
+      headers,
+    }
+  }
+
+  #[test]
+  fn test_order() {
+    let d = dir(vec![(257, Entry::ImageLength(1)), (256, Entry::ImageWidth(1)), (256, Entry::ImageWidth(1))]);
+    let findings = check_order("ifd0", &d);
+    assert_eq!(findings.len(), 2);
+    assert!(findings.iter().all(|it| it.severity == Severity::Error));
+  }
+
+  #[test]
+  fn test_blocks() {
+    let d = dir(vec![
+      (257, Entry::ImageLength(100)),
+      (273, Entry::StripOffsets(vec![0, 10, 20])),
+      (278, Entry::RowsPerStrip(50)),
+      (279, Entry::StripByteCounts(vec![10, 10])),
+    ]);
+    let findings = check_blocks("ifd0", &d);
+    assert_eq!(findings.len(), 2, "{:?}", findings);
+  }
+
+  #[test]
+  fn test_cfa() {
+    let d = dir(vec![
+      (33421, Entry::CFARepeatPatternDim { rows: 2, cols: 2 }),
+      (33422, Entry::CFAPattern(vec![CFAPattern::R, CFAPattern::G, CFAPattern::B])),
+    ]);
+    assert_eq!(check_cfa("ifd0", &d).len(), 1);
+  }
+
+  #[test]
+  fn test_types() {
+    let mut stream = ByteStream::from_bytes(b"II\x2a\x00\x08\x00\x00\x00").unwrap();
+    let tiff = Tiff { directories: vec![] };
+    let mut check = |kind: IfdKind, tag: u16, ty: DataType, count: u32| {
+      let mut d = dir(vec![(tag, Entry::Unknown(tag, ty, count, 0))]);
+      d.kind = kind;
+      d.headers[0].ty = ty;
+      d.headers[0].count = count;
+      let mut validator = Validator::new(&mut stream, &tiff);
+      validator.check_types("ifd0", &d).unwrap();
+      validator.findings.iter().map(|it| it.severity).collect::<Vec<_>>()
+    };
+    // Tag 2 is GPSLatitude in GPS IFDs and InteroperabilityVersion in Interop IFDs.
+    assert_eq!(check(IfdKind::Gps, 2, DataType::Rational, 3), vec![]);
+    assert_eq!(check(IfdKind::Interop, 2, DataType::Rational, 3), vec![Severity::Error, Severity::Error]);
+    assert_eq!(check(IfdKind::Interop, 2, DataType::Blob, 4), vec![]);
+    // ImageWidth is not checked as such in an Exif IFD.
+    assert_eq!(check(IfdKind::Exif, 256, DataType::Rational, 2), vec![Severity::Info]);
+    assert_eq!(check(IfdKind::Exif, 65000, DataType::U8, 1), vec![Severity::Info]);
+  }
+
+  #[test]
+  fn test_strict_tiff_ep_exposure() {
+    let mut builder = TiffBuilder::new();
+    let strip = builder.blob(&[0; 8]);
+    let exposure = builder.blob(&[1, 0, 0, 0, 100, 0, 0, 0]);
+    let ifd0 = builder.ifd(vec![
+      short(256, &[4]),
+      short(257, &[2]),
+      short(258, &[8]),
+      short(259, &[1]),
+      short(262, &[32803]),
+      long(273, &[strip]),
+      short(277, &[1]),
+      long(279, &[8]),
+      short(33421, &[2, 2]),
+      entry(33422, DataType::U8, 4, vec![0, 1, 1, 2]),
+      pointer(33434, DataType::Rational, 1, exposure),
+      entry(37398, DataType::U8, 4, vec![1, 0, 0, 0]),
+    ], 0);
+    let mut stream = ByteStream::from_bytes(builder.build(ifd0)).unwrap();
+    let tiff = Parser::new(&mut stream).parse().unwrap();
+    let findings = Validator::new(&mut stream, &tiff).with_profile(Profile::TiffEp).validate().unwrap();
+    let rejected: Vec<&Finding> = findings.iter()
+      .filter(|it| it.severity >= Severity::threshold(true))
+      .filter(|it| it.location.ends_with("/ExposureTime") || it.location.ends_with("/33434"))
+      .collect();
+    assert!(rejected.is_empty(), "{:?}", rejected);
+    assert!(!findings.iter().any(|it| it.location == "ifd0/33434"), "{:?}", findings);
+  }
+}